@@ -0,0 +1,621 @@
+use crate::image;
+use crate::render;
+use crate::sampler::SamplerKind;
+use std::fmt;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: raytracing-in-a-weekend [render] [OPTIONS]
+       raytracing-in-a-weekend scenes
+       raytracing-in-a-weekend help
+
+Commands:
+    render      Render a scene to an image file (default)
+    scenes      List the built-in scenes
+    help        Print this message
+
+Render options:
+    -W, --width <PIXELS>          Image width [default: 2000]
+    -H, --height <PIXELS>         Image height [default: 1000]
+    -r, --resolution <WxH>        Image width and height at once, e.g. 800x400
+    -s, --samples <N>             Samples per pixel [default: 50]
+    -p, --progressive <N>         Render in passes of N samples per pixel, writing the
+                                  image so far to the output file after each one
+        --adaptive <ERROR>        Keep sampling each pixel, up to --samples, only until its
+                                  estimated relative error is below ERROR, e.g. 0.02
+        --min-samples <N>         Samples every pixel gets before adaptive sampling decides
+                                  whether it needs more [default: 16, or --samples if lower]
+        --sample-heatmap <PATH>   Also write an image of how many samples each pixel got
+        --sampler <KIND>          Where the random numbers of every sample come from
+                                  [default: sobol]
+                                  [possible values: independent, stratified, halton, sobol]
+        --filter <FILTER>         How samples are weighted into the pixels around them
+                                  [default: box] [possible values: box, tent, gaussian,
+                                  mitchell, lanczos]
+        --filter-radius <PIXELS>  How far samples reach [default: 0.5 for box, 1 for tent,
+                                  1.5 for gaussian, 2 for mitchell, 3 for lanczos]
+    -d, --max-depth <N>           Maximum number of bounces per ray [default: 50]
+    -j, --threads <N>             Number of render threads [default: number of cores]
+        --tile-size <PIXELS>      Width and height of the tiles handed to threads [default: 16]
+    -o, --output <PATH>           Output file [default: out/random_spheres_concurrent.ppm]
+    -f, --format <FORMAT>         Output format, inferred from the output extension
+                                  when omitted [possible values: ppm, png, hdr, exr]
+        --exr-compression <KIND>  EXR compression [default: zip] [possible values: none, zip]
+        --exr-precision <TYPE>    EXR channel type [default: half] [possible values: half, float]
+        --exposure <STOPS>        Exposure adjustment for PPM and PNG output [default: 0]
+        --tone-map <OPERATOR>     Tone mapping for PPM and PNG output [default: clamp]
+                                  [possible values: clamp, reinhard, extended-reinhard, aces]
+        --white-point <L>         Luminance mapped to white by extended-reinhard [default: 4]
+        --gamma <G>               Use a plain gamma curve instead of the sRGB transfer function
+        --scene <NAME|FILE>       Built-in scene, scene file or model (OBJ, PLY, STL)
+                                  [default: random]
+        --seed <N>                Seed for every random decision of the render, picked
+                                  at random (and printed) when omitted
+";
+
+pub enum Command {
+    Render(Box<RenderOptions>),
+    ListScenes,
+    Help,
+}
+
+#[derive(Debug)]
+pub struct RenderOptions {
+    pub width: usize,
+    pub height: usize,
+    pub samples: usize,
+    pub progressive: Option<usize>,
+    pub adaptive: Option<render::Adaptive>,
+    pub sample_heatmap: Option<(PathBuf, image::Format)>,
+    pub sampler: SamplerKind,
+    pub filter: render::Filter,
+    pub max_depth: usize,
+    pub threads: usize,
+    pub tile_size: usize,
+    pub output: PathBuf,
+    pub format: image::Format,
+    pub display: image::DisplayTransform,
+    pub scene: SceneSource,
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SceneSource {
+    Builtin(String),
+    File(PathBuf),
+}
+
+pub const SCENES: &[(&str, &str)] = &[(
+    "random",
+    "The book's final scene: three big spheres among plenty of small random ones",
+)];
+
+#[derive(Debug, PartialEq)]
+pub enum CliError {
+    UnknownCommand(String),
+    UnknownFlag(String),
+    MissingValue(String),
+    InvalidValue {
+        flag: String,
+        value: String,
+        reason: String,
+    },
+    Conflict(String),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CliError::UnknownCommand(command) => write!(f, "unknown command `{}`", command),
+            CliError::UnknownFlag(flag) => write!(f, "unknown option `{}`", flag),
+            CliError::MissingValue(flag) => write!(f, "option `{}` requires a value", flag),
+            CliError::InvalidValue {
+                flag,
+                value,
+                reason,
+            } => {
+                write!(f, "invalid value `{}` for `{}`: {}", value, flag, reason)
+            }
+            CliError::Conflict(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Command, CliError> {
+    let mut args = args.into_iter().peekable();
+
+    let command = match args.peek().map(String::as_str) {
+        Some("render") => {
+            args.next();
+            "render"
+        }
+        Some("scenes") => "scenes",
+        Some("help") | Some("-h") | Some("--help") => "help",
+        Some(arg) if !arg.starts_with('-') => {
+            return Err(CliError::UnknownCommand(arg.to_string()))
+        }
+        _ => "render",
+    };
+
+    match command {
+        "scenes" | "help" => {
+            args.next();
+
+            if let Some(extra) = args.next() {
+                return Err(CliError::UnknownFlag(extra));
+            }
+
+            Ok(if command == "scenes" {
+                Command::ListScenes
+            } else {
+                Command::Help
+            })
+        }
+        _ => parse_render(args),
+    }
+}
+
+fn parse_render<I: Iterator<Item = String>>(mut args: I) -> Result<Command, CliError> {
+    let mut width = None;
+    let mut height = None;
+    let mut resolution = None;
+    let mut samples = 50;
+    let mut progressive = None;
+    let mut adaptive_threshold = None;
+    let mut min_samples = None;
+    let mut sample_heatmap = None;
+    let mut sampler = SamplerKind::Sobol;
+    let mut filter = render::FilterKind::Box;
+    let mut filter_radius = None;
+    let mut max_depth = 50;
+    let mut threads = None;
+    let mut tile_size = 16;
+    let mut output = None;
+    let mut format = None;
+    let mut scene = SceneSource::Builtin(String::from("random"));
+    let mut seed = None;
+    let mut exr_compression = None;
+    let mut exr_precision = None;
+    let mut exposure = 0.0;
+    let mut tone_map = image::ToneMap::Clamp;
+    let mut white_point = None;
+    let mut gamma = None;
+
+    while let Some(arg) = args.next() {
+        if arg == "-h" || arg == "--help" {
+            return Ok(Command::Help);
+        }
+
+        // Both `--flag value` and `--flag=value` are accepted
+        let (flag, inline_value) = match arg.find('=') {
+            Some(index) if arg.starts_with("--") => {
+                (arg[..index].to_string(), Some(arg[index + 1..].to_string()))
+            }
+            _ => (arg, None),
+        };
+
+        let mut value = || match inline_value.clone() {
+            Some(value) => Ok(value),
+            None => args
+                .next()
+                .ok_or_else(|| CliError::MissingValue(flag.clone())),
+        };
+
+        match flag.as_str() {
+            "-W" | "--width" => width = Some(positive(&flag, &value()?)?),
+            "-H" | "--height" => height = Some(positive(&flag, &value()?)?),
+            "-r" | "--resolution" => resolution = Some(parse_resolution(&flag, &value()?)?),
+            "-s" | "--samples" => samples = positive(&flag, &value()?)?,
+            "-p" | "--progressive" => progressive = Some(positive(&flag, &value()?)?),
+            "--adaptive" => adaptive_threshold = Some(positive_number(&flag, &value()?)?),
+            "--min-samples" => min_samples = Some(positive(&flag, &value()?)?),
+            "--sample-heatmap" => sample_heatmap = Some(PathBuf::from(value()?)),
+            "--sampler" => {
+                sampler = named(&flag, &value()?, SamplerKind::from_name, SamplerKind::NAMES)?
+            }
+            "--filter" => {
+                filter = named(
+                    &flag,
+                    &value()?,
+                    render::FilterKind::from_name,
+                    render::FilterKind::NAMES,
+                )?
+            }
+            "--filter-radius" => filter_radius = Some(positive_number(&flag, &value()?)?),
+            "-d" | "--max-depth" => max_depth = positive(&flag, &value()?)?,
+            "-j" | "--threads" => threads = Some(positive(&flag, &value()?)?),
+            "--tile-size" => tile_size = positive(&flag, &value()?)?,
+            "-o" | "--output" => output = Some(PathBuf::from(value()?)),
+            "-f" | "--format" => {
+                format = Some(named(
+                    &flag,
+                    &value()?,
+                    image::Format::from_name,
+                    image::Format::NAMES,
+                )?)
+            }
+            "--exr-compression" => {
+                exr_compression = Some(named(
+                    &flag,
+                    &value()?,
+                    image::ExrCompression::from_name,
+                    &["none", "zip"],
+                )?)
+            }
+            "--exr-precision" => {
+                exr_precision = Some(named(
+                    &flag,
+                    &value()?,
+                    image::ExrPrecision::from_name,
+                    &["half", "float"],
+                )?)
+            }
+            "--exposure" => exposure = number(&flag, &value()?)?,
+            "--tone-map" => {
+                tone_map = named(
+                    &flag,
+                    &value()?,
+                    image::ToneMap::from_name,
+                    image::ToneMap::NAMES,
+                )?
+            }
+            "--white-point" => white_point = Some(positive_number(&flag, &value()?)?),
+            "--gamma" => gamma = Some(positive_number(&flag, &value()?)?),
+            "--scene" => {
+                let name = value()?;
+
+                scene = if SCENES.iter().any(|(scene, _)| *scene == name) {
+                    SceneSource::Builtin(name)
+                } else if name.contains(std::path::is_separator) || name.contains('.') {
+                    SceneSource::File(PathBuf::from(name))
+                } else {
+                    return Err(CliError::InvalidValue {
+                        flag: flag.clone(),
+                        value: name,
+                        reason: String::from(
+                            "not a built-in scene (run the `scenes` command to list them) nor a file path",
+                        ),
+                    });
+                };
+            }
+            "--seed" => {
+                let raw = value()?;
+                seed = Some(raw.parse().map_err(|_| CliError::InvalidValue {
+                    flag: flag.clone(),
+                    value: raw.clone(),
+                    reason: String::from("expected a non-negative integer"),
+                })?);
+            }
+            _ => return Err(CliError::UnknownFlag(flag)),
+        }
+    }
+
+    let (width, height) = match (resolution, width, height) {
+        (Some(_), Some(_), _) | (Some(_), _, Some(_)) => {
+            return Err(CliError::Conflict(String::from(
+                "`--resolution` cannot be combined with `--width` or `--height`",
+            )))
+        }
+        (Some(resolution), None, None) => resolution,
+        (None, width, height) => (width.unwrap_or(2000), height.unwrap_or(1000)),
+    };
+
+    let output = output.unwrap_or_else(|| PathBuf::from("out/random_spheres_concurrent.ppm"));
+
+    let mut format = match (format, image::Format::from_path(&output)) {
+        (Some(format), Some(inferred)) if !format.same_kind(inferred) => {
+            return Err(CliError::Conflict(format!(
+                "output file `{}` does not match the requested format `{}`",
+                output.display(),
+                format.name()
+            )))
+        }
+        (Some(format), _) => format,
+        (None, Some(inferred)) => inferred,
+        (None, None) => {
+            return Err(CliError::Conflict(format!(
+                "cannot infer the output format of `{}`, use `--format`",
+                output.display()
+            )))
+        }
+    };
+
+    match &mut format {
+        image::Format::Exr(options) => {
+            options.compression = exr_compression.unwrap_or(options.compression);
+            options.precision = exr_precision.unwrap_or(options.precision);
+        }
+        _ if exr_compression.is_some() || exr_precision.is_some() => {
+            return Err(CliError::Conflict(String::from(
+                "`--exr-compression` and `--exr-precision` only apply to EXR output",
+            )))
+        }
+        _ => {}
+    }
+
+    match (&mut tone_map, white_point) {
+        (image::ToneMap::ExtendedReinhard { white }, Some(white_point)) => *white = white_point,
+        (_, Some(_)) => {
+            return Err(CliError::Conflict(String::from(
+                "`--white-point` only applies to `--tone-map extended-reinhard`",
+            )))
+        }
+        _ => {}
+    }
+
+    let display = image::DisplayTransform {
+        exposure,
+        tone_map,
+        transfer: gamma.map_or(image::Transfer::Srgb, image::Transfer::Gamma),
+    };
+
+    if progressive.is_some_and(|pass_samples| pass_samples > samples) {
+        return Err(CliError::Conflict(format!(
+            "cannot render passes of more than the {} samples per pixel of the whole image",
+            samples
+        )));
+    }
+
+    let adaptive = match (adaptive_threshold, min_samples) {
+        (Some(threshold), min_samples) => Some(render::Adaptive {
+            min_samples: min_samples.unwrap_or_else(|| samples.min(16)),
+            threshold,
+        }),
+        (None, Some(_)) => {
+            return Err(CliError::Conflict(String::from(
+                "`--min-samples` only applies to `--adaptive`",
+            )))
+        }
+        (None, None) => None,
+    };
+
+    if adaptive.is_some_and(|adaptive| adaptive.min_samples > samples) {
+        return Err(CliError::Conflict(format!(
+            "cannot take more than the {} samples per pixel of `--samples` before adapting",
+            samples
+        )));
+    }
+
+    // The heatmap is an image like any other, so its format comes from its extension
+    let sample_heatmap = match sample_heatmap {
+        Some(path) => match image::Format::from_path(&path) {
+            Some(format) => Some((path, format)),
+            None => {
+                return Err(CliError::Conflict(format!(
+                    "cannot infer the image format of the sample heatmap `{}`",
+                    path.display()
+                )))
+            }
+        },
+        None => None,
+    };
+
+    let mut filter = render::Filter::new(filter);
+    filter.radius = filter_radius.unwrap_or(filter.radius);
+
+    let threads = threads
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |cores| cores.get()));
+
+    Ok(Command::Render(Box::new(RenderOptions {
+        width,
+        height,
+        samples,
+        progressive,
+        adaptive,
+        sample_heatmap,
+        sampler,
+        filter,
+        max_depth,
+        threads,
+        tile_size,
+        output,
+        format,
+        display,
+        scene,
+        seed,
+    })))
+}
+
+fn positive(flag: &str, value: &str) -> Result<usize, CliError> {
+    match value.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(CliError::InvalidValue {
+            flag: flag.to_string(),
+            value: value.to_string(),
+            reason: String::from("expected a positive integer"),
+        }),
+    }
+}
+
+fn number(flag: &str, value: &str) -> Result<f64, CliError> {
+    match value.parse::<f64>() {
+        Ok(n) if n.is_finite() => Ok(n),
+        _ => Err(CliError::InvalidValue {
+            flag: flag.to_string(),
+            value: value.to_string(),
+            reason: String::from("expected a number"),
+        }),
+    }
+}
+
+fn positive_number(flag: &str, value: &str) -> Result<f64, CliError> {
+    match number(flag, value)? {
+        n if n > 0.0 => Ok(n),
+        _ => Err(CliError::InvalidValue {
+            flag: flag.to_string(),
+            value: value.to_string(),
+            reason: String::from("expected a positive number"),
+        }),
+    }
+}
+
+fn named<T>(
+    flag: &str,
+    value: &str,
+    from_name: fn(&str) -> Option<T>,
+    names: &[&str],
+) -> Result<T, CliError> {
+    from_name(value).ok_or_else(|| CliError::InvalidValue {
+        flag: flag.to_string(),
+        value: value.to_string(),
+        reason: format!("expected one of: {}", names.join(", ")),
+    })
+}
+
+fn parse_resolution(flag: &str, value: &str) -> Result<(usize, usize), CliError> {
+    let invalid = || CliError::InvalidValue {
+        flag: flag.to_string(),
+        value: value.to_string(),
+        reason: String::from("expected WIDTHxHEIGHT, e.g. 800x400"),
+    };
+
+    let mut parts = value.split(['x', 'X']);
+    let width = parts.next().ok_or_else(invalid)?;
+    let height = parts.next().ok_or_else(invalid)?;
+
+    if parts.next().is_some() {
+        return Err(invalid());
+    }
+
+    match (positive(flag, width), positive(flag, height)) {
+        (Ok(width), Ok(height)) => Ok((width, height)),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Command, CliError> {
+        parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn render_options(args: &[&str]) -> RenderOptions {
+        match parse_args(args) {
+            Ok(Command::Render(options)) => *options,
+            _ => panic!("expected render options for {:?}", args),
+        }
+    }
+
+    #[test]
+    fn defaults_match_the_original_render() {
+        let options = render_options(&[]);
+        assert_eq!((options.width, options.height), (2000, 1000));
+        assert_eq!(options.samples, 50);
+        assert!(options.threads >= 1);
+        assert_eq!(options.tile_size, 16);
+        assert_eq!(
+            options.output,
+            PathBuf::from("out/random_spheres_concurrent.ppm")
+        );
+        assert_eq!(options.format, image::Format::Ppm);
+        assert_eq!(options.sampler, SamplerKind::Sobol);
+        assert_eq!(options.filter, render::Filter::default());
+    }
+
+    #[test]
+    fn accepts_inline_and_separate_values() {
+        let options = render_options(&["render", "--resolution=320x200", "-s", "4", "--seed", "7"]);
+        assert_eq!((options.width, options.height), (320, 200));
+        assert_eq!(options.samples, 4);
+        assert_eq!(options.seed, Some(7));
+
+        let options = render_options(&["-o", "out.exr", "--exr-compression", "none"]);
+        assert_eq!(
+            options.format,
+            image::Format::Exr(image::ExrOptions {
+                compression: image::ExrCompression::None,
+                precision: image::ExrPrecision::Half,
+            })
+        );
+
+        let options = render_options(&[
+            "--tone-map=extended-reinhard",
+            "--white-point=8",
+            "--exposure=-1.5",
+        ]);
+        assert_eq!(
+            options.display.tone_map,
+            image::ToneMap::ExtendedReinhard { white: 8.0 }
+        );
+        assert_eq!(options.display.exposure, -1.5);
+
+        let options = render_options(&["--adaptive=0.05", "-s", "8", "--sample-heatmap", "h.png"]);
+        let adaptive = options.adaptive.unwrap();
+        assert_eq!((adaptive.min_samples, adaptive.threshold), (8, 0.05));
+        assert_eq!(
+            options.sample_heatmap,
+            Some((PathBuf::from("h.png"), image::Format::Png))
+        );
+
+        let options = render_options(&["--filter", "gaussian", "--filter-radius=2"]);
+        assert_eq!(
+            options.filter,
+            render::Filter {
+                kind: render::FilterKind::Gaussian,
+                radius: 2.0
+            }
+        );
+
+        let options = render_options(&["--scene", "scenes/spheres.scene"]);
+        assert_eq!(
+            options.scene,
+            SceneSource::File(PathBuf::from("scenes/spheres.scene"))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_combinations() {
+        assert!(matches!(
+            parse_args(&["--resolution", "10x10", "--width", "20"]),
+            Err(CliError::Conflict(_))
+        ));
+        assert!(matches!(
+            parse_args(&["-o", "image.bmp"]),
+            Err(CliError::Conflict(_))
+        ));
+        assert!(matches!(
+            parse_args(&["-o", "image.png", "--exr-precision", "float"]),
+            Err(CliError::Conflict(_))
+        ));
+        assert!(matches!(
+            parse_args(&["--samples", "10", "--progressive", "20"]),
+            Err(CliError::Conflict(_))
+        ));
+        assert!(matches!(
+            parse_args(&["--tone-map", "aces", "--white-point", "2"]),
+            Err(CliError::Conflict(_))
+        ));
+        assert!(matches!(
+            parse_args(&["--min-samples", "4"]),
+            Err(CliError::Conflict(_))
+        ));
+        assert!(matches!(
+            parse_args(&["--adaptive", "0.05", "-s", "8", "--min-samples", "16"]),
+            Err(CliError::Conflict(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        assert!(matches!(
+            parse_args(&["--samples", "0"]),
+            Err(CliError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            parse_args(&["--scene", "nope"]),
+            Err(CliError::InvalidValue { .. })
+        ));
+        assert_eq!(
+            parse_args(&["--width"]).err(),
+            Some(CliError::MissingValue(String::from("--width")))
+        );
+        assert_eq!(
+            parse_args(&["frobnicate"]).err(),
+            Some(CliError::UnknownCommand(String::from("frobnicate")))
+        );
+    }
+}
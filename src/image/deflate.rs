@@ -0,0 +1,244 @@
+// A small DEFLATE (RFC 1951) compressor wrapped in a zlib (RFC 1950) stream, which is what PNG
+// expects in its image data.
+//
+// It finds repeated strings with a hash chain (LZ77) and encodes the result with the fixed
+// Huffman codes from the spec, so there are no code tables to compute or store. That is far
+// from the best compression possible, but rendered images are mostly smooth gradients that
+// filter down to long runs of repeated bytes, which this handles well.
+
+const WINDOW_SIZE: usize = 32 * 1024;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const HASH_BITS: usize = 15;
+// How many earlier positions with the same hash are tried before settling for the best match
+const MAX_CHAIN: usize = 64;
+
+// Base value and number of extra bits for length codes 257 to 285
+#[rustfmt::skip]
+pub const LENGTHS: [(usize, u32); 29] = [
+    (3, 0), (4, 0), (5, 0), (6, 0), (7, 0), (8, 0), (9, 0), (10, 0),
+    (11, 1), (13, 1), (15, 1), (17, 1), (19, 2), (23, 2), (27, 2), (31, 2),
+    (35, 3), (43, 3), (51, 3), (59, 3), (67, 4), (83, 4), (99, 4), (115, 4),
+    (131, 5), (163, 5), (195, 5), (227, 5), (258, 0),
+];
+
+// Base value and number of extra bits for distance codes 0 to 29
+#[rustfmt::skip]
+pub const DISTANCES: [(usize, u32); 30] = [
+    (1, 0), (2, 0), (3, 0), (4, 0), (5, 1), (7, 1), (9, 2), (13, 2),
+    (17, 3), (25, 3), (33, 4), (49, 4), (65, 5), (97, 5), (129, 6), (193, 6),
+    (257, 7), (385, 7), (513, 8), (769, 8), (1025, 9), (1537, 9), (2049, 10), (3073, 10),
+    (4097, 11), (6145, 11), (8193, 12), (12289, 12), (16385, 13), (24577, 13),
+];
+
+pub fn zlib_compress(data: &[u8]) -> Vec<u8> {
+    // CMF: deflate with a 32K window. FLG: no dictionary, fastest level, and a check value
+    // that makes the pair a multiple of 31.
+    let mut output = vec![0x78, 0x01];
+    output.extend(deflate(data));
+    output.extend_from_slice(&adler32(data).to_be_bytes());
+    output
+}
+
+pub fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut bits = BitWriter::new();
+
+    // A single, final block using the fixed Huffman codes
+    bits.write(1, 1);
+    bits.write(1, 2);
+
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut previous = vec![usize::MAX; WINDOW_SIZE];
+    let mut position = 0;
+
+    while position < data.len() {
+        let (length, distance) = longest_match(data, position, &head, &previous);
+
+        if length >= MIN_MATCH {
+            write_match(&mut bits, length, distance);
+
+            for p in position..position + length {
+                insert(data, p, &mut head, &mut previous);
+            }
+
+            position += length;
+        } else {
+            write_literal(&mut bits, usize::from(data[position]));
+            insert(data, position, &mut head, &mut previous);
+            position += 1;
+        }
+    }
+
+    // End of block
+    write_literal(&mut bits, 256);
+    bits.finish()
+}
+
+// Remembers `position` as the most recent occurrence of the 3 bytes starting there
+fn insert(data: &[u8], position: usize, head: &mut [usize], previous: &mut [usize]) {
+    if position + MIN_MATCH <= data.len() {
+        let hash = hash(&data[position..position + MIN_MATCH]);
+        previous[position % WINDOW_SIZE] = head[hash];
+        head[hash] = position;
+    }
+}
+
+fn hash(bytes: &[u8]) -> usize {
+    let value = u32::from(bytes[0]) << 16 | u32::from(bytes[1]) << 8 | u32::from(bytes[2]);
+    (value.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize
+}
+
+fn longest_match(
+    data: &[u8],
+    position: usize,
+    head: &[usize],
+    previous: &[usize],
+) -> (usize, usize) {
+    if position + MIN_MATCH > data.len() {
+        return (0, 0);
+    }
+
+    let max_length = MAX_MATCH.min(data.len() - position);
+    let mut best = (0, 0);
+    let mut candidate = head[hash(&data[position..position + MIN_MATCH])];
+
+    for _ in 0..MAX_CHAIN {
+        if candidate == usize::MAX || position - candidate > WINDOW_SIZE - 1 {
+            break;
+        }
+
+        let length = data[candidate..]
+            .iter()
+            .zip(&data[position..position + max_length])
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        if length > best.0 {
+            best = (length, position - candidate);
+
+            if length == max_length {
+                break;
+            }
+        }
+
+        let next = previous[candidate % WINDOW_SIZE];
+
+        // The chain slot may have been overwritten by a more recent position
+        if next != usize::MAX && next >= candidate {
+            break;
+        }
+
+        candidate = next;
+    }
+
+    best
+}
+
+fn write_literal(bits: &mut BitWriter, symbol: usize) {
+    let (code, length) = match symbol {
+        0..=143 => (0b0011_0000 + symbol, 8),
+        144..=255 => (0b1_1001_0000 + symbol - 144, 9),
+        256..=279 => (symbol - 256, 7),
+        _ => (0b1100_0000 + symbol - 280, 8),
+    };
+
+    bits.write_huffman(code as u32, length);
+}
+
+fn write_match(bits: &mut BitWriter, length: usize, distance: usize) {
+    let index = LENGTHS
+        .iter()
+        .rposition(|&(base, _)| base <= length)
+        .unwrap();
+    let (base, extra_bits) = LENGTHS[index];
+    write_literal(bits, 257 + index);
+    bits.write((length - base) as u32, extra_bits);
+
+    let index = DISTANCES
+        .iter()
+        .rposition(|&(base, _)| base <= distance)
+        .unwrap();
+    let (base, extra_bits) = DISTANCES[index];
+    bits.write_huffman(index as u32, 5);
+    bits.write((distance - base) as u32, extra_bits);
+}
+
+// DEFLATE packs values starting from the least significant bit of each byte, except for
+// Huffman codes, which go most significant bit first.
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u64,
+    count: u32,
+}
+
+impl BitWriter {
+    fn new() -> BitWriter {
+        BitWriter {
+            bytes: Vec::new(),
+            buffer: 0,
+            count: 0,
+        }
+    }
+
+    fn write(&mut self, value: u32, count: u32) {
+        self.buffer |= u64::from(value) << self.count;
+        self.count += count;
+
+        while self.count >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    fn write_huffman(&mut self, code: u32, length: u32) {
+        self.write(code.reverse_bits() >> (32 - length), length);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+
+        self.bytes
+    }
+}
+
+pub fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+
+    // 5552 is the largest number of bytes that can be added up before `b` could overflow
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += u32::from(byte);
+            b += a;
+        }
+
+        a %= 65521;
+        b %= 65521;
+    }
+
+    b << 16 | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adler32_of_known_input() {
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn repeated_data_gets_smaller() {
+        let data: Vec<u8> = (0..10_000).map(|i| (i % 7) as u8).collect();
+        assert!(zlib_compress(&data).len() < 200);
+    }
+
+    #[test]
+    fn empty_input_is_a_valid_stream() {
+        // A final fixed-Huffman block with just the end-of-block code
+        assert_eq!(deflate(&[]), vec![0x03, 0x00]);
+    }
+}
@@ -0,0 +1,269 @@
+use crate::image::deflate::zlib_compress;
+use crate::render::Framebuffer;
+use std::io::Write;
+
+// Single part, scanline OpenEXR files, following "The OpenEXR File Layout":
+// https://openexr.com/en/latest/OpenEXRFileLayout.html
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ExrOptions {
+    pub compression: ExrCompression,
+    pub precision: ExrPrecision,
+}
+
+impl Default for ExrOptions {
+    fn default() -> ExrOptions {
+        ExrOptions {
+            compression: ExrCompression::Zip,
+            precision: ExrPrecision::Half,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ExrCompression {
+    None,
+    Zip,
+}
+
+impl ExrCompression {
+    pub fn from_name(name: &str) -> Option<ExrCompression> {
+        match name.to_ascii_lowercase().as_str() {
+            "none" => Some(ExrCompression::None),
+            "zip" => Some(ExrCompression::Zip),
+            _ => None,
+        }
+    }
+
+    // The value of the `compression` attribute, and how many scanlines go in each chunk
+    fn id_and_lines(self) -> (u8, usize) {
+        match self {
+            ExrCompression::None => (0, 1),
+            ExrCompression::Zip => (3, 16),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ExrPrecision {
+    Half,
+    Float,
+}
+
+impl ExrPrecision {
+    pub fn from_name(name: &str) -> Option<ExrPrecision> {
+        match name.to_ascii_lowercase().as_str() {
+            "half" => Some(ExrPrecision::Half),
+            "float" => Some(ExrPrecision::Float),
+            _ => None,
+        }
+    }
+}
+
+pub fn write<W: Write>(
+    writer: &mut W,
+    framebuffer: &Framebuffer,
+    options: ExrOptions,
+) -> std::io::Result<()> {
+    let width = framebuffer.width();
+    let height = framebuffer.height();
+    let (compression, lines_per_chunk) = options.compression.id_and_lines();
+
+    let mut header = Vec::new();
+    // Magic number, and version 2 with no flags: a single part scanline image
+    header.extend_from_slice(&[0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0]);
+
+    // Channels must be sorted by name, so this is also the order they are stored in
+    let pixel_type: i32 = match options.precision {
+        ExrPrecision::Half => 1,
+        ExrPrecision::Float => 2,
+    };
+    let mut channels = Vec::new();
+
+    for name in &[b"B", b"G", b"R"] {
+        channels.extend_from_slice(*name);
+        channels.push(0);
+        channels.extend_from_slice(&pixel_type.to_le_bytes());
+        // pLinear and three reserved bytes, then x and y sampling
+        channels.extend_from_slice(&[0, 0, 0, 0]);
+        channels.extend_from_slice(&1i32.to_le_bytes());
+        channels.extend_from_slice(&1i32.to_le_bytes());
+    }
+
+    channels.push(0);
+    write_attribute(&mut header, "channels", "chlist", &channels);
+    write_attribute(&mut header, "compression", "compression", &[compression]);
+
+    let mut window = Vec::new();
+
+    for value in &[0, 0, width as i32 - 1, height as i32 - 1] {
+        window.extend_from_slice(&value.to_le_bytes());
+    }
+
+    write_attribute(&mut header, "dataWindow", "box2i", &window);
+    write_attribute(&mut header, "displayWindow", "box2i", &window);
+    write_attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    write_attribute(
+        &mut header,
+        "pixelAspectRatio",
+        "float",
+        &1f32.to_le_bytes(),
+    );
+    write_attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    write_attribute(
+        &mut header,
+        "screenWindowWidth",
+        "float",
+        &1f32.to_le_bytes(),
+    );
+    header.push(0);
+
+    let colors: Vec<_> = framebuffer.colors().collect();
+    let mut chunks = Vec::new();
+
+    for (i, rows) in colors.chunks(width * lines_per_chunk).enumerate() {
+        let mut data = Vec::new();
+
+        for row in rows.chunks(width) {
+            for channel in 0..3 {
+                for color in row {
+                    let value = match channel {
+                        0 => color.z,
+                        1 => color.y,
+                        _ => color.x,
+                    } as f32;
+
+                    match options.precision {
+                        ExrPrecision::Half => data.extend_from_slice(&to_half(value).to_le_bytes()),
+                        ExrPrecision::Float => data.extend_from_slice(&value.to_le_bytes()),
+                    }
+                }
+            }
+        }
+
+        if options.compression == ExrCompression::Zip {
+            let compressed = zlib_compress(&predict(&interleave(&data)));
+
+            // Readers take a chunk that is as big as its uncompressed data to be uncompressed
+            if compressed.len() < data.len() {
+                data = compressed;
+            }
+        }
+
+        let mut chunk = Vec::with_capacity(data.len() + 8);
+        chunk.extend_from_slice(&((i * lines_per_chunk) as i32).to_le_bytes());
+        chunk.extend_from_slice(&(data.len() as i32).to_le_bytes());
+        chunk.extend(data);
+        chunks.push(chunk);
+    }
+
+    // The offset table points to where each chunk starts, counting from the start of the file
+    let mut offset = header.len() + chunks.len() * 8;
+    writer.write_all(&header)?;
+
+    for chunk in &chunks {
+        writer.write_all(&(offset as u64).to_le_bytes())?;
+        offset += chunk.len();
+    }
+
+    for chunk in &chunks {
+        writer.write_all(chunk)?;
+    }
+
+    Ok(())
+}
+
+fn write_attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+// Before zlib, ZIP compression puts the first byte of every value in the first half of the
+// buffer and the second byte in the other half...
+fn interleave(data: &[u8]) -> Vec<u8> {
+    data.iter()
+        .step_by(2)
+        .chain(data.iter().skip(1).step_by(2))
+        .cloned()
+        .collect()
+}
+
+// ...and then stores each byte as its difference with the previous one
+fn predict(data: &[u8]) -> Vec<u8> {
+    let mut predicted = Vec::with_capacity(data.len());
+    let mut previous = 0u8;
+
+    for (i, &byte) in data.iter().enumerate() {
+        predicted.push(if i == 0 {
+            byte
+        } else {
+            byte.wrapping_sub(previous).wrapping_add(128)
+        });
+        previous = byte;
+    }
+
+    predicted
+}
+
+// Converts to an IEEE 754 half precision float, rounding to the nearest value
+fn to_half(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x007f_ffff;
+
+    if exponent == 0xff {
+        // Infinity stays infinity, and NaN stays NaN
+        return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+
+    let exponent = exponent - 127 + 15;
+
+    if exponent >= 0x1f {
+        // Too big: infinity
+        sign | 0x7c00
+    } else if exponent <= 0 {
+        // Too small for a normal half: a subnormal, or zero
+        if exponent < -10 {
+            return sign;
+        }
+
+        let mantissa = mantissa | 0x0080_0000;
+        let shift = (14 - exponent) as u32;
+        let half_mantissa = mantissa >> shift;
+        let round = (mantissa >> (shift - 1)) & 1;
+
+        sign | (half_mantissa + round) as u16
+    } else {
+        let half = u32::from(sign) | (exponent as u32) << 10 | mantissa >> 13;
+        // Rounding can carry into the exponent, which is exactly what should happen
+        (half + ((mantissa >> 12) & 1)) as u16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn half_floats() {
+        assert_eq!(to_half(0.0), 0x0000);
+        assert_eq!(to_half(1.0), 0x3c00);
+        assert_eq!(to_half(-2.0), 0xc000);
+        assert_eq!(to_half(0.5), 0x3800);
+        assert_eq!(to_half(65504.0), 0x7bff);
+        assert_eq!(to_half(1e6), 0x7c00);
+        // The smallest subnormal half
+        assert_eq!(to_half(5.960_464_5e-8), 0x0001);
+        assert_eq!(to_half(1.0 / 3.0), 0x3555);
+    }
+
+    #[test]
+    fn zip_preprocessing() {
+        assert_eq!(interleave(&[1, 2, 3, 4, 5]), vec![1, 3, 5, 2, 4]);
+        assert_eq!(predict(&[10, 12, 11, 11]), vec![10, 130, 127, 128]);
+    }
+}
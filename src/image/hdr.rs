@@ -0,0 +1,146 @@
+use crate::math::Vector3;
+use crate::render::Framebuffer;
+use std::io::Write;
+
+// Radiance RGBE (.hdr): every pixel is three 8 bit mantissas sharing an 8 bit exponent, which
+// is plenty of range for linear radiance. http://paulbourke.net/dataformats/pic/
+pub fn write<W: Write>(writer: &mut W, framebuffer: &Framebuffer) -> std::io::Result<()> {
+    let width = framebuffer.width();
+
+    write!(
+        writer,
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+        framebuffer.height(),
+        width
+    )?;
+
+    let pixels: Vec<[u8; 4]> = framebuffer.colors().map(rgbe).collect();
+
+    for scanline in pixels.chunks(width) {
+        write_scanline(writer, scanline)?;
+    }
+
+    Ok(())
+}
+
+fn rgbe(color: Vector3) -> [u8; 4] {
+    let max = color.x.max(color.y).max(color.z);
+
+    if max < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+
+    let (mantissa, exponent) = frexp(max);
+    let scale = mantissa * 256.0 / max;
+
+    [
+        (color.x.max(0.0) * scale) as u8,
+        (color.y.max(0.0) * scale) as u8,
+        (color.z.max(0.0) * scale) as u8,
+        (exponent + 128) as u8,
+    ]
+}
+
+// Splits `value` into a mantissa in [0.5, 1) and a power of two, like C's `frexp`
+fn frexp(value: f64) -> (f64, i32) {
+    let mut exponent = value.log2().floor() as i32 + 1;
+    let mut mantissa = value / 2f64.powi(exponent);
+
+    // `log2` can be off by one right at powers of two
+    if mantissa >= 1.0 {
+        mantissa /= 2.0;
+        exponent += 1;
+    } else if mantissa < 0.5 {
+        mantissa *= 2.0;
+        exponent -= 1;
+    }
+
+    (mantissa, exponent)
+}
+
+// Scanlines are run length encoded one component at a time, which the format only allows for
+// widths between 8 and 32767. Anything else is written flat.
+fn write_scanline<W: Write>(writer: &mut W, scanline: &[[u8; 4]]) -> std::io::Result<()> {
+    let width = scanline.len();
+
+    if !(8..=0x7fff).contains(&width) {
+        for pixel in scanline {
+            writer.write_all(pixel)?;
+        }
+
+        return Ok(());
+    }
+
+    writer.write_all(&[2, 2, (width >> 8) as u8, (width & 0xff) as u8])?;
+
+    for component in 0..4 {
+        let values: Vec<u8> = scanline.iter().map(|pixel| pixel[component]).collect();
+        write_runs(writer, &values)?;
+    }
+
+    Ok(())
+}
+
+// Runs are encoded as (128 + count, value), and everything else as (count, values...). Both
+// kinds can hold up to 127 values.
+fn write_runs<W: Write>(writer: &mut W, values: &[u8]) -> std::io::Result<()> {
+    const MIN_RUN: usize = 4;
+    let mut position = 0;
+
+    while position < values.len() {
+        // Find where the next run worth encoding starts, and how long it is
+        let mut run_start = position;
+        let mut run_length = 0;
+
+        while run_start < values.len() {
+            run_length = values[run_start..]
+                .iter()
+                .take(127)
+                .take_while(|&&value| value == values[run_start])
+                .count();
+
+            if run_length >= MIN_RUN {
+                break;
+            }
+
+            run_start += run_length;
+        }
+
+        // Everything before that goes out as literal values
+        for chunk in values[position..run_start].chunks(127) {
+            writer.write_all(&[chunk.len() as u8])?;
+            writer.write_all(chunk)?;
+        }
+
+        if run_start < values.len() {
+            writer.write_all(&[128 + run_length as u8, values[run_start]])?;
+        }
+
+        position = run_start + run_length;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rgbe_keeps_values_above_one() {
+        assert_eq!(rgbe(Vector3::new(1.0, 0.5, 0.0)), [128, 64, 0, 129]);
+        assert_eq!(rgbe(Vector3::new(6.0, 3.0, 0.0)), [192, 96, 0, 131]);
+        assert_eq!(rgbe(Vector3::new(0.0, 0.0, 0.0)), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn run_length_encoding() {
+        let mut output = Vec::new();
+        write_runs(&mut output, &[1, 2, 3, 3, 3, 3, 3, 4]).unwrap();
+        assert_eq!(output, vec![2, 1, 2, 128 + 5, 3, 1, 4]);
+
+        let mut output = Vec::new();
+        write_runs(&mut output, &[9; 300]).unwrap();
+        assert_eq!(output, vec![128 + 127, 9, 128 + 127, 9, 128 + 46, 9]);
+    }
+}
@@ -0,0 +1,325 @@
+// A DEFLATE (RFC 1951) decompressor for zlib (RFC 1950) streams, to read the image data of PNG
+// files.
+//
+// Unlike our compressor, it has to take whatever other programs write: stored blocks, blocks
+// with the fixed Huffman codes, and blocks that bring their own codes. Those are decoded one
+// bit at a time by walking the canonical code, which is slower than a lookup table but plenty
+// fast for loading a few textures.
+use crate::image::deflate::{adler32, DISTANCES, LENGTHS};
+
+// The order in which the lengths of the code length code are stored
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+const MAX_BITS: usize = 15;
+
+pub fn zlib_decompress(data: &[u8]) -> Result<Vec<u8>, String> {
+    if data.len() < 6 {
+        return Err(String::from("the zlib stream is too short"));
+    }
+
+    let (cmf, flg) = (data[0], data[1]);
+
+    if cmf & 0x0f != 8 || (u16::from(cmf) << 8 | u16::from(flg)) % 31 != 0 {
+        return Err(String::from("the zlib stream has an invalid header"));
+    }
+
+    if flg & 0x20 != 0 {
+        return Err(String::from(
+            "zlib streams with a preset dictionary are not supported",
+        ));
+    }
+
+    let mut bits = BitReader::new(&data[2..]);
+    let output = inflate(&mut bits)?;
+
+    // The checksum comes right after the last block, on a byte boundary
+    let end = 2 + bits.byte_position();
+    let checksum = data
+        .get(end..end + 4)
+        .ok_or_else(|| String::from("the zlib stream is missing its checksum"))?;
+
+    if u32::from_be_bytes([checksum[0], checksum[1], checksum[2], checksum[3]]) != adler32(&output)
+    {
+        return Err(String::from("the zlib stream does not match its checksum"));
+    }
+
+    Ok(output)
+}
+
+fn inflate(bits: &mut BitReader) -> Result<Vec<u8>, String> {
+    let mut output = Vec::new();
+
+    loop {
+        let last = bits.read(1)? == 1;
+
+        match bits.read(2)? {
+            0 => stored_block(bits, &mut output)?,
+            1 => {
+                let (literals, distances) = fixed_codes();
+                compressed_block(bits, &mut output, &literals, &distances)?
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(bits)?;
+                compressed_block(bits, &mut output, &literals, &distances)?
+            }
+            _ => return Err(String::from("invalid deflate block type")),
+        }
+
+        if last {
+            return Ok(output);
+        }
+    }
+}
+
+fn stored_block(bits: &mut BitReader, output: &mut Vec<u8>) -> Result<(), String> {
+    bits.align();
+    let length = bits.read(16)?;
+    let complement = bits.read(16)?;
+
+    if length != !complement & 0xffff {
+        return Err(String::from("a stored deflate block has a corrupt length"));
+    }
+
+    for _ in 0..length {
+        output.push(bits.read(8)? as u8);
+    }
+
+    Ok(())
+}
+
+fn compressed_block(
+    bits: &mut BitReader,
+    output: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<(), String> {
+    loop {
+        let symbol = literals.decode(bits)?;
+
+        match symbol {
+            0..=255 => output.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let (base, extra_bits) = *LENGTHS
+                    .get(symbol - 257)
+                    .ok_or_else(|| String::from("invalid deflate length code"))?;
+                let length = base + bits.read(extra_bits)? as usize;
+
+                let (base, extra_bits) = *DISTANCES
+                    .get(distances.decode(bits)?)
+                    .ok_or_else(|| String::from("invalid deflate distance code"))?;
+                let distance = base + bits.read(extra_bits)? as usize;
+
+                if distance > output.len() {
+                    return Err(String::from(
+                        "a deflate match reaches back before the start of the data",
+                    ));
+                }
+
+                // Matches can overlap what they copy, so they go one byte at a time
+                let start = output.len() - distance;
+                for i in 0..length {
+                    output.push(output[start + i]);
+                }
+            }
+        }
+    }
+}
+
+fn fixed_codes() -> (Huffman, Huffman) {
+    let mut lengths = [0; 288];
+    lengths[..144].iter_mut().for_each(|length| *length = 8);
+    lengths[144..256].iter_mut().for_each(|length| *length = 9);
+    lengths[256..280].iter_mut().for_each(|length| *length = 7);
+    lengths[280..].iter_mut().for_each(|length| *length = 8);
+
+    (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+}
+
+// Blocks with their own codes start with the code lengths of both, themselves compressed with a
+// third code that's given first
+fn dynamic_codes(bits: &mut BitReader) -> Result<(Huffman, Huffman), String> {
+    let literal_count = bits.read(5)? as usize + 257;
+    let distance_count = bits.read(5)? as usize + 1;
+    let code_length_count = bits.read(4)? as usize + 4;
+
+    let mut code_lengths = [0; 19];
+    for &index in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[index] = bits.read(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_lengths);
+
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+
+    while lengths.len() < literal_count + distance_count {
+        let (length, repeat) = match code_lengths.decode(bits)? {
+            length @ 0..=15 => (length as u8, 1),
+            16 => match lengths.last() {
+                Some(&previous) => (previous, 3 + bits.read(2)?),
+                None => return Err(String::from("a deflate code repeats a length before any")),
+            },
+            17 => (0, 3 + bits.read(3)?),
+            _ => (0, 11 + bits.read(7)?),
+        };
+
+        lengths.extend(std::iter::repeat_n(length, repeat as usize));
+    }
+
+    if lengths.len() > literal_count + distance_count {
+        return Err(String::from("deflate code lengths run past their count"));
+    }
+
+    if lengths[256] == 0 {
+        return Err(String::from("a deflate block has no code for its end"));
+    }
+
+    Ok((
+        Huffman::new(&lengths[..literal_count]),
+        Huffman::new(&lengths[literal_count..]),
+    ))
+}
+
+// A canonical Huffman code, stored as how many codes there are of every length and the symbols
+// in the order of their codes
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Huffman {
+        let mut counts = [0; MAX_BITS + 1];
+        for &length in lengths {
+            counts[usize::from(length)] += 1;
+        }
+        counts[0] = 0;
+
+        // Codes of the same length are given to their symbols in order
+        let mut offsets = [0; MAX_BITS + 2];
+        for length in 1..=MAX_BITS {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+
+        let mut symbols = vec![0; usize::from(offsets[MAX_BITS + 1])];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                let offset = &mut offsets[usize::from(length)];
+                symbols[usize::from(*offset)] = symbol as u16;
+                *offset += 1;
+            }
+        }
+
+        Huffman { counts, symbols }
+    }
+
+    // Reads one bit at a time until the code so far is one of the codes of that length, which
+    // are numbered consecutively from `first`
+    fn decode(&self, bits: &mut BitReader) -> Result<usize, String> {
+        let (mut code, mut first, mut index) = (0, 0, 0);
+
+        for &count in &self.counts[1..] {
+            code |= bits.read(1)? as i32;
+            let count = i32::from(count);
+
+            if code - first < count {
+                return Ok(usize::from(self.symbols[(index + code - first) as usize]));
+            }
+
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err(String::from("invalid deflate Huffman code"))
+    }
+}
+
+// The reading side of `BitWriter`: values come out least significant bit first
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(bytes: &'a [u8]) -> BitReader<'a> {
+        BitReader { bytes, position: 0 }
+    }
+
+    fn read(&mut self, count: u32) -> Result<u32, String> {
+        let mut value = 0;
+
+        for i in 0..count {
+            let byte = self
+                .bytes
+                .get(self.position / 8)
+                .ok_or_else(|| String::from("the deflate data ends too early"))?;
+            value |= u32::from(byte >> (self.position % 8) & 1) << i;
+            self.position += 1;
+        }
+
+        Ok(value)
+    }
+
+    fn align(&mut self) {
+        self.position = self.position.div_ceil(8) * 8;
+    }
+
+    // The first whole byte after everything read so far
+    fn byte_position(&self) -> usize {
+        self.position.div_ceil(8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::deflate::zlib_compress;
+
+    #[test]
+    fn reads_what_we_compress() {
+        let data: Vec<u8> = (0..5000u32).map(|i| (i * i % 251) as u8).collect();
+        assert_eq!(zlib_decompress(&zlib_compress(&data)).unwrap(), data);
+        assert_eq!(
+            zlib_decompress(&zlib_compress(&[])).unwrap(),
+            Vec::<u8>::new()
+        );
+    }
+
+    #[test]
+    fn reads_stored_blocks_and_blocks_with_their_own_codes() {
+        // Both written by zlib itself, at levels 0 and 9
+        let stored = [
+            0x78, 0x01, 0x01, 0x0f, 0x00, 0xf0, 0xff, 0x73, 0x74, 0x6f, 0x72, 0x65, 0x64, 0x20,
+            0x61, 0x73, 0x20, 0x69, 0x74, 0x20, 0x69, 0x73, 0x2d, 0xed, 0x05, 0x7f,
+        ];
+        assert_eq!(zlib_decompress(&stored).unwrap(), b"stored as it is");
+
+        let dynamic = [
+            0x78, 0xda, 0xe5, 0x8c, 0x57, 0x15, 0x80, 0x30, 0x0c, 0x45, 0xad, 0x3c, 0x05, 0x18,
+            0x40, 0x4d, 0x0b, 0xe9, 0x60, 0x34, 0xdd, 0x05, 0xd4, 0x93, 0x83, 0x0d, 0xbe, 0xef,
+            0xa8, 0x8e, 0x90, 0x9a, 0x5f, 0x76, 0xe8, 0xcc, 0x23, 0xc0, 0xf0, 0x85, 0xad, 0x9d,
+            0xb1, 0x80, 0x3b, 0x65, 0x54, 0xc1, 0x87, 0x7a, 0x6e, 0xac, 0x6c, 0x67, 0x44, 0x25,
+            0xde, 0x79, 0x43, 0x8b, 0x34, 0x7c, 0x75, 0x30, 0xbe, 0x93, 0xa0, 0x87, 0x02, 0x0e,
+            0x9f, 0x1a, 0x67, 0x69, 0x6d, 0x99, 0xbe, 0xec, 0xdf, 0xd7, 0x17, 0x3f, 0x64, 0x7c,
+            0xad,
+        ];
+        let expected = b"the quick brown fox jumps over the lazy dog; \
+                         pack my box with five dozen liquor jugs. "
+            .repeat(4);
+        assert_eq!(zlib_decompress(&dynamic).unwrap(), expected);
+    }
+
+    #[test]
+    fn rejects_corrupt_streams() {
+        let mut data = zlib_compress(b"some data to spoil");
+        let last = data.len() - 1;
+        data[last] ^= 1;
+
+        assert_eq!(
+            zlib_decompress(&data).err().unwrap(),
+            "the zlib stream does not match its checksum"
+        );
+        assert!(zlib_decompress(&data[..data.len() / 2]).is_err());
+    }
+}
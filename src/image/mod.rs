@@ -0,0 +1,138 @@
+use crate::math::Vector3;
+use crate::render::Framebuffer;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+mod deflate;
+mod exr;
+mod hdr;
+mod inflate;
+mod png;
+mod ppm;
+mod tonemap;
+
+pub use exr::{ExrCompression, ExrOptions, ExrPrecision};
+pub use tonemap::{DisplayTransform, ToneMap, Transfer};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Format {
+    Ppm,
+    Png,
+    Hdr,
+    Exr(ExrOptions),
+}
+
+impl Format {
+    pub const NAMES: &'static [&'static str] = &["ppm", "png", "hdr", "exr"];
+
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name.to_ascii_lowercase().as_str() {
+            "ppm" => Some(Format::Ppm),
+            "png" => Some(Format::Png),
+            "hdr" => Some(Format::Hdr),
+            "exr" => Some(Format::Exr(ExrOptions::default())),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Format> {
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(Format::from_name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Ppm => "ppm",
+            Format::Png => "png",
+            Format::Hdr => "hdr",
+            Format::Exr(_) => "exr",
+        }
+    }
+
+    // Whether two formats write the same kind of file, regardless of their options
+    pub fn same_kind(self, other: Format) -> bool {
+        std::mem::discriminant(&self) == std::mem::discriminant(&other)
+    }
+}
+
+// PPM and PNG go through the display transform, while HDR and EXR store the linear colors of the
+// framebuffer as they are
+pub fn write(
+    path: &Path,
+    format: Format,
+    framebuffer: &Framebuffer,
+    display: &DisplayTransform,
+) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    let (width, height) = (framebuffer.width(), framebuffer.height());
+
+    match format {
+        Format::Ppm => ppm::write(&mut writer, width, height, &framebuffer.to_rgb8(display))?,
+        Format::Png => png::write(&mut writer, width, height, &framebuffer.to_rgb8(display))?,
+        Format::Hdr => hdr::write(&mut writer, framebuffer)?,
+        Format::Exr(options) => exr::write(&mut writer, framebuffer, options)?,
+    }
+
+    writer.flush()
+}
+
+// An image read from a file, top row first, with every channel from 0 to 1 as it was stored
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Vector3>,
+}
+
+impl Image {
+    pub fn pixel(&self, x: usize, y: usize) -> Vector3 {
+        self.pixels[y * self.width + x]
+    }
+
+    // The linear colors of an image that was stored with `transfer`, as most are with sRGB
+    pub fn decode(mut self, transfer: Transfer) -> Image {
+        for pixel in &mut self.pixels {
+            *pixel = Vector3::new(
+                transfer.decode(pixel.x),
+                transfer.decode(pixel.y),
+                transfer.decode(pixel.z),
+            );
+        }
+
+        self
+    }
+}
+
+#[derive(Debug)]
+pub enum ImageError {
+    Io(PathBuf, std::io::Error),
+    Invalid(PathBuf, String),
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImageError::Io(path, error) => write!(f, "{}: {}", path.display(), error),
+            ImageError::Invalid(path, message) => write!(f, "{}: {}", path.display(), message),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {}
+
+// PNG and PPM files, told apart by their extension
+pub fn read(path: &Path) -> Result<Image, ImageError> {
+    let invalid = |message: String| ImageError::Invalid(path.to_path_buf(), message);
+    let bytes = || std::fs::read(path).map_err(|error| ImageError::Io(path.to_path_buf(), error));
+
+    match Format::from_path(path) {
+        Some(Format::Png) => png::read(&bytes()?).map_err(invalid),
+        Some(Format::Ppm) => ppm::read(&bytes()?).map_err(invalid),
+        _ => Err(invalid(String::from(
+            "images can only be read from PNG and PPM files",
+        ))),
+    }
+}
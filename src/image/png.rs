@@ -0,0 +1,458 @@
+use crate::image::deflate::zlib_compress;
+use crate::image::inflate::zlib_decompress;
+use crate::image::Image;
+use crate::math::Vector3;
+use std::io::Write;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+const BYTES_PER_PIXEL: usize = 3;
+
+// 8 bit per channel RGB PNG, no interlacing: https://www.w3.org/TR/png/
+pub fn write<W: Write>(
+    writer: &mut W,
+    width: usize,
+    height: usize,
+    rgb_pixels: &[u8],
+) -> std::io::Result<()> {
+    writer.write_all(&SIGNATURE)?;
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // Bit depth, color type (RGB), compression, filter method and interlacing
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+    write_chunk(writer, b"IHDR", &header)?;
+
+    write_chunk(writer, b"IDAT", &zlib_compress(&filter(width, rgb_pixels)))?;
+    write_chunk(writer, b"IEND", &[])
+}
+
+// Any non-interlaced PNG, at any bit depth. Transparency is dropped, leaving the colors as they
+// were stored, from 0 to 1.
+pub fn read(bytes: &[u8]) -> Result<Image, String> {
+    if !bytes.starts_with(&SIGNATURE) {
+        return Err(String::from("not a PNG file"));
+    }
+
+    let mut header = None;
+    let mut palette: &[u8] = &[];
+    let mut data = Vec::new();
+    let mut rest = &bytes[SIGNATURE.len()..];
+
+    loop {
+        if rest.len() < 12 {
+            return Err(String::from("the file ends before its last chunk"));
+        }
+
+        let length = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        let kind = &rest[4..8];
+
+        if rest.len() < 12 + length {
+            return Err(format!(
+                "the {} chunk runs past the end of the file",
+                String::from_utf8_lossy(kind)
+            ));
+        }
+
+        let chunk = &rest[8..8 + length];
+        let crc = &rest[8 + length..12 + length];
+
+        if crc32(rest[4..8 + length].iter()).to_be_bytes() != crc {
+            return Err(format!(
+                "the {} chunk does not match its checksum",
+                String::from_utf8_lossy(kind)
+            ));
+        }
+
+        match kind {
+            b"IHDR" => header = Some(Header::parse(chunk)?),
+            b"PLTE" => palette = chunk,
+            b"IDAT" => data.extend_from_slice(chunk),
+            b"IEND" => break,
+            // Gamma, transparency, text and so on
+            _ => {}
+        }
+
+        rest = &rest[12 + length..];
+    }
+
+    let header = header.ok_or_else(|| String::from("the file has no IHDR chunk"))?;
+    let pixels = unfilter(&header, &zlib_decompress(&data)?)?;
+    let rows = pixels.chunks(header.stride);
+    let max = f64::from((1u32 << header.bit_depth) - 1);
+
+    let mut image = Image {
+        width: header.width,
+        height: header.height,
+        pixels: Vec::with_capacity(header.width * header.height),
+    };
+
+    for row in rows {
+        for x in 0..header.width {
+            let sample = |channel| header.sample(row, x * header.channels + channel);
+
+            image.pixels.push(match header.color_type {
+                // Grey, with or without alpha
+                0 | 4 => {
+                    let grey = f64::from(sample(0)) / max;
+                    Vector3::new(grey, grey, grey)
+                }
+                3 => {
+                    let index = sample(0) as usize * 3;
+                    let color = palette
+                        .get(index..index + 3)
+                        .ok_or_else(|| format!("palette index {} is out of range", index / 3))?;
+                    Vector3::new(
+                        f64::from(color[0]) / 255.0,
+                        f64::from(color[1]) / 255.0,
+                        f64::from(color[2]) / 255.0,
+                    )
+                }
+                // RGB, with or without alpha
+                _ => Vector3::new(
+                    f64::from(sample(0)) / max,
+                    f64::from(sample(1)) / max,
+                    f64::from(sample(2)) / max,
+                ),
+            });
+        }
+    }
+
+    Ok(image)
+}
+
+struct Header {
+    width: usize,
+    height: usize,
+    bit_depth: u32,
+    color_type: u8,
+    channels: usize,
+    // The bytes in a row, without its filter type
+    stride: usize,
+}
+
+impl Header {
+    fn parse(chunk: &[u8]) -> Result<Header, String> {
+        if chunk.len() != 13 {
+            return Err(String::from("the IHDR chunk has the wrong length"));
+        }
+
+        let width = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]) as usize;
+        let height = u32::from_be_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]) as usize;
+        let (bit_depth, color_type) = (u32::from(chunk[8]), chunk[9]);
+
+        let (channels, depths): (usize, &[u32]) = match color_type {
+            0 => (1, &[1, 2, 4, 8, 16]),
+            2 => (3, &[8, 16]),
+            3 => (1, &[1, 2, 4, 8]),
+            4 => (2, &[8, 16]),
+            6 => (4, &[8, 16]),
+            _ => return Err(format!("unknown color type {}", color_type)),
+        };
+
+        if !depths.contains(&bit_depth) {
+            return Err(format!(
+                "color type {} cannot have a bit depth of {}",
+                color_type, bit_depth
+            ));
+        }
+
+        if width == 0 || height == 0 {
+            return Err(String::from("the image is empty"));
+        }
+
+        if chunk[12] != 0 {
+            return Err(String::from("interlaced PNG files are not supported"));
+        }
+
+        // The rows, each with its filter type in front, have to fit in memory
+        let stride = width
+            .checked_mul(channels * bit_depth as usize)
+            .map(|bits| bits.div_ceil(8))
+            .filter(|&stride| {
+                stride
+                    .checked_add(1)
+                    .and_then(|row| row.checked_mul(height))
+                    .is_some()
+            })
+            .ok_or_else(|| String::from("the image is too large"))?;
+
+        Ok(Header {
+            width,
+            height,
+            bit_depth,
+            color_type,
+            channels,
+            stride,
+        })
+    }
+
+    // What filters take as the pixel to the left: the one before, or the byte before for bit
+    // depths that fit more than one pixel in a byte
+    fn bytes_per_pixel(&self) -> usize {
+        (self.channels * self.bit_depth as usize).div_ceil(8).max(1)
+    }
+
+    // Samples are packed from the most significant bit, and 16 bit ones are big endian
+    fn sample(&self, row: &[u8], index: usize) -> u32 {
+        match self.bit_depth {
+            8 => u32::from(row[index]),
+            16 => u32::from(row[index * 2]) << 8 | u32::from(row[index * 2 + 1]),
+            depth => {
+                let bit = index * depth as usize;
+                let shift = 8 - depth as usize - bit % 8;
+                u32::from(row[bit / 8] >> shift) & ((1 << depth) - 1)
+            }
+        }
+    }
+}
+
+// Undoes the filter of every row, leaving the rows one after the other
+fn unfilter(header: &Header, data: &[u8]) -> Result<Vec<u8>, String> {
+    let stride = header.stride;
+    let size = stride
+        .checked_add(1)
+        .and_then(|row| row.checked_mul(header.height));
+
+    if size != Some(data.len()) {
+        return Err(String::from(
+            "the image data does not match the size of the image",
+        ));
+    }
+
+    let mut pixels = vec![0; stride * header.height];
+
+    for (y, filtered) in data.chunks(stride + 1).enumerate() {
+        let (done, rest) = pixels.split_at_mut(y * stride);
+        let above = if y == 0 {
+            None
+        } else {
+            Some(&done[(y - 1) * stride..])
+        };
+
+        unfilter_row(
+            filtered[0],
+            &filtered[1..],
+            above,
+            &mut rest[..stride],
+            header.bytes_per_pixel(),
+        )?;
+    }
+
+    Ok(pixels)
+}
+
+fn unfilter_row(
+    filter_type: u8,
+    filtered: &[u8],
+    above: Option<&[u8]>,
+    row: &mut [u8],
+    bytes_per_pixel: usize,
+) -> Result<(), String> {
+    if filter_type > 4 {
+        return Err(format!("unknown filter type {}", filter_type));
+    }
+
+    for i in 0..row.len() {
+        let left = if i >= bytes_per_pixel {
+            row[i - bytes_per_pixel]
+        } else {
+            0
+        };
+        let up = above.map_or(0, |above| above[i]);
+        let up_left = match above {
+            Some(above) if i >= bytes_per_pixel => above[i - bytes_per_pixel],
+            _ => 0,
+        };
+
+        let prediction = match filter_type {
+            0 => 0,
+            1 => left,
+            2 => up,
+            3 => ((u16::from(left) + u16::from(up)) / 2) as u8,
+            _ => paeth(left, up, up_left),
+        };
+
+        row[i] = filtered[i].wrapping_add(prediction);
+    }
+
+    Ok(())
+}
+
+fn write_chunk<W: Write>(writer: &mut W, kind: &[u8; 4], data: &[u8]) -> std::io::Result<()> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+
+    let crc = crc32(kind.iter().chain(data));
+    writer.write_all(&crc.to_be_bytes())
+}
+
+// PNG compresses better when each row is stored as the difference to some prediction from
+// its neighbours. Every row gets the filter that produces the smallest values, a heuristic
+// recommended by the spec itself.
+fn filter(width: usize, rgb_pixels: &[u8]) -> Vec<u8> {
+    let stride = width * BYTES_PER_PIXEL;
+    let zeros = vec![0; stride];
+    let mut filtered = Vec::with_capacity(rgb_pixels.len() + rgb_pixels.len() / stride.max(1));
+
+    for (y, row) in rgb_pixels.chunks(stride).enumerate() {
+        let above = if y == 0 {
+            &zeros[..]
+        } else {
+            &rgb_pixels[(y - 1) * stride..y * stride]
+        };
+
+        let best = (0..5u8)
+            .map(|filter_type| (filter_type, filter_row(filter_type, row, above)))
+            .min_by_key(|(_, bytes)| {
+                bytes
+                    .iter()
+                    .map(|&b| u32::from((b as i8).unsigned_abs()))
+                    .sum::<u32>()
+            })
+            .unwrap();
+
+        filtered.push(best.0);
+        filtered.extend(best.1);
+    }
+
+    filtered
+}
+
+fn filter_row(filter_type: u8, row: &[u8], above: &[u8]) -> Vec<u8> {
+    (0..row.len())
+        .map(|i| {
+            let left = if i >= BYTES_PER_PIXEL {
+                row[i - BYTES_PER_PIXEL]
+            } else {
+                0
+            };
+            let up = above[i];
+            let up_left = if i >= BYTES_PER_PIXEL {
+                above[i - BYTES_PER_PIXEL]
+            } else {
+                0
+            };
+
+            let prediction = match filter_type {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((u16::from(left) + u16::from(up)) / 2) as u8,
+                _ => paeth(left, up, up_left),
+            };
+
+            row[i].wrapping_sub(prediction)
+        })
+        .collect()
+}
+
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = i16::from(left) + i16::from(up) - i16::from(up_left);
+    let to_left = (estimate - i16::from(left)).abs();
+    let to_up = (estimate - i16::from(up)).abs();
+    let to_up_left = (estimate - i16::from(up_left)).abs();
+
+    if to_left <= to_up && to_left <= to_up_left {
+        left
+    } else if to_up <= to_up_left {
+        up
+    } else {
+        up_left
+    }
+}
+
+fn crc32<'a, I: IntoIterator<Item = &'a u8>>(bytes: I) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_of_known_input() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn filters_reverse_to_the_original_row() {
+        let above = [10, 20, 30, 40, 50, 60];
+        let row = [12, 25, 31, 200, 0, 90];
+
+        for filter_type in 0..5 {
+            let filtered = filter_row(filter_type, &row, &above);
+            let mut restored = [0u8; 6];
+
+            for i in 0..row.len() {
+                let left = if i >= 3 { restored[i - 3] } else { 0 };
+                let up_left = if i >= 3 { above[i - 3] } else { 0 };
+                let prediction = match filter_type {
+                    0 => 0,
+                    1 => left,
+                    2 => above[i],
+                    3 => ((u16::from(left) + u16::from(above[i])) / 2) as u8,
+                    _ => paeth(left, above[i], up_left),
+                };
+                restored[i] = filtered[i].wrapping_add(prediction);
+            }
+
+            assert_eq!(restored, row, "filter type {}", filter_type);
+        }
+    }
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        let rgb_pixels: Vec<u8> = (0..4 * 3 * 3).map(|i| (i * 37 % 256) as u8).collect();
+        let mut file = Vec::new();
+        write(&mut file, 4, 3, &rgb_pixels).unwrap();
+
+        let image = read(&file).unwrap();
+        assert_eq!((image.width, image.height), (4, 3));
+        assert_eq!(
+            image.pixels[5],
+            Vector3::new(43.0 / 255.0, 80.0 / 255.0, 117.0 / 255.0)
+        );
+    }
+
+    #[test]
+    fn reads_packed_grey_pixels() {
+        // A 2 bit grey image, 3 pixels wide: 0, 1 and 3 out of 3
+        let mut file = SIGNATURE.to_vec();
+        let header = [0, 0, 0, 3, 0, 0, 0, 1, 2, 0, 0, 0, 0];
+        write_chunk(&mut file, b"IHDR", &header).unwrap();
+        write_chunk(&mut file, b"IDAT", &zlib_compress(&[0, 0b0001_1100])).unwrap();
+        write_chunk(&mut file, b"IEND", &[]).unwrap();
+
+        let image = read(&file).unwrap();
+        let greys: Vec<f64> = image.pixels.iter().map(|pixel| pixel.x).collect();
+        assert_eq!(greys, vec![0.0, 1.0 / 3.0, 1.0]);
+    }
+
+    #[test]
+    fn rejects_images_too_large_to_hold() {
+        // 4294967295 by 4294967295 RGBA pixels, with 16 bits a sample
+        let header = [
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 16, 6, 0, 0, 0,
+        ];
+        assert_eq!(
+            Header::parse(&header).err().unwrap(),
+            "the image is too large"
+        );
+    }
+}
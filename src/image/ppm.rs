@@ -0,0 +1,135 @@
+use crate::image::Image;
+use crate::math::Vector3;
+use std::io::Write;
+
+// Binary ("raw") PPM: a tiny text header followed by the RGB bytes as they are
+pub fn write<W: Write>(
+    writer: &mut W,
+    width: usize,
+    height: usize,
+    rgb_pixels: &[u8],
+) -> std::io::Result<()> {
+    write!(writer, "P6\n{} {}\n255\n", width, height)?;
+    writer.write_all(rgb_pixels)
+}
+
+// Both binary (P6) and plain text (P3) PPM, with up to 16 bits per channel
+pub fn read(bytes: &[u8]) -> Result<Image, String> {
+    let mut position = 0;
+
+    // The header is whitespace-separated, with comments from `#` to the end of the line
+    let mut word = || {
+        loop {
+            match bytes.get(position) {
+                Some(b'#') => {
+                    while bytes.get(position).is_some_and(|&b| b != b'\n') {
+                        position += 1;
+                    }
+                }
+                Some(b) if b.is_ascii_whitespace() => position += 1,
+                _ => break,
+            }
+        }
+
+        let start = position;
+        while bytes
+            .get(position)
+            .is_some_and(|b| !b.is_ascii_whitespace())
+        {
+            position += 1;
+        }
+
+        std::str::from_utf8(&bytes[start..position]).unwrap_or("")
+    };
+
+    let binary = match word() {
+        "P6" => true,
+        "P3" => false,
+        _ => return Err(String::from("not an RGB PPM file")),
+    };
+
+    let mut number = |what: &str| -> Result<usize, String> {
+        word()
+            .parse()
+            .map_err(|_| format!("the header is missing the {}", what))
+    };
+    let width = number("width")?;
+    let height = number("height")?;
+    let max = number("maximum value")?;
+
+    if width == 0 || height == 0 {
+        return Err(String::from("the image is empty"));
+    }
+
+    if max == 0 || max > 65535 {
+        return Err(String::from("the maximum value must be from 1 to 65535"));
+    }
+
+    let count = width
+        .checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(3))
+        .ok_or_else(|| String::from("the image is too large"))?;
+    let samples: Vec<usize> = if binary {
+        // A single whitespace character separates the header from the pixels
+        let data = &bytes[(position + 1).min(bytes.len())..];
+        let size = if max > 255 { 2 } else { 1 };
+
+        if data.len() / size < count {
+            return Err(String::from("the file ends before its last pixel"));
+        }
+
+        data.chunks(size)
+            .take(count)
+            .map(|sample| {
+                sample
+                    .iter()
+                    .fold(0, |value, &b| value << 8 | usize::from(b))
+            })
+            .collect()
+    } else {
+        (0..count)
+            .map(|_| number("value of a pixel"))
+            .collect::<Result<_, _>>()?
+    };
+
+    let channel = |sample: usize| sample.min(max) as f64 / max as f64;
+    let pixels: Vec<_> = samples
+        .chunks(3)
+        .map(|rgb| Vector3::new(channel(rgb[0]), channel(rgb[1]), channel(rgb[2])))
+        .collect();
+    assert_eq!(pixels.len(), width * height);
+
+    Ok(Image {
+        width,
+        height,
+        pixels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_header_and_raw_bytes() {
+        let mut output = Vec::new();
+        write(&mut output, 2, 1, &[255, 0, 0, 0, 128, 255]).unwrap();
+        assert_eq!(output, b"P6\n2 1\n255\n\xff\x00\x00\x00\x80\xff");
+    }
+
+    #[test]
+    fn reads_binary_and_plain_files() {
+        let binary = read(b"P6\n# a comment\n2 1\n255\n\xff\x00\x00\x00\x33\xff").unwrap();
+        assert_eq!((binary.width, binary.height), (2, 1));
+        assert_eq!(binary.pixels[1], Vector3::new(0.0, 0.2, 1.0));
+
+        let plain = read(b"P3 1 2 15\n15 0 0\n0 3 15\n").unwrap();
+        assert_eq!(plain.pixels[1], Vector3::new(0.0, 0.2, 1.0));
+
+        assert!(read(b"P6 2 1 255\n\xff\x00").is_err());
+        assert_eq!(
+            read(b"P6 4611686018427387904 4 255\n").err().unwrap(),
+            "the image is too large"
+        );
+    }
+}
@@ -0,0 +1,184 @@
+use crate::math::{luminance, Vector3};
+
+// How linear radiance becomes a displayable 8 bit color: first it's scaled by the exposure,
+// then the tone mapping operator squeezes it into [0, 1], and last the transfer function encodes
+// it for the display.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DisplayTransform {
+    // In stops: every stop doubles (or halves, if negative) the amount of light
+    pub exposure: f64,
+    pub tone_map: ToneMap,
+    pub transfer: Transfer,
+}
+
+impl Default for DisplayTransform {
+    fn default() -> DisplayTransform {
+        DisplayTransform {
+            exposure: 0.0,
+            tone_map: ToneMap::Clamp,
+            transfer: Transfer::Srgb,
+        }
+    }
+}
+
+impl DisplayTransform {
+    pub fn apply(&self, color: Vector3) -> [u8; 3] {
+        let exposed = color * 2f64.powf(self.exposure);
+        let mapped = self.tone_map.apply(exposed);
+
+        [
+            quantize(self.transfer.encode(mapped.x)),
+            quantize(self.transfer.encode(mapped.y)),
+            quantize(self.transfer.encode(mapped.z)),
+        ]
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ToneMap {
+    // Anything above 1 is clipped
+    Clamp,
+    // L / (1 + L) on luminance: everything fits, but nothing ever reaches white
+    Reinhard,
+    // Reinhard, but luminance at `white` (and above) maps to white
+    ExtendedReinhard { white: f64 },
+    // Krzysztof Narkowicz's curve fit of the ACES filmic reference rendering transform:
+    // https://knarkowicz.wordpress.com/2016/01/06/aces-filmic-tone-mapping-curve/
+    Aces,
+}
+
+impl ToneMap {
+    pub const NAMES: &'static [&'static str] = &["clamp", "reinhard", "extended-reinhard", "aces"];
+
+    // `extended-reinhard` starts with a white point of 4, which can be changed afterwards
+    pub fn from_name(name: &str) -> Option<ToneMap> {
+        match name.to_ascii_lowercase().as_str() {
+            "clamp" => Some(ToneMap::Clamp),
+            "reinhard" => Some(ToneMap::Reinhard),
+            "extended-reinhard" => Some(ToneMap::ExtendedReinhard { white: 4.0 }),
+            "aces" => Some(ToneMap::Aces),
+            _ => None,
+        }
+    }
+
+    pub fn apply(self, color: Vector3) -> Vector3 {
+        let color = Vector3::new(color.x.max(0.0), color.y.max(0.0), color.z.max(0.0));
+
+        let mapped = match self {
+            ToneMap::Clamp => color,
+            ToneMap::Reinhard => scale_luminance(color, |l| l / (1.0 + l)),
+            ToneMap::ExtendedReinhard { white } => {
+                scale_luminance(color, |l| l * (1.0 + l / (white * white)) / (1.0 + l))
+            }
+            ToneMap::Aces => {
+                let aces = |x: f64| {
+                    // The fit expects the input to be scaled down a bit to match the exposure of
+                    // the reference transform
+                    let x = x * 0.6;
+                    (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)
+                };
+
+                Vector3::new(aces(color.x), aces(color.y), aces(color.z))
+            }
+        };
+
+        Vector3::new(
+            mapped.x.clamp(0.0, 1.0),
+            mapped.y.clamp(0.0, 1.0),
+            mapped.z.clamp(0.0, 1.0),
+        )
+    }
+}
+
+// Maps the luminance of `color` with `curve`, keeping its hue
+fn scale_luminance<F: Fn(f64) -> f64>(color: Vector3, curve: F) -> Vector3 {
+    let luminance = luminance(color);
+
+    if luminance <= 0.0 {
+        color
+    } else {
+        color * (curve(luminance) / luminance)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Transfer {
+    // The piecewise curve from the sRGB standard, what monitors expect
+    Srgb,
+    // A plain power curve, `value ^ (1 / gamma)`. The book uses a gamma of 2.
+    Gamma(f64),
+}
+
+impl Transfer {
+    pub fn encode(self, value: f64) -> f64 {
+        match self {
+            Transfer::Srgb => {
+                if value <= 0.003_130_8 {
+                    12.92 * value
+                } else {
+                    1.055 * value.powf(1.0 / 2.4) - 0.055
+                }
+            }
+            Transfer::Gamma(gamma) => value.powf(1.0 / gamma),
+        }
+    }
+
+    // Back from display values to linear ones, for colors that come from images or models
+    pub fn decode(self, value: f64) -> f64 {
+        match self {
+            Transfer::Srgb => {
+                if value <= 0.040_45 {
+                    value / 12.92
+                } else {
+                    ((value + 0.055) / 1.055).powf(2.4)
+                }
+            }
+            Transfer::Gamma(gamma) => value.powf(gamma),
+        }
+    }
+}
+
+fn quantize(value: f64) -> u8 {
+    (value * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn exposure_is_in_stops() {
+        let transform = DisplayTransform {
+            exposure: -1.0,
+            tone_map: ToneMap::Clamp,
+            transfer: Transfer::Gamma(1.0),
+        };
+
+        assert_eq!(transform.apply(Vector3::new(1.0, 0.5, 2.0)), [128, 64, 255]);
+    }
+
+    #[test]
+    fn srgb_transfer() {
+        assert_eq!(Transfer::Srgb.encode(0.0), 0.0);
+        assert_approx_eq!(Transfer::Srgb.encode(1.0), 1.0);
+        assert_approx_eq!(Transfer::Srgb.encode(0.18), 0.461_356, 1e-6);
+        assert_approx_eq!(Transfer::Srgb.decode(0.461_356), 0.18, 1e-6);
+        assert_approx_eq!(Transfer::Srgb.decode(Transfer::Srgb.encode(0.002)), 0.002);
+    }
+
+    #[test]
+    fn operators_stay_in_range() {
+        let bright = Vector3::new(50.0, 20.0, 10.0);
+
+        for &tone_map in &[ToneMap::Clamp, ToneMap::Reinhard, ToneMap::Aces] {
+            let mapped = tone_map.apply(bright);
+            assert!(mapped.x <= 1.0 && mapped.y <= 1.0 && mapped.z <= 1.0);
+        }
+
+        // Extended Reinhard reaches white exactly at the white point
+        let white = ToneMap::ExtendedReinhard { white: 4.0 }.apply(Vector3::new(4.0, 4.0, 4.0));
+        assert_approx_eq!(white.x, 1.0);
+        assert_approx_eq!(ToneMap::Reinhard.apply(Vector3::new(1.0, 1.0, 1.0)).y, 0.5);
+    }
+}
@@ -0,0 +1,250 @@
+use crate::math::Vector3;
+use std::ops::Mul;
+
+// Row-major matrices: `rows[r][c]` is the value at row `r`, column `c`, and vectors are
+// multiplied as columns on the right.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Matrix3 {
+    pub rows: [[f64; 3]; 3],
+}
+
+impl Matrix3 {
+    pub fn new(rows: [[f64; 3]; 3]) -> Matrix3 {
+        Matrix3 { rows }
+    }
+
+    pub fn identity() -> Matrix3 {
+        Matrix3::from_diagonal(Vector3::new(1.0, 1.0, 1.0))
+    }
+
+    pub fn from_diagonal(diagonal: Vector3) -> Matrix3 {
+        let mut rows = [[0.0; 3]; 3];
+
+        for (axis, row) in rows.iter_mut().enumerate() {
+            row[axis] = diagonal[axis];
+        }
+
+        Matrix3 { rows }
+    }
+
+    // A matrix with the three vectors as its columns
+    pub fn from_columns(x: Vector3, y: Vector3, z: Vector3) -> Matrix3 {
+        Matrix3::new([[x.x, y.x, z.x], [x.y, y.y, z.y], [x.z, y.z, z.z]])
+    }
+
+    pub fn transpose(&self) -> Matrix3 {
+        let mut rows = [[0.0; 3]; 3];
+
+        for (r, row) in self.rows.iter().enumerate() {
+            for (c, value) in row.iter().enumerate() {
+                rows[c][r] = *value;
+            }
+        }
+
+        Matrix3 { rows }
+    }
+
+    pub fn determinant(&self) -> f64 {
+        let m = &self.rows;
+
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    // The adjugate over the determinant, or `None` if the matrix flattens space and can't be
+    // undone
+    pub fn inverse(&self) -> Option<Matrix3> {
+        let determinant = self.determinant();
+
+        if determinant.abs() < 1e-12 {
+            return None;
+        }
+
+        let m = &self.rows;
+        let cofactor = |r0: usize, r1: usize, c0: usize, c1: usize| {
+            m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]
+        };
+
+        let adjugate = [
+            [
+                cofactor(1, 2, 1, 2),
+                -cofactor(0, 2, 1, 2),
+                cofactor(0, 1, 1, 2),
+            ],
+            [
+                -cofactor(1, 2, 0, 2),
+                cofactor(0, 2, 0, 2),
+                -cofactor(0, 1, 0, 2),
+            ],
+            [
+                cofactor(1, 2, 0, 1),
+                -cofactor(0, 2, 0, 1),
+                cofactor(0, 1, 0, 1),
+            ],
+        ];
+
+        let mut rows = [[0.0; 3]; 3];
+
+        for (row, adjugate_row) in rows.iter_mut().zip(&adjugate) {
+            for (value, adjugate_value) in row.iter_mut().zip(adjugate_row) {
+                *value = adjugate_value / determinant;
+            }
+        }
+
+        Some(Matrix3 { rows })
+    }
+}
+
+impl Mul<Matrix3> for Matrix3 {
+    type Output = Matrix3;
+
+    fn mul(self, rhs: Matrix3) -> Matrix3 {
+        let mut rows = [[0.0; 3]; 3];
+
+        for (r, row) in rows.iter_mut().enumerate() {
+            for (c, value) in row.iter_mut().enumerate() {
+                *value = (0..3).map(|k| self.rows[r][k] * rhs.rows[k][c]).sum();
+            }
+        }
+
+        Matrix3 { rows }
+    }
+}
+
+impl Mul<Vector3> for Matrix3 {
+    type Output = Vector3;
+
+    fn mul(self, rhs: Vector3) -> Vector3 {
+        let row = |r: &[f64; 3]| r[0] * rhs.x + r[1] * rhs.y + r[2] * rhs.z;
+        Vector3::new(row(&self.rows[0]), row(&self.rows[1]), row(&self.rows[2]))
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Matrix4 {
+    pub rows: [[f64; 4]; 4],
+}
+
+impl Matrix4 {
+    pub fn new(rows: [[f64; 4]; 4]) -> Matrix4 {
+        Matrix4 { rows }
+    }
+
+    pub fn identity() -> Matrix4 {
+        Matrix4::affine(Matrix3::identity(), Vector3::new(0.0, 0.0, 0.0))
+    }
+
+    // The matrix that applies `linear` and then moves everything by `translation`
+    pub fn affine(linear: Matrix3, translation: Vector3) -> Matrix4 {
+        let mut rows = [[0.0, 0.0, 0.0, 1.0]; 4];
+
+        for (r, row) in rows.iter_mut().take(3).enumerate() {
+            row[..3].copy_from_slice(&linear.rows[r]);
+            row[3] = translation[r];
+        }
+
+        Matrix4 { rows }
+    }
+
+    // The top left 3x3 part: everything but the translation (and projection)
+    pub fn linear(&self) -> Matrix3 {
+        let mut rows = [[0.0; 3]; 3];
+
+        for (row, full_row) in rows.iter_mut().zip(&self.rows) {
+            row.copy_from_slice(&full_row[..3]);
+        }
+
+        Matrix3 { rows }
+    }
+
+    pub fn translation(&self) -> Vector3 {
+        Vector3::new(self.rows[0][3], self.rows[1][3], self.rows[2][3])
+    }
+
+    pub fn is_affine(&self) -> bool {
+        self.rows[3] == [0.0, 0.0, 0.0, 1.0]
+    }
+
+    // Only affine matrices are inverted, which is all transforms need: the inverse undoes the
+    // translation first and then the linear part. `None` for anything else, or for matrices
+    // that flatten space.
+    pub fn inverse(&self) -> Option<Matrix4> {
+        if !self.is_affine() {
+            return None;
+        }
+
+        let linear = self.linear().inverse()?;
+        Some(Matrix4::affine(linear, -(linear * self.translation())))
+    }
+}
+
+impl Mul<Matrix4> for Matrix4 {
+    type Output = Matrix4;
+
+    fn mul(self, rhs: Matrix4) -> Matrix4 {
+        let mut rows = [[0.0; 4]; 4];
+
+        for (r, row) in rows.iter_mut().enumerate() {
+            for (c, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.rows[r][k] * rhs.rows[k][c]).sum();
+            }
+        }
+
+        Matrix4 { rows }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_identity(matrix: Matrix4) {
+        for (r, row) in matrix.rows.iter().enumerate() {
+            for (c, value) in row.iter().enumerate() {
+                let expected = if r == c { 1.0 } else { 0.0 };
+                assert!((value - expected).abs() < 1e-9, "{:?}", matrix);
+            }
+        }
+    }
+
+    #[test]
+    fn inverses() {
+        let matrix = Matrix4::new([
+            [2.0, 0.0, 1.0, 3.0],
+            [0.0, 1.0, 4.0, -1.0],
+            [1.0, -2.0, 0.0, 2.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+
+        assert_identity(matrix * matrix.inverse().unwrap());
+        assert_identity(matrix.inverse().unwrap() * matrix);
+
+        let linear = matrix.linear();
+        let inverse = Matrix4::affine(linear.inverse().unwrap(), Vector3::new(0.0, 0.0, 0.0));
+        assert_identity(Matrix4::affine(linear, Vector3::new(0.0, 0.0, 0.0)) * inverse);
+        assert_eq!(linear.determinant(), linear.transpose().determinant());
+
+        let flat = Matrix3::from_diagonal(Vector3::new(1.0, 0.0, 1.0));
+        assert!(flat.inverse().is_none());
+        assert!(Matrix4::affine(flat, Vector3::new(1.0, 2.0, 3.0))
+            .inverse()
+            .is_none());
+
+        let mut projective = matrix;
+        projective.rows[3][2] = 1.0;
+        assert!(projective.inverse().is_none());
+    }
+
+    #[test]
+    fn columns_and_vectors() {
+        let x = Vector3::new(0.0, 1.0, 0.0);
+        let y = Vector3::new(-1.0, 0.0, 0.0);
+        let z = Vector3::new(0.0, 0.0, 1.0);
+        let rotation = Matrix3::from_columns(x, y, z);
+
+        assert_eq!(rotation * Vector3::new(1.0, 0.0, 0.0), x);
+        assert_eq!(rotation * Vector3::new(0.0, 1.0, 0.0), y);
+        assert_eq!(rotation.transpose() * x, Vector3::new(1.0, 0.0, 0.0));
+    }
+}
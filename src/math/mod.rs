@@ -1,4 +1,17 @@
-#[derive(Copy, Clone, Debug)]
+use crate::sampler::Sampler;
+use std::f64::consts::PI;
+
+mod matrix;
+mod polynomial;
+mod quaternion;
+mod transform;
+
+pub use matrix::{Matrix3, Matrix4};
+pub use polynomial::{solve_quadratic, solve_quartic};
+pub use quaternion::Quaternion;
+pub use transform::{Pose, Transform};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Vector3 {
     pub x: f64,
     pub y: f64,
@@ -76,6 +89,20 @@ impl std::ops::Mul<Vector3> for Vector3 {
     }
 }
 
+// Components by axis: 0 is x, 1 is y and 2 is z
+impl std::ops::Index<usize> for Vector3 {
+    type Output = f64;
+
+    fn index(&self, axis: usize) -> &f64 {
+        match axis {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("axis {} out of range for a Vector3", axis),
+        }
+    }
+}
+
 impl std::ops::Neg for Vector3 {
     type Output = Self;
 
@@ -84,17 +111,40 @@ impl std::ops::Neg for Vector3 {
     }
 }
 
-pub fn random_in_unit_sphere() -> Vector3 {
-    loop {
-        let p = Vector3::new(rand::random(), rand::random(), rand::random()) * 2.0
-            - Vector3::new(1.0, 1.0, 1.0);
+// A uniformly distributed point in the unit sphere: a direction from two dimensions of the
+// sampler, and a distance from a third. The cube root accounts for there being more room
+// towards the surface than near the center.
+pub fn random_in_unit_sphere(sampler: &mut dyn Sampler) -> Vector3 {
+    let (u, v) = sampler.next_2d();
+    let z = 1.0 - 2.0 * u;
+    let radius = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * v;
 
-        if p.squared_length() <= 1.0 {
-            return p;
-        }
-    }
+    Vector3::new(radius * phi.cos(), radius * phi.sin(), z) * sampler.next_1d().cbrt()
+}
+
+// Rec. 709 luminance of a linear color, which shares its primaries with sRGB
+pub fn luminance(color: Vector3) -> f64 {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
 }
 
 pub fn reflect(vec_in: Vector3, normal: Vector3) -> Vector3 {
     vec_in - 2.0 * vec_in.dot(normal) * normal
 }
+
+// Two unit vectors perpendicular to `normal` and to each other, for shapes that need directions
+// along their surface. From "Building an Orthonormal Basis, Revisited" (Duff et al.).
+pub fn orthonormal_basis(normal: Vector3) -> (Vector3, Vector3) {
+    let sign = 1f64.copysign(normal.z);
+    let a = -1.0 / (sign + normal.z);
+    let b = normal.x * normal.y * a;
+
+    (
+        Vector3::new(
+            1.0 + sign * normal.x * normal.x * a,
+            sign * b,
+            -sign * normal.x,
+        ),
+        Vector3::new(b, sign + normal.y * normal.y * a, -normal.y),
+    )
+}
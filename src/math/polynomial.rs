@@ -0,0 +1,187 @@
+// Real roots of the polynomials that come up when intersecting rays with curved surfaces.
+// Roots are returned in increasing order.
+
+// The roots of a*x^2 + b*x + c. Written so that neither root comes from subtracting two
+// numbers that are almost equal, which loses most of the precision of the smaller root.
+//
+// A double root is returned twice, so a ray grazing a surface still goes in and out of it, but
+// when `a` is 0 there's just the one root of the line that's left.
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    if a == 0.0 {
+        if b == 0.0 {
+            return Vec::new();
+        }
+
+        return vec![-c / b];
+    }
+
+    let discriminant = b * b - 4.0 * a * c;
+
+    if discriminant < 0.0 {
+        return Vec::new();
+    }
+
+    let q = -0.5 * (b + discriminant.sqrt().copysign(b));
+
+    if q == 0.0 {
+        return vec![0.0, 0.0];
+    }
+
+    let (x0, x1) = (q / a, c / q);
+    vec![x0.min(x1), x0.max(x1)]
+}
+
+// The roots of a*x^4 + b*x^3 + c*x^2 + d*x + e, with Ferrari's method (as in "Solving Quartic
+// Equations", Schwarze, Graphics Gems I). The closed form can be quite far off for tori seen
+// from afar, so every root is then refined with a few steps of Newton's method.
+pub fn solve_quartic(a: f64, b: f64, c: f64, d: f64, e: f64) -> Vec<f64> {
+    if a == 0.0 {
+        return solve_cubic(b, c, d, e);
+    }
+
+    let (b, c, d, e) = (b / a, c / a, d / a, e / a);
+
+    // Substituting x = y - b/4 leaves y^4 + p*y^2 + q*y + r, without the cubic term
+    let b2 = b * b;
+    let p = -3.0 / 8.0 * b2 + c;
+    let q = b2 * b / 8.0 - b * c / 2.0 + d;
+    let r = -3.0 / 256.0 * b2 * b2 + b2 * c / 16.0 - b * d / 4.0 + e;
+
+    let mut roots = if is_zero(r) {
+        // y * (y^3 + p*y + q)
+        let mut roots = solve_cubic(1.0, 0.0, p, q);
+        roots.push(0.0);
+        roots
+    } else {
+        // Any root z of the resolvent cubic splits the quartic into two quadratics
+        let z = solve_cubic(1.0, -p / 2.0, -r, r * p / 2.0 - q * q / 8.0)[0];
+        let u = z * z - r;
+        let v = 2.0 * z - p;
+
+        if u < -1e-12 || v < -1e-12 {
+            return Vec::new();
+        }
+
+        let (u, v) = (u.max(0.0).sqrt(), v.max(0.0).sqrt().copysign(q));
+
+        [(v, z - u), (-v, z + u)]
+            .iter()
+            .flat_map(|&(linear, constant)| solve_quadratic(1.0, linear, constant))
+            .collect()
+    };
+
+    let polynomial = |x: f64| (((x + b) * x + c) * x + d) * x + e;
+    let derivative = |x: f64| ((4.0 * x + 3.0 * b) * x + 2.0 * c) * x + d;
+
+    for root in roots.iter_mut() {
+        *root -= b / 4.0;
+
+        for _ in 0..2 {
+            let slope = derivative(*root);
+
+            if slope != 0.0 {
+                *root -= polynomial(*root) / slope;
+            }
+        }
+    }
+
+    sorted(roots)
+}
+
+// The roots of a*x^3 + b*x^2 + c*x + d, of which there is always at least one
+fn solve_cubic(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    if a == 0.0 {
+        return solve_quadratic(b, c, d);
+    }
+
+    let (b, c, d) = (b / a, c / a, d / a);
+
+    // Substituting x = y - b/3 leaves y^3 + 3p*y + 2q
+    let b2 = b * b;
+    let p = (c - b2 / 3.0) / 3.0;
+    let q = (2.0 / 27.0 * b2 * b - b * c / 3.0 + d) / 2.0;
+    let p3 = p * p * p;
+    let discriminant = q * q + p3;
+
+    let roots = if is_zero(discriminant) {
+        if is_zero(q) {
+            vec![0.0]
+        } else {
+            let u = (-q).cbrt();
+            vec![2.0 * u, -u]
+        }
+    } else if discriminant < 0.0 {
+        // Three real roots, which are easier to find with trigonometry
+        let phi = (-q / (-p3).sqrt()).acos() / 3.0;
+        let t = 2.0 * (-p).sqrt();
+        let third = std::f64::consts::PI / 3.0;
+
+        vec![
+            t * phi.cos(),
+            -t * (phi + third).cos(),
+            -t * (phi - third).cos(),
+        ]
+    } else {
+        let root = discriminant.sqrt();
+        vec![(root - q).cbrt() - (root + q).cbrt()]
+    };
+
+    sorted(roots.into_iter().map(|y| y - b / 3.0).collect())
+}
+
+fn is_zero(x: f64) -> bool {
+    x.abs() < 1e-12
+}
+
+fn sorted(mut roots: Vec<f64>) -> Vec<f64> {
+    roots.sort_by(f64::total_cmp);
+    roots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_roots(roots: Vec<f64>, expected: &[f64]) {
+        assert_eq!(roots.len(), expected.len(), "{:?} != {:?}", roots, expected);
+
+        for (root, expected) in roots.iter().zip(expected) {
+            assert!(
+                (root - expected).abs() < 1e-9,
+                "{:?} != {:?}",
+                roots,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn solves_quadratics_without_losing_small_roots() {
+        assert_eq!(solve_quadratic(1.0, -3.0, 2.0), vec![1.0, 2.0]);
+        assert_eq!(solve_quadratic(1.0, 0.0, 1.0), Vec::<f64>::new());
+        assert_eq!(solve_quadratic(1.0, -2.0, 1.0), vec![1.0, 1.0]);
+        assert_eq!(solve_quadratic(0.0, 2.0, -1.0), vec![0.5]);
+
+        // x^2 - 1e8*x + 1, where the naive formula gets the small root badly wrong
+        let small = solve_quadratic(1.0, -1e8, 1.0)[0];
+        assert!((small - 1e-8).abs() < 1e-20);
+    }
+
+    #[test]
+    fn solves_quartics() {
+        // (x - 1)(x - 2)(x - 3)(x - 4)
+        assert_roots(
+            solve_quartic(1.0, -10.0, 35.0, -50.0, 24.0),
+            &[1.0, 2.0, 3.0, 4.0],
+        );
+        // (x^2 - 4)(x^2 + 1)
+        assert_roots(solve_quartic(2.0, 0.0, -6.0, 0.0, -8.0), &[-2.0, 2.0]);
+        // x^4 + 1
+        assert_roots(solve_quartic(1.0, 0.0, 0.0, 0.0, 1.0), &[]);
+        // x(x - 1)(x + 2)(x - 5)
+        assert_roots(
+            solve_quartic(1.0, -4.0, -7.0, 10.0, 0.0),
+            &[-2.0, 0.0, 1.0, 5.0],
+        );
+    }
+}
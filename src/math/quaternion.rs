@@ -0,0 +1,173 @@
+use crate::math::{Matrix3, Vector3};
+use std::ops::Mul;
+
+// Unit quaternions, for rotations: `w` is the cosine of half the angle, and (`x`, `y`, `z`) the
+// axis scaled by the sine of half the angle. Unlike matrices they can be blended smoothly and
+// don't drift away from being rotations when many of them are combined.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Quaternion {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Quaternion {
+    // Counterclockwise around `axis` when looking at it from its tip
+    pub fn from_axis_angle(axis: Vector3, degrees: f64) -> Quaternion {
+        let axis = axis.unit();
+        let (sin, cos) = (degrees.to_radians() / 2.0).sin_cos();
+
+        Quaternion {
+            w: cos,
+            x: axis.x * sin,
+            y: axis.y * sin,
+            z: axis.z * sin,
+        }
+    }
+
+    // Rotates around x first, then around y, then around z, all in degrees and around the fixed
+    // world axes
+    pub fn from_euler(degrees: Vector3) -> Quaternion {
+        Quaternion::from_axis_angle(Vector3::new(0.0, 0.0, 1.0), degrees.z)
+            * Quaternion::from_axis_angle(Vector3::new(0.0, 1.0, 0.0), degrees.y)
+            * Quaternion::from_axis_angle(Vector3::new(1.0, 0.0, 0.0), degrees.x)
+    }
+
+    pub fn normalized(&self) -> Quaternion {
+        let length = (self.w * self.w + self.x * self.x + self.y * self.y + self.z * self.z).sqrt();
+
+        Quaternion {
+            w: self.w / length,
+            x: self.x / length,
+            y: self.y / length,
+            z: self.z / length,
+        }
+    }
+
+    // Turns from `self` towards `other` at a steady rate, `t` going from 0 to 1, and along the
+    // shorter way around
+    pub fn slerp(self, other: Quaternion, t: f64) -> Quaternion {
+        let (a, mut b) = (self.normalized(), other.normalized());
+        let mut cos = a.w * b.w + a.x * b.x + a.y * b.y + a.z * b.z;
+
+        // `b` and `-b` are the same rotation, from opposite sides
+        if cos < 0.0 {
+            b = Quaternion {
+                w: -b.w,
+                x: -b.x,
+                y: -b.y,
+                z: -b.z,
+            };
+            cos = -cos;
+        }
+
+        // Nearly the same rotation, where the angle between them is too small to divide by
+        let (wa, wb) = if cos > 0.9995 {
+            (1.0 - t, t)
+        } else {
+            let angle = cos.acos();
+            let sin = angle.sin();
+            (((1.0 - t) * angle).sin() / sin, (t * angle).sin() / sin)
+        };
+
+        Quaternion {
+            w: wa * a.w + wb * b.w,
+            x: wa * a.x + wb * b.x,
+            y: wa * a.y + wb * b.y,
+            z: wa * a.z + wb * b.z,
+        }
+        .normalized()
+    }
+
+    pub fn to_matrix(self) -> Matrix3 {
+        let Quaternion { w, x, y, z } = self.normalized();
+
+        Matrix3::new([
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - w * z),
+                2.0 * (x * z + w * y),
+            ],
+            [
+                2.0 * (x * y + w * z),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - w * x),
+            ],
+            [
+                2.0 * (x * z - w * y),
+                2.0 * (y * z + w * x),
+                1.0 - 2.0 * (x * x + y * y),
+            ],
+        ])
+    }
+}
+
+// `a * b` rotates by `b` first, and then by `a`
+impl Mul<Quaternion> for Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, rhs: Quaternion) -> Quaternion {
+        Quaternion {
+            w: self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+            x: self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            y: self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            z: self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Vector3, b: Vector3) {
+        assert!((a - b).magnitude() < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn rotations() {
+        let quarter_turn = Quaternion::from_axis_angle(Vector3::new(0.0, 0.0, 2.0), 90.0);
+        assert_close(
+            quarter_turn.to_matrix() * Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+        );
+
+        let half_turn = quarter_turn * quarter_turn;
+        assert_close(
+            half_turn.to_matrix() * Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(-1.0, 0.0, 0.0),
+        );
+        assert_close(
+            half_turn.to_matrix() * Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(0.0, 0.0, 1.0),
+        );
+    }
+
+    #[test]
+    fn slerp_turns_halfway_the_short_way() {
+        let from = Quaternion::from_axis_angle(Vector3::new(0.0, 0.0, 1.0), 0.0);
+        let to = Quaternion::from_axis_angle(Vector3::new(0.0, 0.0, 1.0), 270.0);
+
+        // 270 degrees one way is 90 the other
+        assert_close(
+            from.slerp(to, 0.5).to_matrix() * Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(2f64.sqrt() / 2.0, -(2f64.sqrt()) / 2.0, 0.0),
+        );
+        assert_close(
+            from.slerp(to, 1.0).to_matrix() * Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, -1.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn euler_angles_rotate_around_x_then_y_then_z() {
+        let rotation = Quaternion::from_euler(Vector3::new(90.0, 90.0, 0.0));
+
+        // x takes y to z, and then y takes z to x
+        assert_close(
+            rotation.to_matrix() * Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+        );
+    }
+}
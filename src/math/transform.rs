@@ -0,0 +1,257 @@
+use crate::math::{orthonormal_basis, Matrix3, Matrix4, Quaternion, Vector3};
+
+// An affine transform, kept along with its inverse so neither has to be computed more than once.
+// Transforms are chained with `then`, which applies them in the order they are written.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transform {
+    matrix: Matrix4,
+    inverse: Matrix4,
+}
+
+impl Transform {
+    pub fn identity() -> Transform {
+        Transform {
+            matrix: Matrix4::identity(),
+            inverse: Matrix4::identity(),
+        }
+    }
+
+    // Any affine matrix that can be inverted
+    pub fn from_matrix(matrix: Matrix4) -> Option<Transform> {
+        Some(Transform {
+            matrix,
+            inverse: matrix.inverse()?,
+        })
+    }
+
+    pub fn translation(offset: Vector3) -> Transform {
+        Transform {
+            matrix: Matrix4::affine(Matrix3::identity(), offset),
+            inverse: Matrix4::affine(Matrix3::identity(), -offset),
+        }
+    }
+
+    // Scales are expected not to be zero, which would flatten everything
+    pub fn scaling(scale: Vector3) -> Transform {
+        let inverse = Vector3::new(1.0 / scale.x, 1.0 / scale.y, 1.0 / scale.z);
+        let origin = Vector3::new(0.0, 0.0, 0.0);
+
+        Transform {
+            matrix: Matrix4::affine(Matrix3::from_diagonal(scale), origin),
+            inverse: Matrix4::affine(Matrix3::from_diagonal(inverse), origin),
+        }
+    }
+
+    // Counterclockwise around `axis` when looking at it from its tip
+    pub fn rotation(axis: Vector3, degrees: f64) -> Transform {
+        Transform::from_quaternion(Quaternion::from_axis_angle(axis, degrees))
+    }
+
+    // Around x, then y, then z, see `Quaternion::from_euler`
+    pub fn rotation_euler(degrees: Vector3) -> Transform {
+        Transform::from_quaternion(Quaternion::from_euler(degrees))
+    }
+
+    // Rotations are orthonormal, so their inverse is just their transpose
+    pub fn from_quaternion(rotation: Quaternion) -> Transform {
+        let rotation = rotation.to_matrix();
+        let origin = Vector3::new(0.0, 0.0, 0.0);
+
+        Transform {
+            matrix: Matrix4::affine(rotation, origin),
+            inverse: Matrix4::affine(rotation.transpose(), origin),
+        }
+    }
+
+    // Places something at `from`, looking towards `to`, with `up` pointing as close to up as
+    // possible. Like cameras, it looks down its own -z axis, with y up and x to the right.
+    pub fn look_at(from: Vector3, to: Vector3, up: Vector3) -> Transform {
+        let back = (from - to).unit();
+        let right = up.cross(back).unit();
+        let up = back.cross(right);
+
+        let rotation = Matrix3::from_columns(right, up, back);
+        let inverse = rotation.transpose();
+
+        Transform {
+            matrix: Matrix4::affine(rotation, from),
+            inverse: Matrix4::affine(inverse, -(inverse * from)),
+        }
+    }
+
+    // Places something at `origin` with its own z axis along `axis`, for shapes built around
+    // the z axis. How they're turned around that axis is up to `orthonormal_basis`.
+    pub fn along(origin: Vector3, axis: Vector3) -> Transform {
+        let axis = axis.unit();
+        let (tangent, bitangent) = orthonormal_basis(axis);
+
+        let rotation = Matrix3::from_columns(tangent, bitangent, axis);
+        let inverse = rotation.transpose();
+
+        Transform {
+            matrix: Matrix4::affine(rotation, origin),
+            inverse: Matrix4::affine(inverse, -(inverse * origin)),
+        }
+    }
+
+    // This transform, followed by `next`
+    pub fn then(&self, next: &Transform) -> Transform {
+        Transform {
+            matrix: next.matrix * self.matrix,
+            inverse: self.inverse * next.inverse,
+        }
+    }
+
+    pub fn inverse(&self) -> Transform {
+        Transform {
+            matrix: self.inverse,
+            inverse: self.matrix,
+        }
+    }
+
+    pub fn point(&self, point: Vector3) -> Vector3 {
+        self.matrix.linear() * point + self.matrix.translation()
+    }
+
+    // Directions and offsets, which translations leave alone
+    pub fn vector(&self, vector: Vector3) -> Vector3 {
+        self.matrix.linear() * vector
+    }
+
+    // Normals have to stay perpendicular to the surface, so they go through the transpose of
+    // the inverse instead (and may need to be made unit length again afterwards)
+    pub fn normal(&self, normal: Vector3) -> Vector3 {
+        self.inverse.linear().transpose() * normal
+    }
+}
+
+// A scale, followed by a rotation and then a translation, kept apart so two of them can be
+// blended part by part. Blending the matrices instead would shrink and skew whatever rotates.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Pose {
+    pub scale: Vector3,
+    pub rotation: Quaternion,
+    pub translation: Vector3,
+}
+
+impl Pose {
+    pub fn identity() -> Pose {
+        Pose {
+            scale: Vector3::new(1.0, 1.0, 1.0),
+            rotation: Quaternion::from_axis_angle(Vector3::new(0.0, 0.0, 1.0), 0.0),
+            translation: Vector3::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    pub fn transform(&self) -> Transform {
+        Transform::scaling(self.scale)
+            .then(&Transform::from_quaternion(self.rotation))
+            .then(&Transform::translation(self.translation))
+    }
+
+    // `self` when `t` is 0, `other` when it's 1, and somewhere along the way in between
+    pub fn interpolate(&self, other: &Pose, t: f64) -> Pose {
+        Pose {
+            scale: self.scale + (other.scale - self.scale) * t,
+            rotation: self.rotation.slerp(other.rotation, t),
+            translation: self.translation + (other.translation - self.translation) * t,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    fn assert_close(a: Vector3, b: Vector3) {
+        assert!((a - b).magnitude() < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn transforms_compose_and_invert() {
+        let transform = Transform::scaling(Vector3::new(2.0, 1.0, 1.0))
+            .then(&Transform::rotation(Vector3::new(0.0, 0.0, 1.0), 90.0))
+            .then(&Transform::translation(Vector3::new(0.0, 0.0, 5.0)));
+
+        let point = transform.point(Vector3::new(1.0, 0.0, 0.0));
+        assert_close(point, Vector3::new(0.0, 2.0, 5.0));
+        assert_close(
+            transform.inverse().point(point),
+            Vector3::new(1.0, 0.0, 0.0),
+        );
+
+        // Translations don't move vectors
+        assert_close(
+            transform.vector(Vector3::new(1.0, 0.0, 0.0)),
+            Vector3::new(0.0, 2.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn normals_stay_perpendicular() {
+        // Squashing a 45 degree slope makes it steeper, and its normal flatter
+        let transform = Transform::scaling(Vector3::new(1.0, 0.5, 1.0));
+        let tangent = transform.vector(Vector3::new(1.0, 1.0, 0.0));
+        let normal = transform.normal(Vector3::new(-1.0, 1.0, 0.0));
+
+        assert_approx_eq!(tangent.dot(normal), 0.0);
+    }
+
+    #[test]
+    fn look_at_points_down_negative_z() {
+        let from = Vector3::new(1.0, 2.0, 3.0);
+        let transform = Transform::look_at(
+            from,
+            Vector3::new(1.0, 2.0, -7.0),
+            Vector3::new(0.0, 1.0, 0.0),
+        );
+
+        assert_close(transform.point(Vector3::new(0.0, 0.0, 0.0)), from);
+        assert_close(
+            transform.vector(Vector3::new(0.0, 0.0, -1.0)),
+            Vector3::new(0.0, 0.0, -1.0),
+        );
+        assert_close(transform.inverse().point(from), Vector3::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn poses_blend_their_parts_separately() {
+        let start = Pose::identity();
+        let end = Pose {
+            scale: Vector3::new(3.0, 3.0, 3.0),
+            rotation: Quaternion::from_axis_angle(Vector3::new(0.0, 0.0, 1.0), 180.0),
+            translation: Vector3::new(0.0, 0.0, 4.0),
+        };
+
+        // Halfway there it's twice as big, has turned a quarter and moved up by 2
+        let halfway = start.interpolate(&end, 0.5).transform();
+        assert_close(
+            halfway.point(Vector3::new(1.0, 0.0, 0.0)),
+            Vector3::new(0.0, 2.0, 2.0),
+        );
+        assert_close(
+            halfway.inverse().point(Vector3::new(0.0, 2.0, 2.0)),
+            Vector3::new(1.0, 0.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn from_matrix_needs_an_invertible_affine_matrix() {
+        let matrix = Matrix4::new([
+            [0.0, -1.0, 0.0, 1.0],
+            [2.0, 0.0, 0.0, 2.0],
+            [0.0, 0.0, 1.0, 3.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        let transform = Transform::from_matrix(matrix).unwrap();
+        let point = Vector3::new(4.0, 5.0, 6.0);
+
+        assert_close(transform.point(point), Vector3::new(-4.0, 10.0, 9.0));
+        assert_close(transform.inverse().point(transform.point(point)), point);
+
+        let mut flat = matrix;
+        flat.rows[2][2] = 0.0;
+        assert!(Transform::from_matrix(flat).is_none());
+    }
+}
@@ -0,0 +1,120 @@
+// A small PCG (permuted congruential) generator: https://www.pcg-random.org
+//
+// Every random decision of a render goes through one of these, instead of the thread-local
+// generator behind `rand::random()`, so a render can be reproduced from its seed. Generators
+// are cheap to create, which lets every sample of every pixel get its own stream, derived from
+// the render seed, the pixel coordinates and the number of the sample. That way the image
+// doesn't depend on which thread rendered which pixel, in which order, or in how many passes.
+#[derive(Clone, Debug)]
+pub struct Random {
+    state: u64,
+    increment: u64,
+}
+
+const MULTIPLIER: u64 = 6_364_136_223_846_793_005;
+
+impl Random {
+    pub fn new(seed: u64) -> Random {
+        Random::with_stream(seed, 0)
+    }
+
+    // Each stream is a different sequence for the same seed
+    pub fn with_stream(seed: u64, stream: u64) -> Random {
+        let mut random = Random {
+            state: 0,
+            increment: (stream << 1) | 1,
+        };
+        random.next();
+        random.state = random.state.wrapping_add(mix(seed));
+        random.next();
+        random
+    }
+
+    pub fn for_sample(seed: u64, x: usize, y: usize, sample: usize) -> Random {
+        Random::with_stream(
+            mix(seed).wrapping_add(sample as u64),
+            mix((x as u64) << 32 | y as u64),
+        )
+    }
+
+    fn next(&mut self) -> u32 {
+        let state = self.state;
+        self.state = state.wrapping_mul(MULTIPLIER).wrapping_add(self.increment);
+
+        let xorshifted = (((state >> 18) ^ state) >> 27) as u32;
+        let rotation = (state >> 59) as u32;
+        xorshifted.rotate_right(rotation)
+    }
+
+    // A uniformly distributed number in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        let bits = (u64::from(self.next()) << 32 | u64::from(self.next())) >> 11;
+        bits as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+}
+
+impl rand::RngCore for Random {
+    fn next_u32(&mut self) -> u32 {
+        self.next()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        u64::from(self.next()) << 32 | u64::from(self.next())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            let bytes = self.next().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+// SplitMix64's finalizer, so that similar seeds (or neighbouring pixels) end up with unrelated
+// sequences
+pub fn mix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let mut a = Random::for_sample(42, 3, 7, 0);
+        let mut b = Random::for_sample(42, 3, 7, 0);
+        let mut other = Random::for_sample(42, 7, 3, 0);
+
+        let sequence: Vec<f64> = (0..16).map(|_| a.next_f64()).collect();
+        assert_eq!(sequence, (0..16).map(|_| b.next_f64()).collect::<Vec<_>>());
+        assert_ne!(
+            sequence,
+            (0..16).map(|_| other.next_f64()).collect::<Vec<_>>()
+        );
+
+        let mut next_sample = Random::for_sample(42, 3, 7, 1);
+        assert_ne!(
+            sequence,
+            (0..16).map(|_| next_sample.next_f64()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn floats_are_in_the_unit_interval() {
+        let mut random = Random::new(0);
+
+        for _ in 0..10_000 {
+            let n = random.next_f64();
+            assert!((0.0..1.0).contains(&n));
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use crate::math::Vector3;
+
+// An axis-aligned bounding box: everything between `min` and `max` on every axis. A box with
+// `min` above `max` is empty, which is what surrounding nothing gives.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vector3,
+    pub max: Vector3,
+}
+
+impl Aabb {
+    pub fn new(min: Vector3, max: Vector3) -> Aabb {
+        Aabb { min, max }
+    }
+
+    pub fn empty() -> Aabb {
+        Aabb::new(
+            Vector3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            Vector3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+        )
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    // The smallest box containing both boxes
+    pub fn surrounding(&self, other: &Aabb) -> Aabb {
+        Aabb::new(
+            Vector3::new(
+                self.min.x.min(other.min.x),
+                self.min.y.min(other.min.y),
+                self.min.z.min(other.min.z),
+            ),
+            Vector3::new(
+                self.max.x.max(other.max.x),
+                self.max.y.max(other.max.y),
+                self.max.z.max(other.max.z),
+            ),
+        )
+    }
+
+    // The part both boxes have in common, which is empty if they don't touch
+    pub fn overlap(&self, other: &Aabb) -> Aabb {
+        Aabb::new(
+            Vector3::new(
+                self.min.x.max(other.min.x),
+                self.min.y.max(other.min.y),
+                self.min.z.max(other.min.z),
+            ),
+            Vector3::new(
+                self.max.x.min(other.max.x),
+                self.max.y.min(other.max.y),
+                self.max.z.min(other.max.z),
+            ),
+        )
+    }
+
+    pub fn center(&self) -> Vector3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn diagonal(&self) -> Vector3 {
+        self.max - self.min
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        if self.is_empty() {
+            return 0.0;
+        }
+
+        let d = self.diagonal();
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    // Flat shapes have boxes with no thickness, which rays running along them could miss, so
+    // every axis is grown to at least `thickness`
+    pub fn padded(&self, thickness: f64) -> Aabb {
+        let pad = |min: f64, max: f64| {
+            let missing = (thickness - (max - min)).max(0.0) / 2.0;
+            (min - missing, max + missing)
+        };
+        let (min_x, max_x) = pad(self.min.x, self.max.x);
+        let (min_y, max_y) = pad(self.min.y, self.max.y);
+        let (min_z, max_z) = pad(self.min.z, self.max.z);
+
+        Aabb::new(
+            Vector3::new(min_x, min_y, min_z),
+            Vector3::new(max_x, max_y, max_z),
+        )
+    }
+
+    // Whether a ray crosses the box between `t_min` and `t_max`, with the slab method: the ray
+    // is inside the box wherever it is between the two planes of every axis at once. Takes
+    // the inverse of the ray direction, which is the same for every box a ray is tested against.
+    pub fn hit(&self, origin: Vector3, inverse_direction: Vector3, t_min: f64, t_max: f64) -> bool {
+        let mut t_min = t_min;
+        let mut t_max = t_max;
+
+        for axis in 0..3 {
+            let mut t0 = (self.min[axis] - origin[axis]) * inverse_direction[axis];
+            let mut t1 = (self.max[axis] - origin[axis]) * inverse_direction[axis];
+
+            if inverse_direction[axis] < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            // `max` and `min` ignore the NaN of a ray parallel to, and right on, a slab
+            t_min = t_min.max(t0);
+            t_max = t_max.min(t1);
+
+            if t_max < t_min {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn surrounding_boxes() {
+        let a = Aabb::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        let b = Aabb::new(Vector3::new(-1.0, 0.5, 0.5), Vector3::new(0.5, 2.0, 0.5));
+        let both = a.surrounding(&b);
+
+        assert_eq!(both.min, Vector3::new(-1.0, 0.0, 0.0));
+        assert_eq!(both.max, Vector3::new(1.0, 2.0, 1.0));
+        assert_eq!(both.center(), Vector3::new(0.0, 1.0, 0.5));
+
+        // Nothing is the identity
+        assert!(Aabb::empty().is_empty());
+        assert_eq!(Aabb::empty().surrounding(&a), a);
+        assert_eq!(a.surface_area(), 6.0);
+    }
+
+    #[test]
+    fn ray_intersection() {
+        let unit = Aabb::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        let inverse = |x: f64, y: f64, z: f64| Vector3::new(1.0 / x, 1.0 / y, 1.0 / z);
+        let origin = Vector3::new(-1.0, 0.5, 0.5);
+
+        assert!(unit.hit(origin, inverse(1.0, 0.0, 0.0), 0.0, f64::MAX));
+        assert!(!unit.hit(origin, inverse(-1.0, 0.0, 0.0), 0.0, f64::MAX));
+        assert!(!unit.hit(origin, inverse(1.0, 0.0, 0.0), 0.0, 0.5));
+        assert!(!unit.hit(origin, inverse(1.0, 1.0, 0.0), 0.0, f64::MAX));
+    }
+}
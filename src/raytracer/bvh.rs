@@ -0,0 +1,534 @@
+use crate::math::Vector3;
+use crate::raytracer::csg::combine;
+use crate::raytracer::{Aabb, Hit, Hitable, Operation, Ray, Span};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+// A bounding volume hierarchy: a binary tree of boxes, each one around everything below it, so
+// a ray only has to be tested against the objects whose boxes it goes through.
+//
+// The tree is built top down, splitting the objects of each node where the surface area
+// heuristic says rays will be cheapest to trace: a ray hits a box with a chance proportional to
+// its surface area, so good splits keep big boxes with few objects in them. The nodes are then
+// laid out in a single array, depth first, with every node followed by its first child, which
+// keeps traversal from jumping around in memory.
+//
+// Objects without a bounding box (like infinite planes) can't go in the tree, and are tested
+// against every ray on their own.
+pub struct Bvh {
+    tree: Tree,
+    // In the order the leaves refer to them
+    objects: Vec<Box<dyn Hitable + Sync>>,
+    unbounded: Vec<Box<dyn Hitable + Sync>>,
+}
+
+// The tree itself, which only knows the bounding boxes of what's in it: its leaves refer to
+// primitives by their index, and whoever built it finds out if a ray hits them. That way a mesh
+// can keep its triangles as plain indices into its vertices instead of one object each.
+pub struct Tree {
+    nodes: Vec<Node>,
+    stats: BvhStats,
+}
+
+struct Node {
+    bounds: Aabb,
+    // For leaves, where their objects start. For inner nodes, where their second child is.
+    offset: usize,
+    // How many objects a leaf has, 0 for inner nodes
+    count: usize,
+    // The axis inner nodes are split on
+    axis: usize,
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct BvhStats {
+    pub objects: usize,
+    pub unbounded: usize,
+    pub nodes: usize,
+    pub leaves: usize,
+    pub depth: usize,
+    pub largest_leaf: usize,
+    pub build_time: Duration,
+}
+
+impl fmt::Display for BvhStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} objects in {} nodes, {} of them leaves with up to {} objects, {} levels deep, \
+             built in {:.2?}",
+            self.objects, self.nodes, self.leaves, self.largest_leaf, self.depth, self.build_time
+        )?;
+
+        if self.unbounded > 0 {
+            write!(
+                f,
+                " ({} unbounded objects outside the tree)",
+                self.unbounded
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+// Leaves never hold more objects than this, and nodes with this many or fewer only get split if
+// the heuristic says it's worth it
+const MAX_LEAF_SIZE: usize = 4;
+// Candidate splits are placed between buckets of the centers of the objects
+const BUCKETS: usize = 16;
+// How expensive visiting a node is compared to testing an object
+const TRAVERSAL_COST: f64 = 0.125;
+// Clustered objects can make the heuristic split off only a few of them at a time, so deeper
+// than this the tree just splits in halves, to keep building from taking quadratic time
+const MAX_SAH_DEPTH: usize = 48;
+
+struct Primitive {
+    index: usize,
+    bounds: Aabb,
+    center: Vector3,
+}
+
+impl Bvh {
+    pub fn new(objects: Vec<Box<dyn Hitable + Sync>>) -> Bvh {
+        let mut bounded = Vec::new();
+        let mut unbounded = Vec::new();
+
+        for object in objects {
+            match object.bounding_box() {
+                Some(bounds) => bounded.push((object, bounds)),
+                None => unbounded.push(object),
+            }
+        }
+
+        let bounds: Vec<_> = bounded.iter().map(|(_, bounds)| *bounds).collect();
+        let (mut tree, order) = Tree::new(&bounds);
+        tree.stats.unbounded = unbounded.len();
+
+        // Put the objects in the order the leaves expect
+        let mut slots: Vec<_> = bounded
+            .into_iter()
+            .map(|(object, _)| Some(object))
+            .collect();
+        let objects = order
+            .iter()
+            .map(|&index| slots[index].take().expect("every object is in one leaf"))
+            .collect();
+
+        Bvh {
+            tree,
+            objects,
+            unbounded,
+        }
+    }
+
+    pub fn stats(&self) -> &BvhStats {
+        &self.tree.stats
+    }
+}
+
+impl Tree {
+    // Builds the tree over primitives with the given bounding boxes. Also returns the order the
+    // primitives have to be put in, since every leaf refers to a run of consecutive ones.
+    pub fn new(bounds: &[Aabb]) -> (Tree, Vec<usize>) {
+        let start = Instant::now();
+
+        let mut primitives: Vec<_> = bounds
+            .iter()
+            .enumerate()
+            .map(|(index, bounds)| Primitive {
+                index,
+                bounds: *bounds,
+                center: bounds.center(),
+            })
+            .collect();
+
+        let mut builder = Builder {
+            nodes: Vec::new(),
+            order: Vec::with_capacity(primitives.len()),
+            stats: BvhStats::default(),
+        };
+
+        if !primitives.is_empty() {
+            builder.build(&mut primitives, 1);
+        }
+
+        let mut stats = builder.stats;
+        stats.objects = builder.order.len();
+        stats.nodes = builder.nodes.len();
+        stats.build_time = start.elapsed();
+
+        let tree = Tree {
+            nodes: builder.nodes,
+            stats,
+        };
+
+        (tree, builder.order)
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        self.nodes
+            .first()
+            .map_or_else(Aabb::empty, |root| root.bounds)
+    }
+
+    // Finds the closest hit with the primitives in the tree, where `hit` tests the primitive at
+    // an index (in the order given by `new`) against the ray, up to a `t_max`
+    pub fn check_hit<'a, F>(&self, ray: Ray, t_min: f64, t_max: f64, mut hit: F) -> Option<Hit<'a>>
+    where
+        F: FnMut(usize, f64) -> Option<Hit<'a>>,
+    {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let mut closest_hit: Option<Hit> = None;
+        let mut closest = t_max;
+
+        let direction = ray.direction;
+        let inverse_direction =
+            Vector3::new(1.0 / direction.x, 1.0 / direction.y, 1.0 / direction.z);
+
+        // The tree has no depth limit, so neither can the nodes left to visit
+        let mut stack = Vec::new();
+        let mut index = 0;
+
+        loop {
+            let node = &self.nodes[index];
+
+            if node
+                .bounds
+                .hit(ray.origin, inverse_direction, t_min, closest)
+            {
+                if node.count > 0 {
+                    for primitive in node.offset..node.offset + node.count {
+                        if let Some(hit) = hit(primitive, closest) {
+                            closest = hit.t;
+                            closest_hit = Some(hit);
+                        }
+                    }
+                } else {
+                    // Visit the child closer to the ray first, so hits there can cut the
+                    // search in the other one short
+                    let (near, far) = if direction[node.axis] < 0.0 {
+                        (node.offset, index + 1)
+                    } else {
+                        (index + 1, node.offset)
+                    };
+
+                    stack.push(far);
+                    index = near;
+                    continue;
+                }
+            }
+
+            match stack.pop() {
+                Some(next) => index = next,
+                None => break,
+            }
+        }
+
+        closest_hit
+    }
+}
+
+struct Builder {
+    nodes: Vec<Node>,
+    order: Vec<usize>,
+    stats: BvhStats,
+}
+
+impl Builder {
+    // Adds the subtree for `primitives` and returns the index of its root
+    fn build(&mut self, primitives: &mut [Primitive], depth: usize) -> usize {
+        let bounds = primitives.iter().fold(Aabb::empty(), |bounds, primitive| {
+            bounds.surrounding(&primitive.bounds)
+        });
+        let centers = primitives.iter().fold(Aabb::empty(), |centers, primitive| {
+            centers.surrounding(&Aabb::new(primitive.center, primitive.center))
+        });
+
+        // Split along the axis the centers are most spread on
+        let extent = centers.diagonal();
+        let axis = if extent.x >= extent.y && extent.x >= extent.z {
+            0
+        } else if extent.y >= extent.z {
+            1
+        } else {
+            2
+        };
+
+        // Objects all centered on the same point can't be told apart
+        if primitives.len() == 1 || extent[axis] <= 0.0 {
+            return self.leaf(bounds, primitives, depth);
+        }
+
+        let middle = if depth > MAX_SAH_DEPTH {
+            None
+        } else {
+            self.surface_area_split(primitives, &bounds, &centers, axis)
+        };
+
+        let middle = match middle {
+            Some(0) => return self.leaf(bounds, primitives, depth),
+            Some(middle) => middle,
+            None => {
+                let middle = primitives.len() / 2;
+                primitives.select_nth_unstable_by(middle, |a, b| {
+                    a.center[axis].total_cmp(&b.center[axis])
+                });
+                middle
+            }
+        };
+
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            bounds,
+            offset: 0,
+            count: 0,
+            axis,
+        });
+
+        let (first, second) = primitives.split_at_mut(middle);
+        self.build(first, depth + 1);
+        self.nodes[index].offset = self.build(second, depth + 1);
+
+        index
+    }
+
+    // Sorts `primitives` into buckets along `axis`, and finds the cheapest split between them.
+    // Returns how many primitives go on the first side after partitioning them, `Some(0)` if a
+    // leaf is cheaper than any split, or `None` if no split separates them.
+    fn surface_area_split(
+        &self,
+        primitives: &mut [Primitive],
+        bounds: &Aabb,
+        centers: &Aabb,
+        axis: usize,
+    ) -> Option<usize> {
+        let bucket_of = |primitive: &Primitive| {
+            let offset = (primitive.center[axis] - centers.min[axis])
+                / (centers.max[axis] - centers.min[axis]);
+            ((offset * BUCKETS as f64) as usize).min(BUCKETS - 1)
+        };
+
+        let mut counts = [0usize; BUCKETS];
+        let mut boxes = [Aabb::empty(); BUCKETS];
+
+        for primitive in primitives.iter() {
+            let bucket = bucket_of(primitive);
+            counts[bucket] += 1;
+            boxes[bucket] = boxes[bucket].surrounding(&primitive.bounds);
+        }
+
+        // The cost of splitting after each bucket: sweep from the left for the first side, and
+        // from the right for the second one
+        let mut costs = [0.0; BUCKETS - 1];
+        let mut below = (Aabb::empty(), 0);
+
+        for split in 0..BUCKETS - 1 {
+            below = (below.0.surrounding(&boxes[split]), below.1 + counts[split]);
+            costs[split] = below.0.surface_area() * below.1 as f64;
+        }
+
+        let mut above = (Aabb::empty(), 0);
+
+        for split in (0..BUCKETS - 1).rev() {
+            above = (
+                above.0.surrounding(&boxes[split + 1]),
+                above.1 + counts[split + 1],
+            );
+            costs[split] += above.0.surface_area() * above.1 as f64;
+        }
+
+        let (best_split, best_cost) = costs
+            .iter()
+            .map(|cost| TRAVERSAL_COST + cost / bounds.surface_area().max(f64::MIN_POSITIVE))
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(&b.1))?;
+
+        let leaf_cost = primitives.len() as f64;
+
+        if primitives.len() <= MAX_LEAF_SIZE && leaf_cost <= best_cost {
+            return Some(0);
+        }
+
+        // Partition in place: everything up to and including the best bucket goes first
+        let mut middle = 0;
+
+        for i in 0..primitives.len() {
+            if bucket_of(&primitives[i]) <= best_split {
+                primitives.swap(i, middle);
+                middle += 1;
+            }
+        }
+
+        if middle == 0 || middle == primitives.len() {
+            None
+        } else {
+            Some(middle)
+        }
+    }
+
+    fn leaf(&mut self, bounds: Aabb, primitives: &mut [Primitive], depth: usize) -> usize {
+        // Too many objects on the same spot still have to be split up, even if in no
+        // particular order
+        if primitives.len() > MAX_LEAF_SIZE {
+            let index = self.nodes.len();
+            self.nodes.push(Node {
+                bounds,
+                offset: 0,
+                count: 0,
+                axis: 0,
+            });
+
+            let (first, second) = primitives.split_at_mut(primitives.len() / 2);
+            self.build(first, depth + 1);
+            self.nodes[index].offset = self.build(second, depth + 1);
+
+            return index;
+        }
+
+        self.stats.leaves += 1;
+        self.stats.depth = self.stats.depth.max(depth);
+        self.stats.largest_leaf = self.stats.largest_leaf.max(primitives.len());
+
+        self.nodes.push(Node {
+            bounds,
+            offset: self.order.len(),
+            count: primitives.len(),
+            axis: 0,
+        });
+        self.order
+            .extend(primitives.iter().map(|primitive| primitive.index));
+
+        self.nodes.len() - 1
+    }
+}
+
+impl Hitable for Bvh {
+    fn check_hit(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
+        let mut closest_hit: Option<Hit> = None;
+        let mut closest = t_max;
+
+        for object in &self.unbounded {
+            if let Some(hit) = object.check_hit(ray, t_min, closest) {
+                closest = hit.t;
+                closest_hit = Some(hit);
+            }
+        }
+
+        // Anything the tree finds is closer than what's been found so far
+        self.tree
+            .check_hit(ray, t_min, closest, |index, t_max| {
+                self.objects[index].check_hit(ray, t_min, t_max)
+            })
+            .or(closest_hit)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        if !self.unbounded.is_empty() {
+            return None;
+        }
+
+        Some(self.tree.bounds())
+    }
+
+    // A group of solid objects (like a declared object) is solid too, and inside wherever any
+    // of them are. Groups in CSG are small, so their spans are found without the tree.
+    fn is_solid(&self) -> bool {
+        self.unbounded.is_empty() && self.objects.iter().all(|object| object.is_solid())
+    }
+
+    fn spans(&self, ray: Ray) -> Vec<Span<'_>> {
+        self.objects.iter().fold(Vec::new(), |spans, object| {
+            combine(Operation::Union, spans, object.spans(ray))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::Random;
+    use crate::raytracer::material::Lambertian;
+    use crate::raytracer::{Plane, Sphere};
+    use crate::scene;
+
+    #[test]
+    fn finds_the_same_hits_as_testing_every_object() {
+        // With an infinite wall behind the spheres, which is left out of the tree
+        let scene = || {
+            let mut objects = scene::random_scene(&mut Random::new(3)).objects;
+            objects.push(Box::new(Plane::new(
+                Vector3::new(0.0, 0.0, -12.0),
+                Vector3::new(0.0, 0.0, 1.0),
+                Box::new(Lambertian::new(Vector3::new(0.5, 0.5, 0.5))),
+            )));
+            objects
+        };
+        let objects = scene();
+        let count = objects.len();
+        let bvh = Bvh::new(scene());
+
+        let stats = bvh.stats();
+        assert_eq!(stats.unbounded, 1);
+        assert_eq!(stats.objects + stats.unbounded, count);
+        assert_eq!(stats.nodes, 2 * stats.leaves - 1);
+        assert!(stats.largest_leaf <= MAX_LEAF_SIZE);
+
+        let mut random = Random::new(9);
+
+        for _ in 0..2000 {
+            let mut point = || {
+                Vector3::new(
+                    random.next_f64() * 30.0 - 15.0,
+                    random.next_f64() * 4.0,
+                    random.next_f64() * 30.0 - 15.0,
+                )
+            };
+            let origin = point();
+            let ray = Ray::new(origin, point() - origin, 0.0);
+
+            let expected = objects.check_hit(ray, 0.001, f64::MAX).map(|hit| hit.p);
+            let actual = bvh.check_hit(ray, 0.001, f64::MAX).map(|hit| hit.p);
+            assert_eq!(expected, actual);
+        }
+    }
+
+    #[test]
+    fn handles_deep_trees_of_clustered_objects() {
+        // Every sphere is half as far from the origin as the one before, so the heuristic
+        // only splits a few of them off at a time
+        let spheres = || -> Vec<Box<dyn Hitable + Sync>> {
+            (0..400)
+                .map(|i| {
+                    let distance = 0.5f64.powi(i);
+                    Box::new(Sphere::new(
+                        Vector3::new(distance, 0.0, 0.0),
+                        distance * 0.1,
+                        Box::new(Lambertian::new(Vector3::new(0.5, 0.5, 0.5))),
+                    )) as Box<dyn Hitable + Sync>
+                })
+                .collect()
+        };
+        let list = spheres();
+        let bvh = Bvh::new(spheres());
+
+        assert!(bvh.stats().depth > MAX_SAH_DEPTH);
+
+        for i in 0..400 {
+            let distance = 0.5f64.powi(i);
+            let ray = Ray::new(
+                Vector3::new(distance, 1.0, 0.0),
+                Vector3::new(0.0, -1.0, 0.0),
+                0.0,
+            );
+
+            let expected = list.check_hit(ray, 0.0, f64::MAX).map(|hit| hit.p);
+            let actual = bvh.check_hit(ray, 0.0, f64::MAX).map(|hit| hit.p);
+            assert!(expected.is_some());
+            assert_eq!(expected, actual);
+        }
+    }
+}
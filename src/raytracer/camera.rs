@@ -1,5 +1,6 @@
-use crate::math::Vector3;
+use crate::math::{Transform, Vector3};
 use crate::raytracer::Ray;
+use crate::sampler::Sampler;
 use std::f64::consts::PI;
 
 #[derive(Debug)]
@@ -10,8 +11,9 @@ pub struct Camera {
     origin: Vector3,
     u: Vector3,
     v: Vector3,
-    w: Vector3,
     lens_radius: f64,
+    shutter_open: f64,
+    shutter_close: f64,
 }
 
 impl Camera {
@@ -24,10 +26,12 @@ impl Camera {
         aperture: f64,
         focus_distance: f64,
     ) -> Camera {
-        // u, v and w will form the coordinate system for the camera
-        let w = (look_from - look_at).unit();
-        let u = view_up.cross(w).unit();
-        let v = w.cross(u);
+        // u, v and w will form the coordinate system for the camera: the camera's own x, y and z
+        // axes once placed in the world, looking down -z
+        let placement = Transform::look_at(look_from, look_at, view_up);
+        let u = placement.vector(Vector3::new(1.0, 0.0, 0.0));
+        let v = placement.vector(Vector3::new(0.0, 1.0, 0.0));
+        let w = placement.vector(Vector3::new(0.0, 0.0, 1.0));
 
         let lens_radius = aperture / 2.0;
 
@@ -54,28 +58,53 @@ impl Camera {
             lens_radius,
             u,
             v,
-            w,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
         }
     }
 
-    pub fn get_ray(&self, u: f64, v: f64) -> Ray {
-        let point_in_lens = self.lens_radius * random_in_unit_disk();
+    // Rays are sent at any time between `open` and `close`, so whatever moves in between is
+    // blurred along its way. By default the shutter only opens for an instant, at time 0.
+    pub fn with_shutter(self, open: f64, close: f64) -> Camera {
+        Camera { shutter_open: open, shutter_close: close, ..self }
+    }
+
+    pub fn get_ray(&self, u: f64, v: f64, sampler: &mut dyn Sampler) -> Ray {
+        let point_in_lens = self.lens_radius * random_in_unit_disk(sampler);
         let offset = self.u * point_in_lens.x + self.v * point_in_lens.y;
 
+        // Cameras that don't blur anything leave the sampler's dimensions to the materials
+        let time = if self.shutter_close > self.shutter_open {
+            self.shutter_open + (self.shutter_close - self.shutter_open) * sampler.next_1d()
+        } else {
+            self.shutter_open
+        };
+
         Ray::new(
             self.origin + offset,
             self.lower_left_corner + self.horizontal * u + self.vertical * v - self.origin - offset,
+            time,
         )
     }
 }
 
-fn random_in_unit_disk() -> Vector3 {
-    loop {
-        let p =
-            Vector3::new(rand::random(), rand::random(), 0.0) * 2.0 - Vector3::new(1.0, 1.0, 0.0);
+// Shirley and Chiu's concentric mapping from the square to the disk: each square around the
+// center becomes a circle, which keeps samples that were well spread in the square well spread
+// in the disk
+fn random_in_unit_disk(sampler: &mut dyn Sampler) -> Vector3 {
+    let (u, v) = sampler.next_2d();
+    let a = 2.0 * u - 1.0;
+    let b = 2.0 * v - 1.0;
 
-        if p.squared_length() <= 1.0 {
-            return p;
-        }
+    if a == 0.0 && b == 0.0 {
+        return Vector3::new(0.0, 0.0, 0.0);
     }
+
+    let (radius, angle) = if a.abs() > b.abs() {
+        (a, PI / 4.0 * (b / a))
+    } else {
+        (b, PI / 2.0 - PI / 4.0 * (a / b))
+    };
+
+    Vector3::new(radius * angle.cos(), radius * angle.sin(), 0.0)
 }
@@ -0,0 +1,250 @@
+use crate::raytracer::{Aabb, Hit, Hitable, Ray, Span};
+
+// How the insides of two objects are combined
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Operation {
+    // Inside either of them
+    Union,
+    // Inside both of them
+    Intersection,
+    // Inside the first one, but not the second
+    Difference,
+}
+
+impl Operation {
+    fn inside(self, a: bool, b: bool) -> bool {
+        match self {
+            Operation::Union => a || b,
+            Operation::Intersection => a && b,
+            Operation::Difference => a && !b,
+        }
+    }
+}
+
+// Constructive solid geometry: two solid objects combined into a new one, which is solid too and
+// can be combined again. Rays go through both objects along their whole line, and the spans
+// that are inside each of them are merged into the spans inside the combination.
+//
+// Surfaces keep the material of the object they came from, so carving one object out of another
+// lines the hole with the material of the second one.
+pub struct Csg {
+    operation: Operation,
+    a: Box<dyn Hitable + Sync>,
+    b: Box<dyn Hitable + Sync>,
+}
+
+impl Csg {
+    // Only solid objects can be combined, see `Hitable::is_solid`
+    pub fn new(
+        operation: Operation,
+        a: Box<dyn Hitable + Sync>,
+        b: Box<dyn Hitable + Sync>,
+    ) -> Option<Csg> {
+        if a.is_solid() && b.is_solid() {
+            Some(Csg { operation, a, b })
+        } else {
+            None
+        }
+    }
+}
+
+impl Hitable for Csg {
+    // Like any solid, it's only hit where the ray goes into it
+    fn check_hit(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
+        self.spans(ray)
+            .into_iter()
+            .map(|span| span.enter)
+            .find(|hit| hit.t >= t_min && hit.t <= t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let (a, b) = (self.a.bounding_box()?, self.b.bounding_box()?);
+
+        Some(match self.operation {
+            Operation::Union => a.surrounding(&b),
+            Operation::Intersection => a.overlap(&b),
+            Operation::Difference => a,
+        })
+    }
+
+    fn is_solid(&self) -> bool {
+        true
+    }
+
+    fn spans(&self, ray: Ray) -> Vec<Span<'_>> {
+        combine(self.operation, self.a.spans(ray), self.b.spans(ray))
+    }
+}
+
+// Goes through where the line enters and leaves either object in order, keeping track of
+// whether it's inside each of them, and so whether it's inside the combination
+pub fn combine<'a>(operation: Operation, a: Vec<Span<'a>>, b: Vec<Span<'a>>) -> Vec<Span<'a>> {
+    // Every boundary, whether it belongs to `b`, and whether it goes into the object
+    let mut boundaries = Vec::with_capacity(2 * (a.len() + b.len()));
+
+    for (spans, from_b) in [(a, false), (b, true)] {
+        for span in spans {
+            boundaries.push((span.enter, from_b, true));
+            boundaries.push((span.exit, from_b, false));
+        }
+    }
+
+    boundaries.sort_by(|(a, _, _), (b, _, _)| a.t.total_cmp(&b.t));
+
+    let (mut inside_a, mut inside_b) = (false, false);
+    let mut enter = None;
+    let mut spans = Vec::new();
+
+    for (hit, from_b, entering) in boundaries {
+        let was_inside = operation.inside(inside_a, inside_b);
+
+        if from_b {
+            inside_b = entering;
+        } else {
+            inside_a = entering;
+        }
+
+        // Normals face the ray either way, but going into the second object is coming out of
+        // what's carved out of the first one, and the other way around
+        let hit = if from_b && operation == Operation::Difference {
+            Hit {
+                front_face: !hit.front_face,
+                ..hit
+            }
+        } else {
+            hit
+        };
+
+        match (was_inside, operation.inside(inside_a, inside_b)) {
+            (false, true) => enter = Some(hit),
+            (true, false) => {
+                if let Some(enter) = enter.take() {
+                    spans.push(Span { enter, exit: hit });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Vector3;
+    use crate::raytracer::material::Lambertian;
+    use crate::raytracer::{Cuboid, Paraboloid, Plane, Sphere};
+
+    fn sphere(x: f64) -> Box<dyn Hitable + Sync> {
+        let material = Lambertian::new(Vector3::new(0.5, 0.5, 0.5));
+        Box::new(Sphere::new(
+            Vector3::new(x, 0.0, 0.0),
+            1.0,
+            Box::new(material),
+        ))
+    }
+
+    fn ts(csg: &Csg, ray: Ray) -> Vec<(f64, f64)> {
+        csg.spans(ray)
+            .iter()
+            .map(|span| (span.enter.t, span.exit.t))
+            .collect()
+    }
+
+    #[test]
+    fn combines_the_spans_of_two_spheres() {
+        // Along the x axis, from -10: the spheres cover 9..11 and 10..12
+        let ray = Ray::new(
+            Vector3::new(-10.0, 0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            0.0,
+        );
+        let csg = |operation| Csg::new(operation, sphere(0.0), sphere(1.0)).unwrap();
+
+        assert_eq!(ts(&csg(Operation::Union), ray), vec![(9.0, 12.0)]);
+        assert_eq!(ts(&csg(Operation::Intersection), ray), vec![(10.0, 11.0)]);
+        assert_eq!(ts(&csg(Operation::Difference), ray), vec![(9.0, 10.0)]);
+
+        // From the other side, the ray goes into the first sphere where the bite was taken out
+        // of it, through the surface of the second one
+        let back = Ray::new(
+            Vector3::new(10.0, 0.0, 0.0),
+            Vector3::new(-1.0, 0.0, 0.0),
+            0.0,
+        );
+        let bitten = csg(Operation::Difference);
+        let hit = bitten.check_hit(back, 0.0, f64::MAX).unwrap();
+        assert_eq!(hit.t, 10.0);
+        assert_eq!(hit.normal, Vector3::new(1.0, 0.0, 0.0));
+        assert!(hit.front_face);
+    }
+
+    #[test]
+    fn rays_only_hit_where_they_go_in() {
+        // Starting inside two spheres, the ray comes out of them without hitting either, just
+        // like it would with each of them on its own
+        let ray = Ray::new(
+            Vector3::new(0.5, 0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            0.0,
+        );
+        let union = Csg::new(Operation::Union, sphere(0.0), sphere(1.0)).unwrap();
+        assert!(union.check_hit(ray, 0.001, f64::MAX).is_none());
+        assert!(sphere(0.0).check_hit(ray, 0.001, f64::MAX).is_none());
+
+        // A hollow ball, with a ray from its center going into the inner wall first
+        let material = || Box::new(Lambertian::new(Vector3::new(0.5, 0.5, 0.5)));
+        let shell = Csg::new(
+            Operation::Difference,
+            Box::new(Sphere::new(Vector3::new(0.0, 0.0, 0.0), 2.0, material())),
+            Box::new(Sphere::new(Vector3::new(0.0, 0.0, 0.0), 1.0, material())),
+        )
+        .unwrap();
+
+        let ray = Ray::new(
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            0.0,
+        );
+        let hit = shell.check_hit(ray, 0.001, f64::MAX).unwrap();
+        assert_eq!(hit.t, 1.0);
+        assert_eq!(hit.normal, Vector3::new(0.0, -1.0, 0.0));
+        assert!(hit.front_face);
+
+        let bounds = shell.bounding_box().unwrap();
+        assert_eq!(bounds.max, Vector3::new(2.0, 2.0, 2.0));
+    }
+
+    #[test]
+    fn rays_along_the_axis_go_in_and_out_of_a_paraboloid_once() {
+        // Parallel to the axis, the ray crosses the bowl once and leaves through its cap
+        let material = || Box::new(Lambertian::new(Vector3::new(0.5, 0.5, 0.5)));
+        let bowl = Paraboloid::new(2.0, 4.0, 0.0, 4.0, 360.0, true, material());
+        let csg = Csg::new(
+            Operation::Intersection,
+            Box::new(bowl),
+            Box::new(Sphere::new(Vector3::new(0.0, 0.0, 0.0), 20.0, material())),
+        )
+        .unwrap();
+
+        let ray = Ray::new(
+            Vector3::new(1.0, 0.0, -10.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            0.0,
+        );
+        assert_eq!(ts(&csg, ray), vec![(11.0, 14.0)]);
+    }
+
+    #[test]
+    fn only_combines_solid_objects() {
+        let material = || Box::new(Lambertian::new(Vector3::new(0.5, 0.5, 0.5)));
+        let origin = Vector3::new(0.0, 0.0, 0.0);
+
+        let cube = Cuboid::new(origin, Vector3::new(1.0, 1.0, 1.0), material());
+        assert!(Csg::new(Operation::Union, Box::new(cube), sphere(0.0)).is_some());
+
+        let plane = Plane::new(origin, Vector3::new(0.0, 1.0, 0.0), material());
+        assert!(Csg::new(Operation::Union, Box::new(plane), sphere(0.0)).is_none());
+    }
+}
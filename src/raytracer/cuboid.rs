@@ -0,0 +1,151 @@
+use crate::math::Vector3;
+use crate::raytracer::{Aabb, Hit, Hitable, Material, Ray, Span};
+
+// A solid, axis-aligned box between two opposite corners. Rotated boxes are instances of this
+// one. Each face has its own uv coordinates, from 0 to 1 along the two axes it spans.
+pub struct Cuboid {
+    bounds: Aabb,
+    material: Box<dyn Material + Sync>,
+}
+
+impl Cuboid {
+    pub fn new(min: Vector3, max: Vector3, material: Box<dyn Material + Sync>) -> Cuboid {
+        Cuboid {
+            bounds: Aabb::new(min, max),
+            material,
+        }
+    }
+}
+
+impl Cuboid {
+    // Where the ray's line goes into the box and where it comes out, and the axis of the face
+    // it goes through each time
+    fn slabs(&self, ray: Ray) -> Option<((f64, usize), (f64, usize))> {
+        // The same slabs as `Aabb::hit`, but keeping track of which axis the ray enters and
+        // leaves through, since that's the face it hits
+        let (min, max) = (self.bounds.min, self.bounds.max);
+        let mut enter = (f64::NEG_INFINITY, 0);
+        let mut exit = (f64::INFINITY, 0);
+
+        for axis in 0..3 {
+            let inverse = 1.0 / ray.direction[axis];
+            let mut t0 = (min[axis] - ray.origin[axis]) * inverse;
+            let mut t1 = (max[axis] - ray.origin[axis]) * inverse;
+
+            if inverse < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            if t0 > enter.0 {
+                enter = (t0, axis);
+            }
+
+            if t1 < exit.0 {
+                exit = (t1, axis);
+            }
+        }
+
+        if enter.0 > exit.0 {
+            None
+        } else {
+            Some((enter, exit))
+        }
+    }
+
+    fn hit_at(&self, ray: Ray, (t, axis): (f64, usize)) -> Hit<'_> {
+        let (min, max) = (self.bounds.min, self.bounds.max);
+        let p = ray.point_at(t);
+        let size = max - min;
+        let side = if p[axis] - min[axis] > size[axis] / 2.0 {
+            1.0
+        } else {
+            -1.0
+        };
+        let normal = match axis {
+            0 => Vector3::new(side, 0.0, 0.0),
+            1 => Vector3::new(0.0, side, 0.0),
+            _ => Vector3::new(0.0, 0.0, side),
+        };
+
+        let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+        let uv = ((p[a] - min[a]) / size[a], (p[b] - min[b]) / size[b]);
+
+        let dpdu = match a {
+            0 => Vector3::new(size.x, 0.0, 0.0),
+            1 => Vector3::new(0.0, size.y, 0.0),
+            _ => Vector3::new(0.0, 0.0, size.z),
+        };
+
+        Hit::new(&ray, t, p, normal, uv, dpdu, &*self.material)
+    }
+}
+
+impl Hitable for Cuboid {
+    fn check_hit(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
+        let (enter, _) = self.slabs(ray)?;
+
+        if enter.0 < t_min || enter.0 > t_max {
+            return None;
+        }
+
+        Some(self.hit_at(ray, enter))
+    }
+
+    fn is_solid(&self) -> bool {
+        true
+    }
+
+    fn spans(&self, ray: Ray) -> Vec<Span<'_>> {
+        match self.slabs(ray) {
+            Some((enter, exit)) => vec![Span {
+                enter: self.hit_at(ray, enter),
+                exit: self.hit_at(ray, exit),
+            }],
+            None => Vec::new(),
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.bounds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raytracer::material::Lambertian;
+
+    #[test]
+    fn hits_the_face_the_ray_reaches_first() {
+        let cuboid = Cuboid::new(
+            Vector3::new(-1.0, 0.0, -1.0),
+            Vector3::new(1.0, 2.0, 1.0),
+            Box::new(Lambertian::new(Vector3::new(0.5, 0.5, 0.5))),
+        );
+
+        let above = Ray::new(
+            Vector3::new(0.5, 5.0, 0.0),
+            Vector3::new(0.0, -1.0, 0.0),
+            0.0,
+        );
+        let hit = cuboid.check_hit(above, 0.0, f64::MAX).unwrap();
+        assert_eq!(hit.t, 3.0);
+        assert_eq!(hit.normal, Vector3::new(0.0, 1.0, 0.0));
+        assert_eq!(hit.uv, (0.5, 0.75));
+
+        // Like any solid, it isn't hit on the way out by rays that start inside it
+        let inside = Ray::new(
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(-2.0, 0.0, 0.0),
+            0.0,
+        );
+        assert!(cuboid.check_hit(inside, 0.0, f64::MAX).is_none());
+
+        let past = Ray::new(
+            Vector3::new(0.0, 3.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            0.0,
+        );
+        assert!(cuboid.check_hit(past, 0.0, f64::MAX).is_none());
+    }
+}
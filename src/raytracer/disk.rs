@@ -0,0 +1,79 @@
+use crate::math::{orthonormal_basis, Vector3};
+use crate::raytracer::{Aabb, Hit, Hitable, Material, Ray};
+use std::f64::consts::PI;
+
+// A flat, round disk centered at `center` and facing `normal`. Its uv coordinates are polar: u
+// goes once around the center, and v out from the center to the edge.
+pub struct Disk {
+    center: Vector3,
+    normal: Vector3,
+    radius: f64,
+    tangent: Vector3,
+    bitangent: Vector3,
+    material: Box<dyn Material + Sync>,
+}
+
+impl Disk {
+    pub fn new(
+        center: Vector3,
+        normal: Vector3,
+        radius: f64,
+        material: Box<dyn Material + Sync>,
+    ) -> Disk {
+        let normal = normal.unit();
+        let (tangent, bitangent) = orthonormal_basis(normal);
+
+        Disk {
+            center,
+            normal,
+            radius,
+            tangent,
+            bitangent,
+            material,
+        }
+    }
+}
+
+impl Hitable for Disk {
+    fn check_hit(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
+        let denominator = self.normal.dot(ray.direction);
+
+        if denominator.abs() < 1e-12 {
+            return None;
+        }
+
+        let t = self.normal.dot(self.center - ray.origin) / denominator;
+
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        let p = ray.point_at(t);
+        let offset = p - self.center;
+        let distance = offset.magnitude();
+
+        if distance > self.radius {
+            return None;
+        }
+
+        let phi = offset.dot(self.bitangent).atan2(offset.dot(self.tangent));
+        let uv = ((phi + PI) / (2.0 * PI), distance / self.radius);
+
+        // Around the center, counterclockwise seen from the front
+        let dpdu = 2.0 * PI * self.normal.cross(offset);
+
+        Some(Hit::new(&ray, t, p, self.normal, uv, dpdu, &*self.material))
+    }
+
+    // How far the rim reaches along each axis depends on how tilted the disk is from it
+    fn bounding_box(&self) -> Option<Aabb> {
+        let n = self.normal;
+        let extent = Vector3::new(
+            (1.0 - n.x * n.x).max(0.0).sqrt(),
+            (1.0 - n.y * n.y).max(0.0).sqrt(),
+            (1.0 - n.z * n.z).max(0.0).sqrt(),
+        ) * self.radius;
+
+        Some(Aabb::new(self.center - extent, self.center + extent).padded(1e-4))
+    }
+}
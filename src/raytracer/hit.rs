@@ -1,25 +1,97 @@
 use crate::math::Vector3;
-use crate::raytracer::{Ray, Material};
+use crate::raytracer::{Aabb, Ray, Material};
 
+// Normals face back towards where the ray came from, which is the side light scatters off into,
+// and `front_face` says whether that's the outside of the surface: the side its normal points
+// to, for flat shapes. `normal` is the one to shade with, which meshes with vertex normals bend
+// to look smooth, while `geometric_normal` is the surface as it really is.
+//
+// `uv` are the coordinates of the hit on the surface of the object, each one usually between
+// 0 and 1, for whatever needs to be mapped onto it, and `dpdu` is how `p` moves as u grows: a
+// tangent along the surface for anything that isn't the same in every direction on it (it's
+// zero where u doesn't move, like at the poles of a sphere). Meshes can also have a color for
+// every vertex, which materials tint themselves with.
+#[derive(Copy, Clone)]
 pub struct Hit<'a> {
-    t: f64,
+    pub t: f64,
     pub p: Vector3,
     pub normal: Vector3,
+    pub geometric_normal: Vector3,
+    pub front_face: bool,
+    pub uv: (f64, f64),
+    pub dpdu: Vector3,
+    pub color: Option<Vector3>,
     pub material: &'a dyn Material,
 }
 
 impl<'a> Hit<'a> {
-    pub fn new(t: f64, p: Vector3, normal: Vector3, material: &'a dyn Material) -> Hit {
-        Hit { t, p, normal, material }
+    // `outward_normal` is the normal of the surface's outside, which is turned around when `ray`
+    // hits it from the inside
+    pub fn new(
+        ray: &Ray,
+        t: f64,
+        p: Vector3,
+        outward_normal: Vector3,
+        uv: (f64, f64),
+        dpdu: Vector3,
+        material: &'a dyn Material,
+    ) -> Hit<'a> {
+        let front_face = ray.direction.dot(outward_normal) < 0.0;
+        let normal = if front_face { outward_normal } else { -outward_normal };
+
+        Hit {
+            t,
+            p,
+            normal,
+            geometric_normal: normal,
+            front_face,
+            uv,
+            dpdu,
+            color: None,
+            material,
+        }
+    }
+
+    // Shades with `normal` instead, given on the outside like the surface's own
+    pub fn with_shading_normal(self, normal: Vector3) -> Hit<'a> {
+        let normal = if self.front_face { normal } else { -normal };
+        Hit { normal, ..self }
     }
 }
 
-pub trait Hitable {
-    fn check_hit(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<Hit>;
+// A stretch of a ray that is inside a solid object, from where it goes in to where it comes out
+#[derive(Copy, Clone)]
+pub struct Span<'a> {
+    pub enter: Hit<'a>,
+    pub exit: Hit<'a>,
+}
+
+// Objects are shared by every render thread
+pub trait Hitable: Send + Sync {
+    // The closest hit between `t_min` and `t_max`. Solid objects (see `is_solid`) are only hit
+    // where the ray goes into them, so a ray that starts inside one, like a ray refracted into a
+    // glass ball, leaves it without hitting it again.
+    fn check_hit(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>>;
+
+    // A box that contains the whole object, or `None` if the object goes on forever
+    fn bounding_box(&self) -> Option<Aabb>;
+
+    // Solid objects are closed, so they have an inside that can be combined with others in CSG.
+    // Flat and open shapes don't, and neither do meshes, which can't be trusted to be closed.
+    fn is_solid(&self) -> bool {
+        false
+    }
+
+    // For solid objects, every stretch of the ray's whole line (even behind its origin) that is
+    // inside the object, in order and without overlaps. Like for any hit, normals face the ray,
+    // so they point out of the object where it enters and into it where it leaves.
+    fn spans(&self, _ray: Ray) -> Vec<Span<'_>> {
+        Vec::new()
+    }
 }
 
 impl Hitable for Vec<Box<dyn Hitable + Sync>> {
-    fn check_hit(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<Hit> {
+    fn check_hit(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
         let mut closest_hit: Option<Hit> = None;
 
         for hitable in self {
@@ -35,4 +107,10 @@ impl Hitable for Vec<Box<dyn Hitable + Sync>> {
 
         closest_hit
     }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.iter().try_fold(Aabb::empty(), |bounds, hitable| {
+            Some(bounds.surrounding(&hitable.bounding_box()?))
+        })
+    }
 }
@@ -0,0 +1,193 @@
+use crate::math::{Pose, Transform, Vector3};
+use crate::raytracer::{Aabb, Hit, Hitable, Ray, Span};
+use std::sync::Arc;
+
+// Any object, moved, rotated and scaled by a transform. The object itself is shared, so a single
+// copy of its geometry can be placed in the scene as many times as needed.
+//
+// Rather than transforming the object, rays are brought into the object's own space with the
+// inverse transform, and whatever they hit is brought back out. Directions aren't normalized on
+// the way in, so a hit at `t` in object space is at the same `t` along the original ray.
+//
+// Moving instances blend between two poses, and place the object wherever it is at the time of
+// each ray.
+pub struct Instance {
+    object: Arc<dyn Hitable>,
+    placement: Placement,
+}
+
+enum Placement {
+    Fixed(Transform),
+    Moving(Pose, Pose),
+}
+
+impl Instance {
+    pub fn new(object: Arc<dyn Hitable>, transform: Transform) -> Instance {
+        Instance {
+            object,
+            placement: Placement::Fixed(transform),
+        }
+    }
+
+    // From `start` at time 0 to `end` at time 1
+    pub fn moving(object: Arc<dyn Hitable>, start: Pose, end: Pose) -> Instance {
+        Instance {
+            object,
+            placement: Placement::Moving(start, end),
+        }
+    }
+}
+
+impl Instance {
+    fn transform_at(&self, time: f64) -> Transform {
+        match &self.placement {
+            Placement::Fixed(transform) => *transform,
+            Placement::Moving(start, end) => start.interpolate(end, time).transform(),
+        }
+    }
+
+    fn local_ray(transform: &Transform, ray: Ray) -> Ray {
+        let inverse = transform.inverse();
+
+        Ray::new(
+            inverse.point(ray.origin),
+            inverse.vector(ray.direction),
+            ray.time,
+        )
+    }
+
+    // Transforms keep the side of the surface the ray is on, so it still faces the same way
+    fn world_hit<'a>(transform: &Transform, hit: Hit<'a>) -> Hit<'a> {
+        Hit {
+            t: hit.t,
+            p: transform.point(hit.p),
+            normal: transform.normal(hit.normal).unit(),
+            geometric_normal: transform.normal(hit.geometric_normal).unit(),
+            front_face: hit.front_face,
+            uv: hit.uv,
+            dpdu: transform.vector(hit.dpdu),
+            color: hit.color,
+            material: hit.material,
+        }
+    }
+
+    // The box around the transformed corners of the object's box
+    fn transformed_bounds(transform: &Transform, bounds: &Aabb) -> Aabb {
+        let corners = (0..8).map(|corner| {
+            transform.point(Vector3::new(
+                if corner & 1 == 0 {
+                    bounds.min.x
+                } else {
+                    bounds.max.x
+                },
+                if corner & 2 == 0 {
+                    bounds.min.y
+                } else {
+                    bounds.max.y
+                },
+                if corner & 4 == 0 {
+                    bounds.min.z
+                } else {
+                    bounds.max.z
+                },
+            ))
+        });
+
+        corners.fold(Aabb::empty(), |bounds, corner| {
+            bounds.surrounding(&Aabb::new(corner, corner))
+        })
+    }
+}
+
+impl Hitable for Instance {
+    fn check_hit(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
+        let transform = self.transform_at(ray.time);
+
+        self.object
+            .check_hit(Instance::local_ray(&transform, ray), t_min, t_max)
+            .map(|hit| Instance::world_hit(&transform, hit))
+    }
+
+    fn is_solid(&self) -> bool {
+        self.object.is_solid()
+    }
+
+    fn spans(&self, ray: Ray) -> Vec<Span<'_>> {
+        let transform = self.transform_at(ray.time);
+
+        self.object
+            .spans(Instance::local_ray(&transform, ray))
+            .into_iter()
+            .map(|span| Span {
+                enter: Instance::world_hit(&transform, span.enter),
+                exit: Instance::world_hit(&transform, span.exit),
+            })
+            .collect()
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let bounds = self.object.bounding_box()?;
+
+        if bounds.is_empty() {
+            return Some(bounds);
+        }
+
+        Some(match &self.placement {
+            Placement::Fixed(transform) => Instance::transformed_bounds(transform, &bounds),
+            // Everywhere the object goes through, or close enough: turning objects swing out a
+            // little between the steps
+            Placement::Moving(..) => (0..=MOTION_STEPS).fold(Aabb::empty(), |moved, step| {
+                let transform = self.transform_at(step as f64 / MOTION_STEPS as f64);
+                moved.surrounding(&Instance::transformed_bounds(&transform, &bounds))
+            }),
+        })
+    }
+}
+
+// How many times a moving instance is placed along its way to find its bounding box
+const MOTION_STEPS: usize = 16;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raytracer::material::Lambertian;
+    use crate::raytracer::Sphere;
+
+    #[test]
+    fn hits_the_transformed_object() {
+        let sphere: Arc<dyn Hitable> = Arc::new(Sphere::new(
+            Vector3::new(0.0, 0.0, 0.0),
+            1.0,
+            Box::new(Lambertian::new(Vector3::new(1.0, 1.0, 1.0))),
+        ));
+        // An ellipsoid, twice as wide along x, moved to x = 10
+        let instance = Instance::new(
+            sphere.clone(),
+            Transform::scaling(Vector3::new(2.0, 1.0, 1.0))
+                .then(&Transform::translation(Vector3::new(10.0, 0.0, 0.0))),
+        );
+
+        let ray = Ray::new(
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            0.0,
+        );
+        let hit = instance.check_hit(ray, 0.001, f64::MAX).unwrap();
+        assert!((hit.p - Vector3::new(8.0, 0.0, 0.0)).magnitude() < 1e-9);
+        assert!((hit.normal - Vector3::new(-1.0, 0.0, 0.0)).magnitude() < 1e-9);
+        assert!((hit.t - 8.0).abs() < 1e-9);
+
+        let bounds = instance.bounding_box().unwrap();
+        assert_eq!(bounds.min, Vector3::new(8.0, -1.0, -1.0));
+        assert_eq!(bounds.max, Vector3::new(12.0, 1.0, 1.0));
+
+        // The original is still where it was
+        let ray = Ray::new(
+            Vector3::new(-5.0, 0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            0.0,
+        );
+        let hit = sphere.check_hit(ray, 0.001, f64::MAX).unwrap();
+        assert!((hit.p - Vector3::new(-1.0, 0.0, 0.0)).magnitude() < 1e-9);
+    }
+}
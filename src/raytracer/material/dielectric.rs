@@ -1,6 +1,7 @@
 use crate::raytracer::{Ray, Hit, Material};
 use crate::raytracer::material::ScatteredHit;
 use crate::math::{Vector3, reflect};
+use crate::sampler::Sampler;
 
 pub struct Dielectric {
     refraction_index: f64,
@@ -25,20 +26,26 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, hit: &Hit, ray: &Ray) -> Option<ScatteredHit> {
+    fn scatter(&self, hit: &Hit, ray: &Ray, sampler: &mut dyn Sampler) -> Option<ScatteredHit> {
         let attenuation = Vector3::new(1.0, 1.0, 1.0);
 
         // `refract` includes a calculation that will refract with some probability, leaving
         // us to `reflect` if the probability said it didn't refract the ray
-        if let Some(refracted) = refract(ray.direction, hit.normal, self.refraction_index) {
-            Some(ScatteredHit::new(Ray::new(hit.p, refracted), attenuation))
+        if let Some(refracted) = refract(ray.direction, hit.normal, hit.front_face, self.refraction_index, sampler) {
+            Some(ScatteredHit::new(Ray::new(hit.p, refracted, ray.time), attenuation))
         } else {
-            Some(ScatteredHit::new(Ray::new(hit.p, reflect(ray.direction.unit(), hit.normal)), attenuation))
+            Some(ScatteredHit::new(Ray::new(hit.p, reflect(ray.direction.unit(), hit.normal), ray.time), attenuation))
         }
     }
 }
 
-fn refract(incident: Vector3, normal: Vector3, refraction_index: f64) -> Option<Vector3> {
+fn refract(
+    incident: Vector3,
+    normal: Vector3,
+    front_face: bool,
+    refraction_index: f64,
+    sampler: &mut dyn Sampler,
+) -> Option<Vector3> {
     // I wasn't able to figure out refraction from the book, so I'm following the explanation from
     // scratchapixel.com instead:
     // https://www.scratchapixel.com/lessons/3d-basic-rendering/introduction-to-shading/reflection-refraction-fresnel
@@ -50,23 +57,13 @@ fn refract(incident: Vector3, normal: Vector3, refraction_index: f64) -> Option<
 
     // We need to know which is the current medium and which is the new medium though - the ray could
     // be *entering* an object with a dielectric material, or it could be *exiting* one after travelling
-    // through it.
-    // We check the dot product of the incident ray compared to the normal. If it's negative,
-    // then the ray is entering the material. If it's positive, it's exiting.
-    // In this design, the direction vector of a ray is not ensured to be a unit vector, so...
-    let unit = incident.unit();
-    let dot = unit.dot(normal);
-    let refraction_index_ratio = if dot > 0.0 { refraction_index } else { 1.0 / refraction_index };
-
-    // Likewise, all the equations assume the normal points *away* from the plane the incident ray
-    // is hitting. But the normal in this function comes from a hit, and objects in this design always
-    // report the normal of a hit pointing *outwards*. So if the hit is from inside the object, the
-    // normal will be pointing *inside* of the plane hit by the ray, so we need to turn it around:
-    let mut normal = normal;
+    // through it. The hit knows: the ray is entering when it hit the front face of the surface.
+    let refraction_index_ratio = if front_face { 1.0 / refraction_index } else { refraction_index };
 
-    if dot > 0.0 {
-        normal = -normal;
-    }
+    // All the equations assume the normal points *away* from the plane the incident ray is
+    // hitting, which hits already do by facing back towards the ray. In this design, the direction
+    // vector of a ray is not ensured to be a unit vector, so...
+    let unit = incident.unit();
 
     // Calculate the cosine of the angle between the incident ray and the normal to use in
     // Schlick's approximation later
@@ -80,10 +77,10 @@ fn refract(incident: Vector3, normal: Vector3, refraction_index: f64) -> Option<
     // this means that there's *total internal reflection* - or in other words, no refraction (and
     // no refracted ray).
     // schlick gives us the probability of the ray being reflected. If the probability is 0.8,
-    // then we have a 20% chance of getting a number higher than that by calling rand::random().
+    // then we have a 20% chance of getting a number higher than that from the sampler.
     // So if our random number is higher than the chances of reflecting, we refract, otherwise
     // we reflect.
-    if c2 > 0.0 && schlick(cosine, refraction_index) < rand::random() {
+    if c2 > 0.0 && schlick(cosine, refraction_index) < sampler.next_1d() {
         Some(refraction_index_ratio * unit + (refraction_index_ratio * cosine - c2.sqrt()) * normal)
     } else {
         None
@@ -102,6 +99,7 @@ fn schlick(cosine: f64, refraction_index: f64) -> f64 {
 mod tests {
     use super::*;
     use crate::math::Vector3;
+    use crate::random::Random;
     use std::f64::consts::PI;
     use assert_approx_eq::assert_approx_eq;
 
@@ -119,7 +117,7 @@ mod tests {
 
         let refractive_index = 1.5;
 
-        let refracted_vector = refract(incident, normal, refractive_index).unwrap();
+        let refracted_vector = refract(incident, normal, true, refractive_index, &mut Random::new(0)).unwrap();
         let refracted_angle = refracted_vector.unit().dot(-normal).acos();
 
         // When entering a medium with higher refractive index, the angle should
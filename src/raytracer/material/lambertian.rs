@@ -1,21 +1,29 @@
-use crate::raytracer::{Ray, Hit, Material};
+use crate::raytracer::{Ray, Hit, Material, Texture};
 use crate::raytracer::material::ScatteredHit;
+use crate::raytracer::texture::Constant;
 use crate::math::{Vector3, random_in_unit_sphere};
+use crate::sampler::Sampler;
 
 pub struct Lambertian {
-    albedo: Vector3,
+    albedo: Box<dyn Texture>,
 }
 
 impl Lambertian {
     pub fn new(albedo: Vector3) -> Lambertian {
+        Lambertian::textured(Box::new(Constant::new(albedo)))
+    }
+
+    pub fn textured(albedo: Box<dyn Texture>) -> Lambertian {
         Lambertian { albedo }
     }
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, hit: &Hit, _ray: &Ray) -> Option<ScatteredHit> {
-        let target = hit.p + hit.normal + random_in_unit_sphere();
-        let scattered = Ray::new(hit.p, target - hit.p);
-        Some(ScatteredHit::new(scattered, self.albedo))
+    fn scatter(&self, hit: &Hit, ray: &Ray, sampler: &mut dyn Sampler) -> Option<ScatteredHit> {
+        let target = hit.p + hit.normal + random_in_unit_sphere(sampler);
+        let scattered = Ray::new(hit.p, target - hit.p, ray.time);
+        let albedo = self.albedo.value(hit);
+        let albedo = hit.color.map_or(albedo, |color| albedo * color);
+        Some(ScatteredHit::new(scattered, albedo))
     }
 }
@@ -1,33 +1,39 @@
-use crate::raytracer::{Ray, Hit, Material};
+use crate::raytracer::{Ray, Hit, Material, Texture};
 use crate::raytracer::material::ScatteredHit;
-use crate::math::{Vector3, random_in_unit_sphere, reflect};
+use crate::raytracer::texture::Constant;
+use crate::math::{Vector3, luminance, random_in_unit_sphere, reflect};
+use crate::sampler::Sampler;
 
 pub struct Metal {
-    albedo: Vector3,
-    fuzz: f64,
+    albedo: Box<dyn Texture>,
+    fuzz: Box<dyn Texture>,
 }
 
 impl Metal {
     pub fn new(albedo: Vector3, fuzz: f64) -> Metal {
-        let fuzz = if fuzz < 0.0 {
-            0.0
-        } else if fuzz > 1.0 {
-            1.0
-        } else {
-            fuzz
-        };
+        Metal::textured(
+            Box::new(Constant::new(albedo)),
+            Box::new(Constant::new(Vector3::new(fuzz, fuzz, fuzz))),
+        )
+    }
 
+    // The fuzz is the luminance of its texture, so a grey one can be used as it is
+    pub fn textured(albedo: Box<dyn Texture>, fuzz: Box<dyn Texture>) -> Metal {
         Metal { albedo, fuzz }
     }
 }
 
 impl Material for Metal {
-    fn scatter(&self, hit: &Hit, ray: &Ray) -> Option<ScatteredHit> {
+    fn scatter(&self, hit: &Hit, ray: &Ray, sampler: &mut dyn Sampler) -> Option<ScatteredHit> {
         let reflected = reflect(ray.direction.unit(), hit.normal);
-        let scattered = Ray::new(hit.p, reflected + self.fuzz * random_in_unit_sphere());
+        let fuzz = luminance(self.fuzz.value(hit)).clamp(0.0, 1.0);
+        let scattered = Ray::new(hit.p, reflected + fuzz * random_in_unit_sphere(sampler), ray.time);
 
-        if scattered.direction.dot(hit.normal) > 0.0 {
-            Some(ScatteredHit::new(scattered, self.albedo))
+        // Smooth shading can bend the normal enough to reflect into the surface itself
+        if scattered.direction.dot(hit.geometric_normal) > 0.0 {
+            let albedo = self.albedo.value(hit);
+            let albedo = hit.color.map_or(albedo, |color| albedo * color);
+            Some(ScatteredHit::new(scattered, albedo))
         } else {
             None
         }
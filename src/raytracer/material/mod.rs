@@ -1,5 +1,6 @@
 use crate::raytracer::{Ray, Hit};
 use crate::math::Vector3;
+use crate::sampler::Sampler;
 
 mod lambertian;
 mod metal;
@@ -20,6 +21,6 @@ impl ScatteredHit {
     }
 }
 
-pub trait Material {
-    fn scatter(&self, hit: &Hit, ray: &Ray) -> Option<ScatteredHit>;
+pub trait Material: Send + Sync {
+    fn scatter(&self, hit: &Hit, ray: &Ray, sampler: &mut dyn Sampler) -> Option<ScatteredHit>;
 }
@@ -0,0 +1,448 @@
+use crate::math::Vector3;
+use crate::raytracer::bvh::Tree;
+use crate::raytracer::{Aabb, Hit, Hitable, Material, Ray};
+use std::fmt;
+
+// Indexed triangles: every vertex is stored once and triangles refer to their three corners by
+// index, since most vertices are shared by around six triangles. Normals, uv coordinates and
+// (linear) colors are optional, but when there are any there's one for every vertex.
+#[derive(Clone, Debug, Default)]
+pub struct Mesh {
+    pub positions: Vec<Vector3>,
+    pub normals: Vec<Vector3>,
+    pub uvs: Vec<(f64, f64)>,
+    pub colors: Vec<Vector3>,
+    pub triangles: Vec<[u32; 3]>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum MeshError {
+    // The attribute, how many values it should have, and how many it has
+    VertexCount(&'static str, usize, usize),
+    // The triangle, and the vertex it refers to
+    MissingVertex(usize, u32),
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MeshError::VertexCount(attribute, expected, found) => write!(
+                f,
+                "the mesh has {} vertices but {} {}",
+                expected, found, attribute
+            ),
+            MeshError::MissingVertex(triangle, vertex) => write!(
+                f,
+                "triangle {} refers to vertex {}, which does not exist",
+                triangle, vertex
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MeshError {}
+
+impl Mesh {
+    pub fn check(&self) -> Result<(), MeshError> {
+        let vertices = self.positions.len();
+
+        if !self.normals.is_empty() && self.normals.len() != vertices {
+            return Err(MeshError::VertexCount(
+                "normals",
+                vertices,
+                self.normals.len(),
+            ));
+        }
+
+        if !self.uvs.is_empty() && self.uvs.len() != vertices {
+            return Err(MeshError::VertexCount("uvs", vertices, self.uvs.len()));
+        }
+
+        if !self.colors.is_empty() && self.colors.len() != vertices {
+            return Err(MeshError::VertexCount(
+                "colors",
+                vertices,
+                self.colors.len(),
+            ));
+        }
+
+        for (index, triangle) in self.triangles.iter().enumerate() {
+            if let Some(&vertex) = triangle.iter().find(|&&vertex| vertex as usize >= vertices) {
+                return Err(MeshError::MissingVertex(index, vertex));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// A whole mesh as a single object. Its triangles get a BVH of their own, so a mesh with a
+// million triangles costs the scene's BVH no more than a sphere does. The leaves of that tree
+// refer to the triangles of the mesh by index, so they cost nothing on top of the mesh itself.
+pub struct TriangleMesh {
+    tree: Tree,
+    // With its triangles in the order the tree expects
+    mesh: Mesh,
+    material: Box<dyn Material + Sync>,
+}
+
+impl TriangleMesh {
+    pub fn new(
+        mut mesh: Mesh,
+        material: Box<dyn Material + Sync>,
+    ) -> Result<TriangleMesh, MeshError> {
+        mesh.check()?;
+
+        let bounds: Vec<_> = mesh
+            .triangles
+            .iter()
+            .map(|triangle| {
+                triangle.iter().fold(Aabb::empty(), |bounds, &vertex| {
+                    let p = mesh.positions[vertex as usize];
+                    bounds.surrounding(&Aabb::new(p, p))
+                })
+            })
+            .collect();
+        let (tree, order) = Tree::new(&bounds);
+        mesh.triangles = order.iter().map(|&index| mesh.triangles[index]).collect();
+
+        Ok(TriangleMesh {
+            tree,
+            mesh,
+            material,
+        })
+    }
+
+    fn vertices(&self, triangle: usize) -> [usize; 3] {
+        let [a, b, c] = self.mesh.triangles[triangle];
+        [a as usize, b as usize, c as usize]
+    }
+
+    fn check_triangle_hit(
+        &self,
+        triangle: usize,
+        ray: Ray,
+        t_min: f64,
+        t_max: f64,
+    ) -> Option<Hit<'_>> {
+        let mesh = &self.mesh;
+        let vertices = self.vertices(triangle);
+        let [p0, p1, p2] = [
+            mesh.positions[vertices[0]],
+            mesh.positions[vertices[1]],
+            mesh.positions[vertices[2]],
+        ];
+        let (t, barycentric) = intersect([p0, p1, p2], ray, t_min, t_max)?;
+        let [b0, b1, b2] = barycentric;
+        let interpolate = |a: Vector3, b: Vector3, c: Vector3| a * b0 + b * b1 + c * b2;
+
+        // The triangle faces the side its vertices go around counterclockwise, unless it has
+        // vertex normals, which give a smooth surface and say which side is out
+        let mut outward = (p1 - p0).cross(p2 - p0).unit();
+        let shading = if mesh.normals.is_empty() {
+            outward
+        } else {
+            let [n0, n1, n2] = [
+                mesh.normals[vertices[0]],
+                mesh.normals[vertices[1]],
+                mesh.normals[vertices[2]],
+            ];
+            let normal = interpolate(n0, n1, n2).unit();
+
+            if outward.dot(normal) < 0.0 {
+                outward = -outward;
+            }
+
+            normal
+        };
+
+        // Without uv coordinates of its own, the triangle is mapped by its barycentric
+        // coordinates, so u goes from the first vertex to the second
+        let (uv, dpdu) = if mesh.uvs.is_empty() {
+            ((b1, b2), p1 - p0)
+        } else {
+            let [uv0, uv1, uv2] = [
+                mesh.uvs[vertices[0]],
+                mesh.uvs[vertices[1]],
+                mesh.uvs[vertices[2]],
+            ];
+            let uv = (
+                b0 * uv0.0 + b1 * uv1.0 + b2 * uv2.0,
+                b0 * uv0.1 + b1 * uv1.1 + b2 * uv2.1,
+            );
+
+            // Both edges from the third vertex, in space and in uv, give two equations for how
+            // p changes along u and v. Triangles whose uvs are all in a line have no answer.
+            let (du02, dv02) = (uv0.0 - uv2.0, uv0.1 - uv2.1);
+            let (du12, dv12) = (uv1.0 - uv2.0, uv1.1 - uv2.1);
+            let determinant = du02 * dv12 - dv02 * du12;
+            let dpdu = if determinant.abs() < 1e-12 {
+                p1 - p0
+            } else {
+                ((p0 - p2) * dv12 - (p1 - p2) * dv02) * (1.0 / determinant)
+            };
+
+            (uv, dpdu)
+        };
+
+        let mut hit = Hit::new(
+            &ray,
+            t,
+            interpolate(p0, p1, p2),
+            outward,
+            uv,
+            dpdu,
+            &*self.material,
+        )
+        .with_shading_normal(shading);
+
+        if !mesh.colors.is_empty() {
+            hit.color = Some(interpolate(
+                mesh.colors[vertices[0]],
+                mesh.colors[vertices[1]],
+                mesh.colors[vertices[2]],
+            ));
+        }
+
+        Some(hit)
+    }
+}
+
+impl Hitable for TriangleMesh {
+    fn check_hit(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
+        self.tree.check_hit(ray, t_min, t_max, |triangle, t_max| {
+            self.check_triangle_hit(triangle, ray, t_min, t_max)
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.tree.bounds())
+    }
+}
+
+// The watertight ray/triangle test from "Watertight Ray/Triangle Intersection" (Woop et al.):
+// rays that go right through an edge or a vertex shared by several triangles always hit at least
+// one of them, where simpler tests can let them slip through the crack.
+//
+// Everything is moved so the ray starts at the origin, the axes are swapped so the ray goes
+// mostly along z, and the triangle is sheared so the ray goes exactly along z. From there, the
+// ray hits if the origin is on the same side of all three (2D) edges. Shared edges get exactly
+// the same edge function from both triangles, so there are no gaps between them.
+//
+// Returns `t` and the barycentric coordinates of the hit, the weights of each vertex.
+fn intersect(triangle: [Vector3; 3], ray: Ray, t_min: f64, t_max: f64) -> Option<(f64, [f64; 3])> {
+    let d = ray.direction;
+    let kz = if d.x.abs() > d.y.abs() {
+        if d.x.abs() > d.z.abs() {
+            0
+        } else {
+            2
+        }
+    } else if d.y.abs() > d.z.abs() {
+        1
+    } else {
+        2
+    };
+    let kx = (kz + 1) % 3;
+    let ky = (kx + 1) % 3;
+
+    let shear = Vector3::new(-d[kx] / d[kz], -d[ky] / d[kz], 1.0 / d[kz]);
+    let [p0, p1, p2] = triangle.map(|p| {
+        let p = p - ray.origin;
+        Vector3::new(
+            p[kx] + shear.x * p[kz],
+            p[ky] + shear.y * p[kz],
+            p[kz] * shear.z,
+        )
+    });
+
+    let e0 = p1.x * p2.y - p1.y * p2.x;
+    let e1 = p2.x * p0.y - p2.y * p0.x;
+    let e2 = p0.x * p1.y - p0.y * p1.x;
+
+    if (e0 < 0.0 || e1 < 0.0 || e2 < 0.0) && (e0 > 0.0 || e1 > 0.0 || e2 > 0.0) {
+        return None;
+    }
+
+    let determinant = e0 + e1 + e2;
+
+    if determinant == 0.0 {
+        return None;
+    }
+
+    // Still scaled by the determinant, to put off the division until there's surely a hit
+    let t_scaled = e0 * p0.z + e1 * p1.z + e2 * p2.z;
+    let (low, high) = if determinant > 0.0 {
+        (t_min * determinant, t_max * determinant)
+    } else {
+        (t_max * determinant, t_min * determinant)
+    };
+
+    if t_scaled < low || t_scaled > high {
+        return None;
+    }
+
+    let inverse = 1.0 / determinant;
+    Some((
+        t_scaled * inverse,
+        [e0 * inverse, e1 * inverse, e2 * inverse],
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::Random;
+    use crate::raytracer::material::Lambertian;
+
+    fn grey() -> Box<dyn Material + Sync> {
+        Box::new(Lambertian::new(Vector3::new(0.5, 0.5, 0.5)))
+    }
+
+    // A unit square in the xy plane, split along its diagonal
+    fn square() -> Mesh {
+        Mesh {
+            positions: vec![
+                Vector3::new(0.0, 0.0, 0.0),
+                Vector3::new(1.0, 0.0, 0.0),
+                Vector3::new(1.0, 1.0, 0.0),
+                Vector3::new(0.0, 1.0, 0.0),
+            ],
+            triangles: vec![[0, 1, 2], [0, 2, 3]],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn rays_through_shared_edges_do_not_slip_through() {
+        let mesh = TriangleMesh::new(square(), grey()).unwrap();
+        let mut random = Random::new(5);
+
+        // From random points, right at the diagonal both triangles share
+        for _ in 0..10_000 {
+            let origin = Vector3::new(
+                random.next_f64() * 4.0 - 2.0,
+                random.next_f64() * 4.0 - 2.0,
+                random.next_f64() * 4.0 + 0.1,
+            );
+            let s = random.next_f64();
+            let ray = Ray::new(origin, Vector3::new(s, s, 0.0) - origin, 0.0);
+
+            let hit = mesh.check_hit(ray, 0.0, f64::MAX).unwrap();
+            assert!((hit.t - 1.0).abs() < 1e-9);
+            assert_eq!(hit.normal, Vector3::new(0.0, 0.0, 1.0));
+        }
+    }
+
+    #[test]
+    fn finds_the_same_hits_as_testing_every_triangle() {
+        let mut random = Random::new(8);
+        let mut point = || {
+            Vector3::new(
+                random.next_f64() * 10.0 - 5.0,
+                random.next_f64() * 10.0 - 5.0,
+                random.next_f64() * 10.0 - 5.0,
+            )
+        };
+
+        let mut soup = Mesh::default();
+        for i in 0..300 {
+            let corner = point();
+            soup.positions.extend_from_slice(&[
+                corner,
+                corner + Vector3::new(1.0, 0.0, 0.0),
+                corner + Vector3::new(0.0, 1.0, 1.0),
+            ]);
+            soup.triangles.push([3 * i, 3 * i + 1, 3 * i + 2]);
+        }
+        let mesh = TriangleMesh::new(soup.clone(), grey()).unwrap();
+
+        for _ in 0..2000 {
+            let origin = point();
+            let ray = Ray::new(origin, point() - origin, 0.0);
+
+            let expected = soup
+                .triangles
+                .iter()
+                .filter_map(|triangle| {
+                    let [a, b, c] = triangle.map(|vertex| soup.positions[vertex as usize]);
+                    intersect([a, b, c], ray, 0.0, f64::MAX).map(|(t, _)| t)
+                })
+                .min_by(f64::total_cmp);
+            let actual = mesh.check_hit(ray, 0.0, f64::MAX).map(|hit| hit.t);
+            assert_eq!(expected, actual);
+        }
+    }
+
+    #[test]
+    fn interpolates_vertex_normals_and_uvs() {
+        let mut square = square();
+        square.normals = vec![
+            Vector3::new(-1.0, 0.0, 1.0),
+            Vector3::new(1.0, 0.0, 1.0),
+            Vector3::new(1.0, 0.0, 1.0),
+            Vector3::new(-1.0, 0.0, 1.0),
+        ];
+        square.uvs = vec![(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)];
+        let mesh = TriangleMesh::new(square, grey()).unwrap();
+
+        let ray = Ray::new(
+            Vector3::new(0.5, 0.25, 1.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            0.0,
+        );
+        let hit = mesh.check_hit(ray, 0.0, f64::MAX).unwrap();
+        assert!((hit.normal - Vector3::new(0.0, 0.0, 1.0)).magnitude() < 1e-9);
+        assert!((hit.uv.0 - 1.0).abs() < 1e-9 && (hit.uv.1 - 0.5).abs() < 1e-9);
+
+        let ray = Ray::new(
+            Vector3::new(0.75, 0.5, 1.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            0.0,
+        );
+        let hit = mesh.check_hit(ray, 0.0, f64::MAX).unwrap();
+        assert!(hit.normal.x > 0.0);
+
+        // The triangle itself is still flat, and u grows twice as fast as x
+        assert_eq!(hit.geometric_normal, Vector3::new(0.0, 0.0, 1.0));
+        assert!((hit.dpdu - Vector3::new(0.5, 0.0, 0.0)).magnitude() < 1e-9);
+    }
+
+    #[test]
+    fn vertex_normals_say_which_side_is_the_front() {
+        // Wound to face +z, but with its vertex normals all facing -z
+        let mut square = square();
+        square.normals = vec![Vector3::new(0.0, 0.0, -1.0); 4];
+        let mesh = TriangleMesh::new(square, grey()).unwrap();
+
+        let from_above = Ray::new(
+            Vector3::new(0.5, 0.25, 1.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            0.0,
+        );
+        let hit = mesh.check_hit(from_above, 0.0, f64::MAX).unwrap();
+        assert!(!hit.front_face);
+        assert_eq!(hit.normal, Vector3::new(0.0, 0.0, 1.0));
+        assert_eq!(hit.geometric_normal, Vector3::new(0.0, 0.0, 1.0));
+
+        let from_below = Ray::new(
+            Vector3::new(0.5, 0.25, -1.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            0.0,
+        );
+        let hit = mesh.check_hit(from_below, 0.0, f64::MAX).unwrap();
+        assert!(hit.front_face);
+        assert_eq!(hit.normal, Vector3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn rejects_missing_vertices() {
+        let mut mesh = square();
+        mesh.triangles.push([1, 2, 4]);
+        assert_eq!(mesh.check(), Err(MeshError::MissingVertex(2, 4)));
+
+        let mut mesh = square();
+        mesh.uvs = vec![(0.0, 0.0)];
+        assert_eq!(mesh.check(), Err(MeshError::VertexCount("uvs", 4, 1)));
+    }
+}
@@ -1,11 +1,33 @@
+mod aabb;
+mod bvh;
 mod camera;
+mod csg;
+mod cuboid;
+mod disk;
 mod ray;
 mod hit;
+mod instance;
+mod mesh;
+mod plane;
+mod quad;
+mod quadric;
 mod sphere;
 pub mod material;
+pub mod texture;
 
+pub use aabb::Aabb;
+pub use bvh::Bvh;
 pub use camera::Camera;
+pub use csg::{Csg, Operation};
+pub use cuboid::Cuboid;
+pub use disk::Disk;
 pub use ray::Ray;
-pub use hit::{Hit, Hitable};
+pub use hit::{Hit, Hitable, Span};
+pub use instance::Instance;
+pub use mesh::{Mesh, TriangleMesh};
+pub use plane::Plane;
+pub use quad::Quad;
+pub use quadric::{Capsule, Cone, Cylinder, Paraboloid, Torus};
 pub use sphere::Sphere;
 pub use material::Material;
+pub use texture::Texture;
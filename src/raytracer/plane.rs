@@ -0,0 +1,64 @@
+use crate::math::{orthonormal_basis, Vector3};
+use crate::raytracer::{Aabb, Hit, Hitable, Material, Ray};
+
+// An infinite plane through `point`, facing `normal`. It has no edges to measure uv coordinates
+// from, so they are distances from `point` along two directions on the plane, and keep growing
+// past 1.
+pub struct Plane {
+    point: Vector3,
+    normal: Vector3,
+    tangent: Vector3,
+    bitangent: Vector3,
+    material: Box<dyn Material + Sync>,
+}
+
+impl Plane {
+    pub fn new(point: Vector3, normal: Vector3, material: Box<dyn Material + Sync>) -> Plane {
+        let normal = normal.unit();
+        let (tangent, bitangent) = orthonormal_basis(normal);
+
+        Plane {
+            point,
+            normal,
+            tangent,
+            bitangent,
+            material,
+        }
+    }
+}
+
+impl Hitable for Plane {
+    fn check_hit(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
+        // Points on the plane are the ones whose offset from `point` is perpendicular to the
+        // normal, which leaves a single t for rays that aren't parallel to it
+        let denominator = self.normal.dot(ray.direction);
+
+        if denominator.abs() < 1e-12 {
+            return None;
+        }
+
+        let t = self.normal.dot(self.point - ray.origin) / denominator;
+
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        let p = ray.point_at(t);
+        let offset = p - self.point;
+        let uv = (offset.dot(self.tangent), offset.dot(self.bitangent));
+
+        Some(Hit::new(
+            &ray,
+            t,
+            p,
+            self.normal,
+            uv,
+            self.tangent,
+            &*self.material,
+        ))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        None
+    }
+}
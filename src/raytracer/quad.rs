@@ -0,0 +1,110 @@
+use crate::math::Vector3;
+use crate::raytracer::{Aabb, Hit, Hitable, Material, Ray};
+
+// A flat parallelogram with one corner at `corner` and two edges `u` and `v` from it, so any
+// rectangle at any orientation. Its normal is `u` × `v`, and the uv coordinates of a point are
+// how far along each edge it is, from 0 to 1.
+pub struct Quad {
+    corner: Vector3,
+    u: Vector3,
+    v: Vector3,
+    normal: Vector3,
+    material: Box<dyn Material + Sync>,
+}
+
+impl Quad {
+    pub fn new(
+        corner: Vector3,
+        u: Vector3,
+        v: Vector3,
+        material: Box<dyn Material + Sync>,
+    ) -> Quad {
+        Quad {
+            corner,
+            u,
+            v,
+            normal: u.cross(v),
+            material,
+        }
+    }
+}
+
+impl Hitable for Quad {
+    fn check_hit(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
+        // First where the ray crosses the plane of the quad, like `Plane` does
+        let denominator = self.normal.dot(ray.direction);
+
+        if denominator.abs() < 1e-12 {
+            return None;
+        }
+
+        let t = self.normal.dot(self.corner - ray.origin) / denominator;
+
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        // Then the point in terms of the two edges: solving p = corner + a*u + b*v comes down
+        // to these two ratios of cross products (it's Cramer's rule, in vector form)
+        let p = ray.point_at(t);
+        let offset = p - self.corner;
+        let scale = 1.0 / self.normal.squared_length();
+        let a = self.normal.dot(offset.cross(self.v)) * scale;
+        let b = self.normal.dot(self.u.cross(offset)) * scale;
+
+        if !(0.0..=1.0).contains(&a) || !(0.0..=1.0).contains(&b) {
+            return None;
+        }
+
+        Some(Hit::new(
+            &ray,
+            t,
+            p,
+            self.normal.unit(),
+            (a, b),
+            self.u,
+            &*self.material,
+        ))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let opposite = self.corner + self.u + self.v;
+        let bounds = Aabb::new(self.corner, self.corner)
+            .surrounding(&Aabb::new(self.corner + self.u, self.corner + self.u))
+            .surrounding(&Aabb::new(self.corner + self.v, self.corner + self.v))
+            .surrounding(&Aabb::new(opposite, opposite));
+
+        Some(bounds.padded(1e-4))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raytracer::material::Lambertian;
+
+    #[test]
+    fn hits_inside_its_edges_only() {
+        let quad = Quad::new(
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(2.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            Box::new(Lambertian::new(Vector3::new(0.5, 0.5, 0.5))),
+        );
+        let towards = |x: f64, y: f64| {
+            let ray = Ray::new(Vector3::new(x, y, 1.0), Vector3::new(0.0, 0.0, -1.0), 0.0);
+            quad.check_hit(ray, 0.0, f64::MAX)
+        };
+
+        let hit = towards(1.5, 0.25).unwrap();
+        assert_eq!(hit.t, 1.0);
+        assert_eq!(hit.normal, Vector3::new(0.0, 0.0, 1.0));
+        assert_eq!(hit.uv, (0.75, 0.25));
+
+        assert!(towards(2.5, 0.5).is_none());
+        assert!(towards(1.0, -0.1).is_none());
+
+        let bounds = quad.bounding_box().unwrap();
+        assert!(bounds.max.z > bounds.min.z);
+    }
+}
@@ -0,0 +1,160 @@
+use crate::math::{solve_quadratic, Vector3};
+use crate::raytracer::quadric::{
+    around, azimuth, first_hit, intersections, sorted, spans, Intersection,
+};
+use crate::raytracer::{Aabb, Hit, Hitable, Material, Ray, Span};
+use std::f64::consts::PI;
+
+// Every point within `radius` of the segment from the origin to z = `height`: a cylinder with a
+// half sphere on either end. Its v coordinate goes from the very bottom to the very top.
+pub struct Capsule {
+    radius: f64,
+    height: f64,
+    material: Box<dyn Material + Sync>,
+}
+
+impl Capsule {
+    pub fn new(radius: f64, height: f64, material: Box<dyn Material + Sync>) -> Capsule {
+        Capsule {
+            radius,
+            height: height.max(0.0),
+            material,
+        }
+    }
+
+    fn intersection(&self, t: f64, p: Vector3, normal: Vector3) -> Intersection {
+        let length = self.height + 2.0 * self.radius;
+
+        Intersection {
+            t,
+            p,
+            normal,
+            uv: (azimuth(p) / (2.0 * PI), (p.z + self.radius) / length),
+            dpdu: around(p, 2.0 * PI),
+        }
+    }
+
+    // The half sphere around one end of the segment, on the side facing away from the other end
+    fn end(&self, ray: Ray, t_min: f64, t_max: f64, z: f64, top: bool) -> Vec<Intersection> {
+        let center = Vector3::new(0.0, 0.0, z);
+        let oc = ray.origin - center;
+        let d = ray.direction;
+
+        let roots = solve_quadratic(
+            d.dot(d),
+            2.0 * oc.dot(d),
+            oc.dot(oc) - self.radius * self.radius,
+        );
+
+        intersections(&roots, t_min, t_max, |t| {
+            let p = ray.point_at(t);
+
+            if (p.z >= z) != top {
+                return None;
+            }
+
+            Some(self.intersection(t, p, (p - center) * (1.0 / self.radius)))
+        })
+    }
+
+    // Everywhere the ray goes through the surface between `t_min` and `t_max`, in order
+    fn crossings(&self, ray: Ray, t_min: f64, t_max: f64) -> Vec<Intersection> {
+        let (o, d) = (ray.origin, ray.direction);
+        let radius = self.radius;
+
+        let roots = solve_quadratic(
+            d.x * d.x + d.y * d.y,
+            2.0 * (o.x * d.x + o.y * d.y),
+            o.x * o.x + o.y * o.y - radius * radius,
+        );
+        let mut crossings = intersections(&roots, t_min, t_max, |t| {
+            let p = ray.point_at(t);
+
+            if p.z < 0.0 || p.z > self.height {
+                return None;
+            }
+
+            let normal = Vector3::new(p.x / radius, p.y / radius, 0.0);
+            Some(self.intersection(t, p, normal))
+        });
+
+        crossings.extend(self.end(ray, t_min, t_max, 0.0, false));
+        crossings.extend(self.end(ray, t_min, t_max, self.height, true));
+
+        sorted(crossings)
+    }
+}
+
+impl Hitable for Capsule {
+    fn check_hit(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
+        let crossing = first_hit(self.crossings(ray, t_min, t_max), &ray, self.is_solid())?;
+        Some(crossing.into_hit(&ray, &*self.material))
+    }
+
+    fn is_solid(&self) -> bool {
+        true
+    }
+
+    fn spans(&self, ray: Ray) -> Vec<Span<'_>> {
+        let crossings = self.crossings(ray, f64::NEG_INFINITY, f64::INFINITY);
+        spans(crossings, &ray, &*self.material)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = self.radius;
+
+        Some(Aabb::new(
+            Vector3::new(-r, -r, -r),
+            Vector3::new(r, r, self.height + r),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raytracer::material::Lambertian;
+
+    #[test]
+    fn hits_the_round_ends_and_the_side() {
+        let material = Box::new(Lambertian::new(Vector3::new(0.5, 0.5, 0.5)));
+        let capsule = Capsule::new(1.0, 2.0, material);
+
+        let ray = Ray::new(
+            Vector3::new(0.0, 0.0, 10.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            0.0,
+        );
+        let hit = capsule.check_hit(ray, 0.0, f64::MAX).unwrap();
+        assert_eq!(hit.t, 7.0);
+        assert_eq!(hit.normal, Vector3::new(0.0, 0.0, 1.0));
+        assert_eq!(hit.uv.1, 1.0);
+
+        let ray = Ray::new(
+            Vector3::new(0.0, 0.0, -10.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            0.0,
+        );
+        assert_eq!(capsule.check_hit(ray, 0.0, f64::MAX).unwrap().t, 9.0);
+
+        let ray = Ray::new(
+            Vector3::new(-5.0, 0.0, 1.5),
+            Vector3::new(1.0, 0.0, 0.0),
+            0.0,
+        );
+        let hit = capsule.check_hit(ray, 0.0, f64::MAX).unwrap();
+        assert_eq!(hit.t, 4.0);
+        assert_eq!(hit.normal, Vector3::new(-1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn rays_from_inside_do_not_hit_it() {
+        let material = Box::new(Lambertian::new(Vector3::new(0.5, 0.5, 0.5)));
+        let capsule = Capsule::new(1.0, 2.0, material);
+
+        for direction in &[Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0)] {
+            let ray = Ray::new(Vector3::new(0.0, 0.0, 1.0), *direction, 0.0);
+            assert!(capsule.check_hit(ray, 0.0, f64::MAX).is_none());
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use crate::math::{solve_quadratic, Vector3};
+use crate::raytracer::quadric::{
+    around, azimuth, cap, first_hit, intersections, sorted, spans, sweep, Intersection,
+};
+use crate::raytracer::{Aabb, Hit, Hitable, Material, Ray, Span};
+use std::f64::consts::PI;
+
+// A cone with its base of `radius` at z = 0 and its tip at z = `height`, kept from `z_min` to
+// `z_max`. Cutting it below the tip leaves a frustum, with a smaller cap on top.
+pub struct Cone {
+    radius: f64,
+    height: f64,
+    z_min: f64,
+    z_max: f64,
+    phi_max: f64,
+    capped: bool,
+    material: Box<dyn Material + Sync>,
+}
+
+impl Cone {
+    // `phi_max` is in degrees, and `z_min` and `z_max` are kept between the base and the tip
+    pub fn new(
+        radius: f64,
+        height: f64,
+        z_min: f64,
+        z_max: f64,
+        phi_max: f64,
+        capped: bool,
+        material: Box<dyn Material + Sync>,
+    ) -> Cone {
+        let clamp = |z: f64| z.max(0.0).min(height);
+
+        Cone {
+            radius,
+            height,
+            z_min: clamp(z_min.min(z_max)),
+            z_max: clamp(z_max.max(z_min)),
+            phi_max: sweep(phi_max),
+            capped,
+            material,
+        }
+    }
+
+    fn radius_at(&self, z: f64) -> f64 {
+        self.radius * (1.0 - z / self.height)
+    }
+}
+
+impl Cone {
+    // Everywhere the ray goes through the surface between `t_min` and `t_max`, in order
+    fn crossings(&self, ray: Ray, t_min: f64, t_max: f64) -> Vec<Intersection> {
+        // x^2 + y^2 = k * (z - height)^2, the radius shrinking linearly towards the tip
+        let (o, d) = (ray.origin, ray.direction);
+        let k = (self.radius / self.height).powi(2);
+        let oz = o.z - self.height;
+
+        let roots = solve_quadratic(
+            d.x * d.x + d.y * d.y - k * d.z * d.z,
+            2.0 * (o.x * d.x + o.y * d.y - k * oz * d.z),
+            o.x * o.x + o.y * o.y - k * oz * oz,
+        );
+        let mut crossings = intersections(&roots, t_min, t_max, |t| {
+            let p = ray.point_at(t);
+            let phi = azimuth(p);
+
+            if p.z < self.z_min || p.z > self.z_max || phi > self.phi_max {
+                return None;
+            }
+
+            Some(Intersection {
+                t,
+                p,
+                normal: Vector3::new(p.x, p.y, k * (self.height - p.z)).unit(),
+                uv: (
+                    phi / self.phi_max,
+                    (p.z - self.z_min) / (self.z_max - self.z_min),
+                ),
+                dpdu: around(p, self.phi_max),
+            })
+        });
+
+        if self.capped {
+            let (bottom, top) = (self.radius_at(self.z_min), self.radius_at(self.z_max));
+            crossings.extend(cap(
+                ray,
+                t_min,
+                t_max,
+                self.z_min,
+                bottom,
+                self.phi_max,
+                false,
+            ));
+            crossings.extend(cap(ray, t_min, t_max, self.z_max, top, self.phi_max, true));
+        }
+
+        sorted(crossings)
+    }
+}
+
+impl Hitable for Cone {
+    fn check_hit(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
+        let crossing = first_hit(self.crossings(ray, t_min, t_max), &ray, self.is_solid())?;
+        Some(crossing.into_hit(&ray, &*self.material))
+    }
+
+    fn is_solid(&self) -> bool {
+        self.capped && self.phi_max == 2.0 * PI
+    }
+
+    fn spans(&self, ray: Ray) -> Vec<Span<'_>> {
+        let crossings = self.crossings(ray, f64::NEG_INFINITY, f64::INFINITY);
+        spans(crossings, &ray, &*self.material)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = self.radius_at(self.z_min);
+
+        Some(Aabb::new(
+            Vector3::new(-r, -r, self.z_min),
+            Vector3::new(r, r, self.z_max),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raytracer::material::Lambertian;
+
+    #[test]
+    fn hits_the_slanted_side_and_the_cut_off_top() {
+        let material = Box::new(Lambertian::new(Vector3::new(0.5, 0.5, 0.5)));
+        let cone = Cone::new(1.0, 2.0, 0.0, 1.0, 360.0, true, material);
+
+        // Halfway up, the side is half a unit from the axis and leans back by the same slope
+        let ray = Ray::new(
+            Vector3::new(3.0, 0.0, 1.0),
+            Vector3::new(-1.0, 0.0, 0.0),
+            0.0,
+        );
+        let hit = cone.check_hit(ray, 0.0, f64::MAX).unwrap();
+        assert!((hit.t - 2.5).abs() < 1e-9);
+        assert!((hit.normal - Vector3::new(2.0, 0.0, 1.0).unit()).magnitude() < 1e-9);
+        assert!((hit.uv.1 - 1.0).abs() < 1e-9);
+
+        // The tip was cut off, and the top cap is only half as wide as the base
+        let ray = Ray::new(
+            Vector3::new(0.4, 0.0, 5.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            0.0,
+        );
+        let hit = cone.check_hit(ray, 0.0, f64::MAX).unwrap();
+        assert_eq!(hit.t, 4.0);
+        assert_eq!(hit.normal, Vector3::new(0.0, 0.0, 1.0));
+
+        let ray = Ray::new(
+            Vector3::new(0.6, 0.0, 5.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            0.0,
+        );
+        let hit = cone.check_hit(ray, 0.0, f64::MAX).unwrap();
+        assert!((hit.t - 4.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn rays_from_inside_a_capped_cone_do_not_hit_it() {
+        let material = || Box::new(Lambertian::new(Vector3::new(0.5, 0.5, 0.5)));
+        let ray = Ray::new(
+            Vector3::new(0.0, 0.0, 0.5),
+            Vector3::new(1.0, 0.0, 0.0),
+            0.0,
+        );
+
+        let capped = Cone::new(1.0, 2.0, 0.0, 1.0, 360.0, true, material());
+        assert!(capped.check_hit(ray, 0.0, f64::MAX).is_none());
+
+        let open = Cone::new(1.0, 2.0, 0.0, 1.0, 360.0, false, material());
+        let hit = open.check_hit(ray, 0.0, f64::MAX).unwrap();
+        assert!((hit.t - 0.75).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,189 @@
+use crate::math::{solve_quadratic, Vector3};
+use crate::raytracer::quadric::{
+    around, azimuth, cap, first_hit, intersections, sorted, spans, sweep, Intersection,
+};
+use crate::raytracer::{Aabb, Hit, Hitable, Material, Ray, Span};
+use std::f64::consts::PI;
+
+// x^2 + y^2 = radius^2, from `z_min` to `z_max`
+pub struct Cylinder {
+    radius: f64,
+    z_min: f64,
+    z_max: f64,
+    phi_max: f64,
+    capped: bool,
+    material: Box<dyn Material + Sync>,
+}
+
+impl Cylinder {
+    // `phi_max` is in degrees
+    pub fn new(
+        radius: f64,
+        z_min: f64,
+        z_max: f64,
+        phi_max: f64,
+        capped: bool,
+        material: Box<dyn Material + Sync>,
+    ) -> Cylinder {
+        Cylinder {
+            radius,
+            z_min: z_min.min(z_max),
+            z_max: z_max.max(z_min),
+            phi_max: sweep(phi_max),
+            capped,
+            material,
+        }
+    }
+}
+
+impl Cylinder {
+    // Everywhere the ray goes through the surface between `t_min` and `t_max`, in order
+    fn crossings(&self, ray: Ray, t_min: f64, t_max: f64) -> Vec<Intersection> {
+        let (o, d) = (ray.origin, ray.direction);
+        let radius = self.radius;
+
+        let roots = solve_quadratic(
+            d.x * d.x + d.y * d.y,
+            2.0 * (o.x * d.x + o.y * d.y),
+            o.x * o.x + o.y * o.y - radius * radius,
+        );
+        let mut crossings = intersections(&roots, t_min, t_max, |t| {
+            let p = ray.point_at(t);
+            let phi = azimuth(p);
+
+            if p.z < self.z_min || p.z > self.z_max || phi > self.phi_max {
+                return None;
+            }
+
+            Some(Intersection {
+                t,
+                p,
+                normal: Vector3::new(p.x / radius, p.y / radius, 0.0),
+                uv: (
+                    phi / self.phi_max,
+                    (p.z - self.z_min) / (self.z_max - self.z_min),
+                ),
+                dpdu: around(p, self.phi_max),
+            })
+        });
+
+        if self.capped {
+            crossings.extend(cap(
+                ray,
+                t_min,
+                t_max,
+                self.z_min,
+                radius,
+                self.phi_max,
+                false,
+            ));
+            crossings.extend(cap(
+                ray,
+                t_min,
+                t_max,
+                self.z_max,
+                radius,
+                self.phi_max,
+                true,
+            ));
+        }
+
+        sorted(crossings)
+    }
+}
+
+impl Hitable for Cylinder {
+    fn check_hit(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
+        let crossing = first_hit(self.crossings(ray, t_min, t_max), &ray, self.is_solid())?;
+        Some(crossing.into_hit(&ray, &*self.material))
+    }
+
+    fn is_solid(&self) -> bool {
+        self.capped && self.phi_max == 2.0 * PI
+    }
+
+    fn spans(&self, ray: Ray) -> Vec<Span<'_>> {
+        let crossings = self.crossings(ray, f64::NEG_INFINITY, f64::INFINITY);
+        spans(crossings, &ray, &*self.material)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = self.radius;
+
+        Some(Aabb::new(
+            Vector3::new(-r, -r, self.z_min),
+            Vector3::new(r, r, self.z_max),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raytracer::material::Lambertian;
+
+    fn cylinder(phi_max: f64, capped: bool) -> Cylinder {
+        let material = Box::new(Lambertian::new(Vector3::new(0.5, 0.5, 0.5)));
+        Cylinder::new(1.0, 0.0, 2.0, phi_max, capped, material)
+    }
+
+    #[test]
+    fn hits_the_side_and_the_caps() {
+        let (open, closed) = (cylinder(360.0, false), cylinder(360.0, true));
+
+        let ray = Ray::new(
+            Vector3::new(3.0, 0.0, 1.0),
+            Vector3::new(-1.0, 0.0, 0.0),
+            0.0,
+        );
+        let hit = open.check_hit(ray, 0.0, f64::MAX).unwrap();
+        assert_eq!(hit.t, 2.0);
+        assert_eq!(hit.normal, Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(hit.uv, (0.0, 0.5));
+
+        // Straight down the axis, through the open ends or onto the top cap
+        let ray = Ray::new(
+            Vector3::new(0.5, 0.0, 5.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            0.0,
+        );
+        assert!(open.check_hit(ray, 0.0, f64::MAX).is_none());
+        let hit = closed.check_hit(ray, 0.0, f64::MAX).unwrap();
+        assert_eq!(hit.t, 3.0);
+        assert_eq!(hit.normal, Vector3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn partial_sweeps_let_rays_through_to_the_inside() {
+        // Half a turn leaves out the side towards -y, so the ray goes on to hit the other side
+        let ray = Ray::new(
+            Vector3::new(0.0, -3.0, 1.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            0.0,
+        );
+        let (whole, half) = (cylinder(360.0, false), cylinder(180.0, false));
+        assert_eq!(whole.check_hit(ray, 0.0, f64::MAX).unwrap().t, 2.0);
+        let hit = half.check_hit(ray, 0.0, f64::MAX).unwrap();
+        assert_eq!(hit.t, 4.0);
+        assert!((hit.uv.0 - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn rays_from_inside_only_hit_open_cylinders() {
+        let ray = Ray::new(
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            0.0,
+        );
+        assert!(cylinder(360.0, true)
+            .check_hit(ray, 0.0, f64::MAX)
+            .is_none());
+        assert_eq!(
+            cylinder(360.0, false)
+                .check_hit(ray, 0.0, f64::MAX)
+                .unwrap()
+                .t,
+            1.0
+        );
+    }
+}
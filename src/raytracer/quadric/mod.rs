@@ -0,0 +1,163 @@
+// Round shapes built around the z axis, from its origin up: cylinders, cones and paraboloids
+// (quadrics, the surfaces where some quadratic in x, y and z is zero), tori and capsules. They
+// are found analytically, so they stay perfectly smooth however close they're looked at and
+// take no more memory than their few parameters. Scenes place them anywhere with instances.
+//
+// Most of them can be cut short: they go only from `z_min` to `z_max` along the axis, and only
+// `phi_max` degrees around it, counterclockwise from +x. The flat ends of cylinders, cones and
+// paraboloids can be closed with caps, which makes them solid (as long as they go the whole way
+// around) so they can be used in CSG. Their u coordinate goes around the axis, from 0 to 1
+// over the whole sweep, and v along it, or out from the axis on the caps.
+use crate::math::Vector3;
+use crate::raytracer::{Hit, Material, Ray, Span};
+use std::f64::consts::PI;
+
+mod capsule;
+mod cone;
+mod cylinder;
+mod paraboloid;
+mod torus;
+
+pub use capsule::Capsule;
+pub use cone::Cone;
+pub use cylinder::Cylinder;
+pub use paraboloid::Paraboloid;
+pub use torus::Torus;
+
+// Where a ray meets one part of a shape, before it's known to be the closest part
+pub struct Intersection {
+    pub t: f64,
+    pub p: Vector3,
+    pub normal: Vector3,
+    pub uv: (f64, f64),
+    pub dpdu: Vector3,
+}
+
+impl Intersection {
+    pub fn into_hit<'a>(self, ray: &Ray, material: &'a dyn Material) -> Hit<'a> {
+        Hit::new(
+            ray,
+            self.t,
+            self.p,
+            self.normal,
+            self.uv,
+            self.dpdu,
+            material,
+        )
+    }
+}
+
+// The angle of a point around the z axis, from 0 to 2π counterclockwise from +x
+pub fn azimuth(p: Vector3) -> f64 {
+    let phi = p.y.atan2(p.x);
+
+    if phi < 0.0 {
+        phi + 2.0 * PI
+    } else {
+        phi
+    }
+}
+
+// How a point moves as u grows, when u goes around the z axis from 0 to 1 over `phi_max` radians
+pub fn around(p: Vector3, phi_max: f64) -> Vector3 {
+    Vector3::new(-p.y, p.x, 0.0) * phi_max
+}
+
+// Every one of `roots` between `t_min` and `t_max` that is on the part of the surface that's kept
+pub fn intersections(
+    roots: &[f64],
+    t_min: f64,
+    t_max: f64,
+    surface: impl Fn(f64) -> Option<Intersection>,
+) -> Vec<Intersection> {
+    roots
+        .iter()
+        .filter(|&&t| t >= t_min && t <= t_max)
+        .filter_map(|&t| surface(t))
+        .collect()
+}
+
+// Where a shape is hit, out of its crossings in order: the first of them, unless the shape is
+// solid and the ray starts inside it, see `Hitable::check_hit`. Crossings go into a solid where
+// its outward normal is against the ray.
+pub fn first_hit(crossings: Vec<Intersection>, ray: &Ray, solid: bool) -> Option<Intersection> {
+    crossings
+        .into_iter()
+        .find(|crossing| !solid || crossing.normal.dot(ray.direction) < 0.0)
+}
+
+// In order along the ray
+pub fn sorted(mut intersections: Vec<Intersection>) -> Vec<Intersection> {
+    intersections.sort_by(|a, b| a.t.total_cmp(&b.t));
+    intersections
+}
+
+// A closed shape is entered and left in turns, so every two crossings of its surface along the
+// whole line of a ray make up one span inside of it
+pub fn spans<'a>(
+    crossings: Vec<Intersection>,
+    ray: &Ray,
+    material: &'a dyn Material,
+) -> Vec<Span<'a>> {
+    let mut crossings = crossings.into_iter();
+    let mut spans = Vec::new();
+
+    while let (Some(enter), Some(exit)) = (crossings.next(), crossings.next()) {
+        spans.push(Span {
+            enter: enter.into_hit(ray, material),
+            exit: exit.into_hit(ray, material),
+        });
+    }
+
+    spans
+}
+
+// The disk closing one end of a shape at height `z`, facing up along the axis or down. `phi_max`
+// is in radians.
+pub fn cap(
+    ray: Ray,
+    t_min: f64,
+    t_max: f64,
+    z: f64,
+    radius: f64,
+    phi_max: f64,
+    facing_up: bool,
+) -> Option<Intersection> {
+    if ray.direction.z == 0.0 || radius <= 0.0 {
+        return None;
+    }
+
+    let t = (z - ray.origin.z) / ray.direction.z;
+
+    if t < t_min || t > t_max {
+        return None;
+    }
+
+    let p = ray.point_at(t);
+    let distance = p.x.hypot(p.y);
+    let phi = azimuth(p);
+
+    if distance > radius || phi > phi_max {
+        return None;
+    }
+
+    let normal = Vector3::new(0.0, 0.0, if facing_up { 1.0 } else { -1.0 });
+
+    Some(Intersection {
+        t,
+        p,
+        normal,
+        uv: (phi / phi_max, distance / radius),
+        dpdu: around(p, phi_max),
+    })
+}
+
+// Degrees around the axis into radians. A whole turn comes out as exactly 2π, so that shapes
+// can tell whether they're closed.
+pub fn sweep(degrees: f64) -> f64 {
+    if degrees >= 360.0 {
+        2.0 * PI
+    } else {
+        degrees.to_radians()
+    }
+}
@@ -0,0 +1,171 @@
+use crate::math::{solve_quadratic, Vector3};
+use crate::raytracer::quadric::{
+    around, azimuth, cap, first_hit, intersections, sorted, spans, sweep, Intersection,
+};
+use crate::raytracer::{Aabb, Hit, Hitable, Material, Ray, Span};
+use std::f64::consts::PI;
+
+// A bowl with its bottom at the origin, opening up to `radius` at z = `height`, and kept from
+// `z_min` to `z_max`
+pub struct Paraboloid {
+    radius: f64,
+    height: f64,
+    z_min: f64,
+    z_max: f64,
+    phi_max: f64,
+    capped: bool,
+    material: Box<dyn Material + Sync>,
+}
+
+impl Paraboloid {
+    // `phi_max` is in degrees, and `z_min` and `z_max` are kept between 0 and `height`
+    pub fn new(
+        radius: f64,
+        height: f64,
+        z_min: f64,
+        z_max: f64,
+        phi_max: f64,
+        capped: bool,
+        material: Box<dyn Material + Sync>,
+    ) -> Paraboloid {
+        let clamp = |z: f64| z.max(0.0).min(height);
+
+        Paraboloid {
+            radius,
+            height,
+            z_min: clamp(z_min.min(z_max)),
+            z_max: clamp(z_max.max(z_min)),
+            phi_max: sweep(phi_max),
+            capped,
+            material,
+        }
+    }
+
+    fn radius_at(&self, z: f64) -> f64 {
+        self.radius * (z / self.height).sqrt()
+    }
+}
+
+impl Paraboloid {
+    // Everywhere the ray goes through the surface between `t_min` and `t_max`, in order
+    fn crossings(&self, ray: Ray, t_min: f64, t_max: f64) -> Vec<Intersection> {
+        // k * (x^2 + y^2) = z
+        let (o, d) = (ray.origin, ray.direction);
+        let k = self.height / (self.radius * self.radius);
+
+        let roots = solve_quadratic(
+            k * (d.x * d.x + d.y * d.y),
+            2.0 * k * (o.x * d.x + o.y * d.y) - d.z,
+            k * (o.x * o.x + o.y * o.y) - o.z,
+        );
+        let mut crossings = intersections(&roots, t_min, t_max, |t| {
+            let p = ray.point_at(t);
+            let phi = azimuth(p);
+
+            if p.z < self.z_min || p.z > self.z_max || phi > self.phi_max {
+                return None;
+            }
+
+            Some(Intersection {
+                t,
+                p,
+                normal: Vector3::new(2.0 * k * p.x, 2.0 * k * p.y, -1.0).unit(),
+                uv: (
+                    phi / self.phi_max,
+                    (p.z - self.z_min) / (self.z_max - self.z_min),
+                ),
+                dpdu: around(p, self.phi_max),
+            })
+        });
+
+        if self.capped {
+            let (bottom, top) = (self.radius_at(self.z_min), self.radius_at(self.z_max));
+            crossings.extend(cap(
+                ray,
+                t_min,
+                t_max,
+                self.z_min,
+                bottom,
+                self.phi_max,
+                false,
+            ));
+            crossings.extend(cap(ray, t_min, t_max, self.z_max, top, self.phi_max, true));
+        }
+
+        sorted(crossings)
+    }
+}
+
+impl Hitable for Paraboloid {
+    fn check_hit(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
+        let crossing = first_hit(self.crossings(ray, t_min, t_max), &ray, self.is_solid())?;
+        Some(crossing.into_hit(&ray, &*self.material))
+    }
+
+    fn is_solid(&self) -> bool {
+        self.capped && self.phi_max == 2.0 * PI
+    }
+
+    fn spans(&self, ray: Ray) -> Vec<Span<'_>> {
+        let crossings = self.crossings(ray, f64::NEG_INFINITY, f64::INFINITY);
+        spans(crossings, &ray, &*self.material)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = self.radius_at(self.z_max);
+
+        Some(Aabb::new(
+            Vector3::new(-r, -r, self.z_min),
+            Vector3::new(r, r, self.z_max),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raytracer::material::Lambertian;
+
+    #[test]
+    fn hits_the_inside_of_the_bowl() {
+        let material = Box::new(Lambertian::new(Vector3::new(0.5, 0.5, 0.5)));
+        let bowl = Paraboloid::new(2.0, 4.0, 0.0, 4.0, 360.0, false, material);
+
+        // Down into the bowl, which is 1 wide at a height of 1, onto the inside of its surface
+        let ray = Ray::new(
+            Vector3::new(1.0, 0.0, 5.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            0.0,
+        );
+        let hit = bowl.check_hit(ray, 0.0, f64::MAX).unwrap();
+        assert!((hit.t - 4.0).abs() < 1e-9);
+        assert!((hit.normal - Vector3::new(-2.0, 0.0, 1.0).unit()).magnitude() < 1e-9);
+        assert!(!hit.front_face);
+        assert!((hit.uv.1 - 0.25).abs() < 1e-9);
+
+        // Outside of its rim
+        let ray = Ray::new(
+            Vector3::new(2.5, 0.0, 5.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            0.0,
+        );
+        assert!(bowl.check_hit(ray, 0.0, f64::MAX).is_none());
+    }
+
+    #[test]
+    fn rays_from_inside_a_capped_bowl_do_not_hit_it() {
+        let material = || Box::new(Lambertian::new(Vector3::new(0.5, 0.5, 0.5)));
+        let ray = Ray::new(
+            Vector3::new(0.0, 0.0, 2.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            0.0,
+        );
+
+        let capped = Paraboloid::new(2.0, 4.0, 0.0, 4.0, 360.0, true, material());
+        assert!(capped.check_hit(ray, 0.0, f64::MAX).is_none());
+
+        let open = Paraboloid::new(2.0, 4.0, 0.0, 4.0, 360.0, false, material());
+        let hit = open.check_hit(ray, 0.0, f64::MAX).unwrap();
+        assert!((hit.t - 2f64.sqrt()).abs() < 1e-9);
+    }
+}
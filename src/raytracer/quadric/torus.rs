@@ -0,0 +1,185 @@
+use crate::math::{solve_quartic, Vector3};
+use crate::raytracer::quadric::{
+    around, azimuth, first_hit, intersections, spans, sweep, Intersection,
+};
+use crate::raytracer::{Aabb, Hit, Hitable, Material, Ray, Span};
+use std::f64::consts::PI;
+
+// A ring around the z axis: a tube of `minor_radius` whose center goes around the axis at
+// `major_radius`. Its v coordinate goes around the tube, from the outer edge up over the top.
+pub struct Torus {
+    major_radius: f64,
+    minor_radius: f64,
+    phi_max: f64,
+    material: Box<dyn Material + Sync>,
+}
+
+impl Torus {
+    // `phi_max` is in degrees
+    pub fn new(
+        major_radius: f64,
+        minor_radius: f64,
+        phi_max: f64,
+        material: Box<dyn Material + Sync>,
+    ) -> Torus {
+        Torus {
+            major_radius,
+            minor_radius,
+            phi_max: sweep(phi_max),
+            material,
+        }
+    }
+}
+
+impl Torus {
+    // Everywhere the ray goes through the surface between `t_min` and `t_max`, in order
+    fn crossings(&self, ray: Ray, t_min: f64, t_max: f64) -> Vec<Intersection> {
+        // Points on the torus satisfy (|p|^2 - R^2 - r^2)^2 = 4R^2 * (r^2 - z^2), which along
+        // the ray is a quartic. It's solved along a unit direction, which keeps its coefficients
+        // from getting too large or too small to be solved precisely.
+        let length = ray.direction.magnitude();
+        let (o, d) = (ray.origin, ray.direction * (1.0 / length));
+        let big = self.major_radius * self.major_radius;
+        let small = self.minor_radius * self.minor_radius;
+
+        let f = o.dot(d);
+        let e = o.dot(o) - big - small;
+        let roots: Vec<f64> = solve_quartic(
+            1.0,
+            4.0 * f,
+            2.0 * e + 4.0 * f * f + 4.0 * big * d.z * d.z,
+            4.0 * f * e + 8.0 * big * o.z * d.z,
+            e * e - 4.0 * big * (small - o.z * o.z),
+        )
+        .into_iter()
+        .map(|distance| distance / length)
+        .collect();
+
+        intersections(&roots, t_min, t_max, |t| {
+            let p = ray.point_at(t);
+            let phi = azimuth(p);
+
+            if phi > self.phi_max {
+                return None;
+            }
+
+            // Out from the center of the tube, which is the closest point of the circle it goes
+            // around
+            let distance = p.x.hypot(p.y);
+            let center = Vector3::new(p.x, p.y, 0.0) * (self.major_radius / distance);
+            let theta = p.z.atan2(distance - self.major_radius);
+            let theta = if theta < 0.0 { theta + 2.0 * PI } else { theta };
+
+            Some(Intersection {
+                t,
+                p,
+                normal: (p - center).unit(),
+                uv: (phi / self.phi_max, theta / (2.0 * PI)),
+                dpdu: around(p, self.phi_max),
+            })
+        })
+    }
+}
+
+impl Hitable for Torus {
+    fn check_hit(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
+        let crossing = first_hit(self.crossings(ray, t_min, t_max), &ray, self.is_solid())?;
+        Some(crossing.into_hit(&ray, &*self.material))
+    }
+
+    fn is_solid(&self) -> bool {
+        self.phi_max == 2.0 * PI
+    }
+
+    fn spans(&self, ray: Ray) -> Vec<Span<'_>> {
+        let crossings = self.crossings(ray, f64::NEG_INFINITY, f64::INFINITY);
+        spans(crossings, &ray, &*self.material)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let (outer, r) = (self.major_radius + self.minor_radius, self.minor_radius);
+
+        Some(Aabb::new(
+            Vector3::new(-outer, -outer, -r),
+            Vector3::new(outer, outer, r),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raytracer::material::Lambertian;
+
+    fn torus() -> Torus {
+        let material = Box::new(Lambertian::new(Vector3::new(0.5, 0.5, 0.5)));
+        Torus::new(2.0, 0.5, 360.0, material)
+    }
+
+    #[test]
+    fn hits_the_outside_and_goes_through_the_hole() {
+        let torus = torus();
+
+        let ray = Ray::new(
+            Vector3::new(5.0, 0.0, 0.0),
+            Vector3::new(-2.0, 0.0, 0.0),
+            0.0,
+        );
+        let hit = torus.check_hit(ray, 0.0, f64::MAX).unwrap();
+        assert!((hit.t - 1.25).abs() < 1e-9);
+        assert!((hit.normal - Vector3::new(1.0, 0.0, 0.0)).magnitude() < 1e-9);
+
+        // Down through the hole in the middle, and onto the top of the tube
+        let ray = Ray::new(
+            Vector3::new(0.0, 0.0, 5.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            0.0,
+        );
+        assert!(torus.check_hit(ray, 0.0, f64::MAX).is_none());
+
+        let ray = Ray::new(
+            Vector3::new(0.0, -2.0, 5.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            0.0,
+        );
+        let hit = torus.check_hit(ray, 0.0, f64::MAX).unwrap();
+        assert!((hit.t - 4.5).abs() < 1e-9);
+        assert!((hit.normal - Vector3::new(0.0, 0.0, 1.0)).magnitude() < 1e-9);
+        assert!((hit.uv.0 - 0.75).abs() < 1e-9 && (hit.uv.1 - 0.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn finds_grazing_hits_from_far_away() {
+        let torus = torus();
+
+        // Glancing off the top of the tube from a long way off
+        let origin = Vector3::new(-1000.0, 2.0, 0.49);
+        let ray = Ray::new(origin, Vector3::new(1.0, 0.0, 0.0), 0.0);
+        let hit = torus.check_hit(ray, 0.0, f64::MAX).unwrap();
+        assert!((hit.p.x.hypot(hit.p.y) - 2.0).abs() < 0.2);
+        assert!(hit.p.z > 0.4);
+    }
+
+    #[test]
+    fn rays_from_inside_the_tube_hit_where_they_go_back_in() {
+        let torus = torus();
+
+        // Out of the tube towards the hole, and into it again on the far side
+        let ray = Ray::new(
+            Vector3::new(2.0, 0.0, 0.0),
+            Vector3::new(-1.0, 0.0, 0.0),
+            0.0,
+        );
+        let hit = torus.check_hit(ray, 0.0, f64::MAX).unwrap();
+        assert!((hit.t - 3.5).abs() < 1e-9);
+        assert!((hit.normal - Vector3::new(1.0, 0.0, 0.0)).magnitude() < 1e-9);
+        assert!(hit.front_face);
+
+        let ray = Ray::new(
+            Vector3::new(2.0, 0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            0.0,
+        );
+        assert!(torus.check_hit(ray, 0.0, f64::MAX).is_none());
+    }
+}
@@ -1,14 +1,17 @@
 use crate::math::Vector3;
 
+// Besides where it goes, a ray has the moment it was sent at, somewhere while the camera's
+// shutter was open, so that moving objects are hit where they were at that moment
 #[derive(Copy, Clone, Debug)]
 pub struct Ray {
     pub origin: Vector3,
     pub direction: Vector3,
+    pub time: f64,
 }
 
 impl Ray {
-    pub fn new(origin: Vector3, direction: Vector3) -> Ray {
-        Ray { origin, direction }
+    pub fn new(origin: Vector3, direction: Vector3, time: f64) -> Ray {
+        Ray { origin, direction, time }
     }
 
     pub fn point_at(&self, t: f64) -> Vector3 {
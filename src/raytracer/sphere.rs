@@ -1,20 +1,30 @@
-use crate::math::Vector3;
-use crate::raytracer::{Ray, Hit, Hitable, Material};
+use crate::math::{solve_quadratic, Vector3};
+use std::f64::consts::PI;
+use crate::raytracer::{Aabb, Ray, Hit, Hitable, Material, Span};
 
 pub struct Sphere {
     center: Vector3,
     radius: f64,
     material: Box<dyn Material + Sync>,
+    // How far the center moves from time 0 to time 1
+    motion: Vector3,
 }
 
 impl Sphere {
     pub fn new(center: Vector3, radius: f64, material: Box<dyn Material + Sync>) -> Sphere {
-        Sphere { center, radius, material }
+        Sphere::moving(center, center, radius, material)
     }
-}
 
-impl Hitable for Sphere {
-    fn check_hit(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<Hit> {
+    // A sphere going in a straight line, from `start` at time 0 to `end` at time 1
+    pub fn moving(start: Vector3, end: Vector3, radius: f64, material: Box<dyn Material + Sync>) -> Sphere {
+        Sphere { center: start, radius, material, motion: end - start }
+    }
+
+    fn center_at(&self, time: f64) -> Vector3 {
+        self.center + self.motion * time
+    }
+
+    fn roots(&self, ray: Ray) -> Option<(f64, f64)> {
         // A ray is a function of the form p(t) = A + B*t, where A is the origin,
         // B the direction. If we consider t a moment in time, the function results
         // in how far the ray has traveled in a specific amount of time.
@@ -31,28 +41,67 @@ impl Hitable for Sphere {
         // exits the sphere. If there's only one, then the ray touches the sphere on a
         // single point right at the surface. If there are no solutions, then the ray
         // does not hit the sphere.
-        let oc = ray.origin - self.center;
+        let oc = ray.origin - self.center_at(ray.time);
         let a = ray.direction.dot(ray.direction);
         let b = 2.0 * oc.dot(ray.direction);
         let c = oc.dot(oc) - self.radius * self.radius;
-        let discriminant = b * b - 4.0 * a * c;
 
-        if discriminant < 0.0 {
-            return None;
+        match solve_quadratic(a, b, c)[..] {
+            [t0, t1] => Some((t0, t1)),
+            _ => None,
         }
+    }
+
+    fn hit_at(&self, ray: Ray, t: f64) -> Hit<'_> {
+        // We calculate the point for the ray at t, subtract from the center of the
+        // sphere and get the unit vector. That unit vector represents the direction
+        // from the center to the surface where the ray hit the sphere.
+        let p = ray.point_at(t);
+        let offset = p - self.center_at(ray.time);
+        let normal = offset.unit();
+
+        // u goes around the y axis, the way that takes x towards -z
+        let dpdu = 2.0 * PI * Vector3::new(offset.z, 0.0, -offset.x);
 
-        let t = (-b - discriminant.sqrt()) / (2.0 * a);
+        Hit::new(&ray, t, p, normal, sphere_uv(normal), dpdu, &(*self.material))
+    }
+}
+
+impl Hitable for Sphere {
+    fn check_hit(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
+        let (t, _) = self.roots(ray)?;
 
         if t >= t_min && t <= t_max {
-            // If the ray hits the sphere, then t represents at which t that
-            // happens. We calculate the point for the ray at t, subtract from the
-            // center of the sphere and get the unit vector. That unit vector
-            // represents the direction from the center to the surface where the
-            // ray hit the sphere.
-            let normal = (ray.point_at(t) - self.center).unit();
-            Some(Hit::new(t, ray.point_at(t), normal, &(*self.material)))
+            Some(self.hit_at(ray, t))
         } else {
             None
         }
     }
+
+    fn is_solid(&self) -> bool {
+        true
+    }
+
+    fn spans(&self, ray: Ray) -> Vec<Span<'_>> {
+        match self.roots(ray) {
+            Some((t0, t1)) => vec![Span { enter: self.hit_at(ray, t0), exit: self.hit_at(ray, t1) }],
+            None => Vec::new(),
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        // Wherever it is while it moves
+        let radius = Vector3::new(self.radius, self.radius, self.radius);
+        let start = Aabb::new(self.center - radius, self.center + radius);
+        let end = Aabb::new(self.center_at(1.0) - radius, self.center_at(1.0) + radius);
+        Some(start.surrounding(&end))
+    }
+}
+
+// Longitude and latitude: u goes around the y axis starting from -x, v from the bottom up
+fn sphere_uv(normal: Vector3) -> (f64, f64) {
+    let theta = (-normal.y).acos();
+    let phi = (-normal.z).atan2(normal.x) + PI;
+
+    (phi / (2.0 * PI), theta / PI)
 }
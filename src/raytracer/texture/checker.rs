@@ -0,0 +1,67 @@
+use crate::math::Vector3;
+use crate::raytracer::texture::Texture;
+use crate::raytracer::Hit;
+
+// Squares that take turns between two textures, `scale` of them for every unit of u and v
+pub struct Checker {
+    even: Box<dyn Texture>,
+    odd: Box<dyn Texture>,
+    scale: f64,
+}
+
+impl Checker {
+    pub fn new(even: Box<dyn Texture>, odd: Box<dyn Texture>, scale: f64) -> Checker {
+        Checker { even, odd, scale }
+    }
+}
+
+impl Texture for Checker {
+    fn value(&self, hit: &Hit) -> Vector3 {
+        let (u, v) = hit.uv;
+        let square = (u * self.scale).floor() + (v * self.scale).floor();
+
+        if square.rem_euclid(2.0) == 0.0 {
+            self.even.value(hit)
+        } else {
+            self.odd.value(hit)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raytracer::material::Lambertian;
+    use crate::raytracer::texture::Constant;
+    use crate::raytracer::Ray;
+
+    #[test]
+    fn alternates_between_squares() {
+        let (white, black) = (Vector3::new(1.0, 1.0, 1.0), Vector3::new(0.0, 0.0, 0.0));
+        let checker = Checker::new(
+            Box::new(Constant::new(white)),
+            Box::new(Constant::new(black)),
+            2.0,
+        );
+
+        let material = Lambertian::new(white);
+        let ray = Ray::new(
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            0.0,
+        );
+        let at = |u, v| {
+            let p = Vector3::new(0.0, 0.0, 0.0);
+            let normal = Vector3::new(0.0, 0.0, 1.0);
+            let dpdu = Vector3::new(1.0, 0.0, 0.0);
+            Hit::new(&ray, 1.0, p, normal, (u, v), dpdu, &material)
+        };
+
+        assert_eq!(checker.value(&at(0.1, 0.1)), white);
+        assert_eq!(checker.value(&at(0.6, 0.1)), black);
+        assert_eq!(checker.value(&at(0.6, 0.6)), white);
+
+        // Planes have negative coordinates too, where the squares carry on
+        assert_eq!(checker.value(&at(-0.1, 0.1)), black);
+    }
+}
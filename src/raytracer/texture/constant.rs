@@ -0,0 +1,20 @@
+use crate::math::Vector3;
+use crate::raytracer::texture::Texture;
+use crate::raytracer::Hit;
+
+// The same value everywhere
+pub struct Constant {
+    value: Vector3,
+}
+
+impl Constant {
+    pub fn new(value: Vector3) -> Constant {
+        Constant { value }
+    }
+}
+
+impl Texture for Constant {
+    fn value(&self, _hit: &Hit) -> Vector3 {
+        self.value
+    }
+}
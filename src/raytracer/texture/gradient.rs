@@ -0,0 +1,33 @@
+use crate::math::Vector3;
+use crate::raytracer::texture::Texture;
+use crate::raytracer::Hit;
+
+// Blends from one color at `start` to another at `end`, along the line between them. It goes by
+// where the hit is in the scene rather than on the surface, so it carries on across objects,
+// and past either end the color stays as it is there.
+pub struct Gradient {
+    start: Vector3,
+    end: Vector3,
+    start_color: Vector3,
+    end_color: Vector3,
+}
+
+impl Gradient {
+    pub fn new(start: Vector3, end: Vector3, start_color: Vector3, end_color: Vector3) -> Gradient {
+        Gradient {
+            start,
+            end,
+            start_color,
+            end_color,
+        }
+    }
+}
+
+impl Texture for Gradient {
+    fn value(&self, hit: &Hit) -> Vector3 {
+        let line = self.end - self.start;
+        let t = ((hit.p - self.start).dot(line) / line.squared_length()).clamp(0.0, 1.0);
+
+        (1.0 - t) * self.start_color + t * self.end_color
+    }
+}
@@ -0,0 +1,86 @@
+use crate::image::Image;
+use crate::math::Vector3;
+use crate::raytracer::texture::Texture;
+use crate::raytracer::Hit;
+use std::sync::Arc;
+
+// An image stretched over the uv square, with v going up from its bottom row, and repeated
+// `scale` times for every unit of u and v. Colors in between pixels are blended from the four
+// closest ones.
+pub struct ImageTexture {
+    image: Arc<Image>,
+    scale: f64,
+}
+
+impl ImageTexture {
+    pub fn new(image: Arc<Image>, scale: f64) -> ImageTexture {
+        ImageTexture { image, scale }
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, hit: &Hit) -> Vector3 {
+        let (width, height) = (self.image.width, self.image.height);
+        let (u, v) = hit.uv;
+
+        // Pixel centers are half a pixel in from the edges
+        let x = (u * self.scale).rem_euclid(1.0) * width as f64 - 0.5;
+        let y = (1.0 - (v * self.scale).rem_euclid(1.0)) * height as f64 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+
+        // Wrapping around the edges, like the image repeats
+        let column = |x: f64| (x as i64).rem_euclid(width as i64) as usize;
+        let row = |y: f64| (y as i64).rem_euclid(height as i64) as usize;
+        let (left, right) = (column(x0), column(x0 + 1.0));
+        let (top, bottom) = (row(y0), row(y0 + 1.0));
+
+        let upper = (1.0 - tx) * self.image.pixel(left, top) + tx * self.image.pixel(right, top);
+        let lower =
+            (1.0 - tx) * self.image.pixel(left, bottom) + tx * self.image.pixel(right, bottom);
+
+        (1.0 - ty) * upper + ty * lower
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raytracer::material::Lambertian;
+    use crate::raytracer::Ray;
+
+    #[test]
+    fn blends_neighbouring_pixels_and_wraps_around() {
+        let (red, green) = (Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        let (blue, white) = (Vector3::new(0.0, 0.0, 1.0), Vector3::new(1.0, 1.0, 1.0));
+        let image = Image {
+            width: 2,
+            height: 2,
+            pixels: vec![red, green, blue, white],
+        };
+        let texture = ImageTexture::new(Arc::new(image), 1.0);
+
+        let material = Lambertian::new(white);
+        let ray = Ray::new(
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            0.0,
+        );
+        let at = |u, v| {
+            let p = Vector3::new(0.0, 0.0, 0.0);
+            let normal = Vector3::new(0.0, 0.0, 1.0);
+            let dpdu = Vector3::new(1.0, 0.0, 0.0);
+            Hit::new(&ray, 1.0, p, normal, (u, v), dpdu, &material)
+        };
+        let yellow = Vector3::new(0.5, 0.5, 0.0);
+
+        // The top row is at the top of the uv square
+        assert_eq!(texture.value(&at(0.25, 0.75)), red);
+        assert_eq!(texture.value(&at(0.25, 0.25)), blue);
+        assert_eq!(texture.value(&at(0.5, 0.75)), yellow);
+
+        // Across the left edge from the first column is the last one
+        assert_eq!(texture.value(&at(0.0, 0.75)), yellow);
+        assert_eq!(texture.value(&at(1.25, 0.75)), red);
+    }
+}
@@ -0,0 +1,19 @@
+// Textures give material parameters, like the color of a lambertian surface or how rough a
+// metal is, a value that can change over the surface. They're looked up at every hit, most of
+// them by its uv coordinates.
+use crate::math::Vector3;
+use crate::raytracer::Hit;
+
+mod checker;
+mod constant;
+mod gradient;
+mod image;
+
+pub use checker::Checker;
+pub use constant::Constant;
+pub use gradient::Gradient;
+pub use image::ImageTexture;
+
+pub trait Texture: Send + Sync {
+    fn value(&self, hit: &Hit) -> Vector3;
+}
@@ -0,0 +1,166 @@
+use std::f64::consts::PI;
+
+// How samples are turned back into pixels. Every sample lands on every pixel whose center is
+// within `radius` of it (in pixels, along both axes), weighted by how far it is from that center,
+// and each pixel ends up with the weighted average of the samples around it.
+//
+// Filters wider than a pixel blur a little, but they smooth out the jagged edges that plain
+// averages (the box filter) leave on thin geometry at low sample counts. Mitchell-Netravali and
+// Lanczos have negative lobes, which keeps the image sharper at the cost of some ringing.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Filter {
+    pub kind: FilterKind,
+    pub radius: f64,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FilterKind {
+    Box,
+    Tent,
+    Gaussian,
+    Mitchell,
+    Lanczos,
+}
+
+impl FilterKind {
+    pub const NAMES: &'static [&'static str] = &["box", "tent", "gaussian", "mitchell", "lanczos"];
+
+    pub fn from_name(name: &str) -> Option<FilterKind> {
+        match name.to_ascii_lowercase().as_str() {
+            "box" => Some(FilterKind::Box),
+            "tent" => Some(FilterKind::Tent),
+            "gaussian" => Some(FilterKind::Gaussian),
+            "mitchell" => Some(FilterKind::Mitchell),
+            "lanczos" => Some(FilterKind::Lanczos),
+            _ => None,
+        }
+    }
+
+    // The radius each filter is usually used with. A box of half a pixel is the plain average of
+    // the samples of each pixel.
+    pub fn default_radius(self) -> f64 {
+        match self {
+            FilterKind::Box => 0.5,
+            FilterKind::Tent => 1.0,
+            FilterKind::Gaussian => 1.5,
+            FilterKind::Mitchell => 2.0,
+            FilterKind::Lanczos => 3.0,
+        }
+    }
+}
+
+impl Default for Filter {
+    fn default() -> Filter {
+        Filter::new(FilterKind::Box)
+    }
+}
+
+impl Filter {
+    pub fn new(kind: FilterKind) -> Filter {
+        Filter {
+            kind,
+            radius: kind.default_radius(),
+        }
+    }
+
+    // How many pixels past its own one a sample can reach
+    pub fn margin(&self) -> usize {
+        (self.radius - 0.5).ceil().max(0.0) as usize
+    }
+
+    // Filters are separable: the weight of a sample is the product of the weights of its
+    // horizontal and vertical distances to the pixel center
+    pub fn weight(&self, dx: f64, dy: f64) -> f64 {
+        self.weight_1d(dx) * self.weight_1d(dy)
+    }
+
+    fn weight_1d(&self, distance: f64) -> f64 {
+        let x = distance.abs();
+        let radius = self.radius;
+
+        // A sample right between two pixels only goes to one of them, the one on its right (or
+        // below it), so the default box is exactly the average of the samples of each pixel
+        if self.kind == FilterKind::Box {
+            return if -radius < distance && distance <= radius {
+                1.0
+            } else {
+                0.0
+            };
+        }
+
+        if x >= radius {
+            return 0.0;
+        }
+
+        match self.kind {
+            FilterKind::Box => unreachable!(),
+            FilterKind::Tent => 1.0 - x / radius,
+            FilterKind::Gaussian => {
+                // Shifted down so it reaches 0 at the radius instead of being cut off
+                let sigma = radius / 3.0;
+                let gaussian = |x: f64| (-x * x / (2.0 * sigma * sigma)).exp();
+                gaussian(x) - gaussian(radius)
+            }
+            FilterKind::Mitchell => {
+                // The cubic with B = C = 1/3 that Mitchell and Netravali recommend, which is
+                // defined over [-2, 2]
+                let (b, c) = (1.0 / 3.0, 1.0 / 3.0);
+                let x = 2.0 * x / radius;
+
+                if x < 1.0 {
+                    ((12.0 - 9.0 * b - 6.0 * c) * x.powi(3)
+                        + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+                        + (6.0 - 2.0 * b))
+                        / 6.0
+                } else {
+                    ((-b - 6.0 * c) * x.powi(3)
+                        + (6.0 * b + 30.0 * c) * x * x
+                        + (-12.0 * b - 48.0 * c) * x
+                        + (8.0 * b + 24.0 * c))
+                        / 6.0
+                }
+            }
+            FilterKind::Lanczos => sinc(x) * sinc(x / radius),
+        }
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x < 1e-5 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn weights_fade_out_at_the_radius() {
+        for &name in FilterKind::NAMES {
+            let filter = Filter::new(FilterKind::from_name(name).unwrap());
+
+            assert!(filter.weight(0.0, 0.0) > 0.0);
+            assert_eq!(filter.weight(-filter.radius, 0.0), 0.0);
+            assert_eq!(filter.weight(0.3, -0.2), filter.weight(-0.3, 0.2));
+        }
+
+        // The box is the only one that doesn't fade
+        let box_filter = Filter::default();
+        assert_eq!(box_filter.weight(0.49, 0.49), 1.0);
+        assert_eq!(box_filter.margin(), 0);
+
+        let tent = Filter::new(FilterKind::Tent);
+        assert_approx_eq!(tent.weight_1d(tent.radius - 1e-9), 0.0, 1e-6);
+        let gaussian = Filter::new(FilterKind::Gaussian);
+        assert_approx_eq!(gaussian.weight_1d(gaussian.radius - 1e-9), 0.0, 1e-6);
+
+        // Mitchell-Netravali dips below zero before reaching its radius
+        let mitchell = Filter::new(FilterKind::Mitchell);
+        assert!(mitchell.weight_1d(1.5) < 0.0);
+        assert_eq!(mitchell.margin(), 2);
+    }
+}
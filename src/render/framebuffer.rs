@@ -0,0 +1,308 @@
+use crate::image::DisplayTransform;
+use crate::math::{luminance, Vector3};
+use crate::render::Filter;
+use std::ops::Range;
+
+// A pixel keeps sums rather than averages, so more samples can be added at any time.
+//
+// The samples taken for the pixel itself are counted, along with the sums of their luminance and
+// of its square, to estimate how noisy the pixel still is. Its color is the sum of every sample
+// that landed close enough to it, in linear radiance, weighted by the reconstruction filter.
+#[derive(Copy, Clone, Debug)]
+pub struct Pixel {
+    pub samples: u32,
+    pub luminance_sum: f64,
+    pub luminance_squares: f64,
+    pub weighted_sum: Vector3,
+    pub weight: f64,
+}
+
+impl Pixel {
+    // Counts a sample taken for this pixel, which `splat` still has to add to the color
+    pub fn add_sample(&mut self, color: Vector3) {
+        let luminance = luminance(color);
+
+        self.samples += 1;
+        self.luminance_sum += luminance;
+        self.luminance_squares += luminance * luminance;
+    }
+
+    pub fn splat(&mut self, color: Vector3, weight: f64) {
+        self.weighted_sum = self.weighted_sum + color * weight;
+        self.weight += weight;
+    }
+
+    // The standard error of the mean luminance, relative to that mean: how far off the pixel
+    // probably still is, as a fraction of its value. Very dark pixels are compared against a
+    // small floor instead, otherwise they would never be considered done.
+    pub fn relative_error(&self) -> f64 {
+        if self.samples < 2 {
+            return f64::INFINITY;
+        }
+
+        let n = f64::from(self.samples);
+        let mean = self.luminance_sum / n;
+        let variance = ((self.luminance_squares - n * mean * mean) / (n - 1.0)).max(0.0);
+
+        (variance / n).sqrt() / mean.max(1e-3)
+    }
+
+    pub fn color(&self) -> Vector3 {
+        // Filters with negative lobes could, in theory, cancel out every sample
+        if self.weight.abs() < 1e-12 {
+            Vector3::new(0.0, 0.0, 0.0)
+        } else {
+            self.weighted_sum * (1.0 / self.weight)
+        }
+    }
+
+    fn add(&mut self, other: &Pixel) {
+        self.samples += other.samples;
+        self.luminance_sum += other.luminance_sum;
+        self.luminance_squares += other.luminance_squares;
+        self.weighted_sum = self.weighted_sum + other.weighted_sum;
+        self.weight += other.weight;
+    }
+}
+
+impl Default for Pixel {
+    fn default() -> Pixel {
+        Pixel {
+            samples: 0,
+            luminance_sum: 0.0,
+            luminance_squares: 0.0,
+            weighted_sum: Vector3::new(0.0, 0.0, 0.0),
+            weight: 0.0,
+        }
+    }
+}
+
+// An image of linear, unclamped colors. Rows are stored top to bottom, like in image files, so
+// `row` 0 is the top of the image.
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<Pixel>,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Framebuffer {
+        Framebuffer {
+            width,
+            height,
+            pixels: vec![Pixel::default(); width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[Pixel] {
+        &self.pixels
+    }
+
+    // The average color of every pixel, top row first
+    pub fn colors(&self) -> impl Iterator<Item = Vector3> + '_ {
+        self.pixels.iter().map(Pixel::color)
+    }
+
+    // Splits the image into tiles of (at most) `size` by `size` pixels, left to right and top
+    // to bottom. Tiles own their pixels, so each one can be rendered on a different thread and
+    // merged back afterwards. Samples near the edges of a tile also land on the pixels around
+    // it, so tiles keep a `margin` of pixels past their edges too.
+    pub fn tiles(&self, size: usize, margin: usize) -> Vec<Tile> {
+        let mut tiles = Vec::new();
+
+        for row in (0..self.height).step_by(size) {
+            for x in (0..self.width).step_by(size) {
+                let columns = x..(x + size).min(self.width);
+                let rows = row..(row + size).min(self.height);
+                let stored_columns =
+                    x.saturating_sub(margin)..(columns.end + margin).min(self.width);
+                let stored_rows = row.saturating_sub(margin)..(rows.end + margin).min(self.height);
+
+                tiles.push(Tile {
+                    pixels: vec![Pixel::default(); stored_columns.len() * stored_rows.len()],
+                    columns,
+                    rows,
+                    stored_columns,
+                    stored_rows,
+                });
+            }
+        }
+
+        tiles
+    }
+
+    // Adds the samples of a tile to the ones already in the image
+    pub fn merge(&mut self, tile: &Tile) {
+        let width = tile.stored_columns.len();
+
+        for (line, row) in tile.pixels.chunks(width).zip(tile.stored_rows.clone()) {
+            let start = row * self.width + tile.stored_columns.start;
+
+            for (pixel, tile_pixel) in self.pixels[start..start + width].iter_mut().zip(line) {
+                pixel.add(tile_pixel);
+            }
+        }
+    }
+
+    // An image of how many samples each pixel got, from blue for none to red for `max_samples`
+    pub fn sample_heatmap(&self, max_samples: usize) -> Framebuffer {
+        let mut heatmap = Framebuffer::new(self.width, self.height);
+
+        for (pixel, heat) in self.pixels.iter().zip(&mut heatmap.pixels) {
+            let t = (f64::from(pixel.samples) / max_samples as f64).min(1.0);
+            heat.splat(Vector3::new(t, 1.0 - (2.0 * t - 1.0).abs(), 1.0 - t), 1.0);
+        }
+
+        heatmap
+    }
+
+    // 8 bit RGB, top row first, ready to be written to an image file
+    pub fn to_rgb8(&self, display: &DisplayTransform) -> Vec<u8> {
+        self.colors()
+            .flat_map(|color| display.apply(color).to_vec())
+            .collect()
+    }
+}
+
+pub struct Tile {
+    columns: Range<usize>,
+    rows: Range<usize>,
+    stored_columns: Range<usize>,
+    stored_rows: Range<usize>,
+    pixels: Vec<Pixel>,
+}
+
+impl Tile {
+    // The columns and rows of the image rendered by this tile
+    pub fn columns(&self) -> Range<usize> {
+        self.columns.clone()
+    }
+
+    pub fn rows(&self) -> Range<usize> {
+        self.rows.clone()
+    }
+
+    pub fn pixel_mut(&mut self, x: usize, row: usize) -> &mut Pixel {
+        let width = self.stored_columns.len();
+        &mut self.pixels[(row - self.stored_rows.start) * width + x - self.stored_columns.start]
+    }
+
+    // Adds a sample taken at (`x`, `row`), in pixels from the top left corner of the image, to
+    // every pixel around it that `filter` reaches
+    pub fn splat(&mut self, x: f64, row: f64, color: Vector3, filter: &Filter) {
+        // Pixel centers are at half pixels
+        let (x, row) = (x - 0.5, row - 0.5);
+        let reach = |center: f64, stored: &Range<usize>| {
+            let first = (center - filter.radius).ceil().max(stored.start as f64) as usize;
+            let last = (center + filter.radius)
+                .floor()
+                .min(stored.end as f64 - 1.0);
+            first..(last + 1.0).max(0.0) as usize
+        };
+
+        for pixel_row in reach(row, &self.stored_rows) {
+            for pixel_x in reach(x, &self.stored_columns) {
+                let weight = filter.weight(pixel_x as f64 - x, pixel_row as f64 - row);
+
+                if weight != 0.0 {
+                    self.pixel_mut(pixel_x, pixel_row).splat(color, weight);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::FilterKind;
+
+    #[test]
+    fn tiles_cover_the_whole_image() {
+        let mut framebuffer = Framebuffer::new(5, 3);
+        let mut tiles = framebuffer.tiles(2, 0);
+
+        let regions: Vec<_> = tiles
+            .iter()
+            .map(|tile| (tile.columns(), tile.rows()))
+            .collect();
+        assert_eq!(
+            regions,
+            vec![
+                (0..2, 0..2),
+                (2..4, 0..2),
+                (4..5, 0..2),
+                (0..2, 2..3),
+                (2..4, 2..3),
+                (4..5, 2..3)
+            ]
+        );
+
+        for tile in &mut tiles {
+            for row in tile.rows() {
+                for x in tile.columns() {
+                    tile.pixel_mut(x, row)
+                        .splat(Vector3::new((row * 5 + x) as f64, 0.0, 0.0), 1.0);
+                }
+            }
+
+            framebuffer.merge(tile);
+        }
+
+        let colors: Vec<_> = framebuffer.colors().map(|color| color.x).collect();
+        assert_eq!(colors, (0..15).map(f64::from).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn keeps_values_above_one() {
+        let mut pixel = Pixel::default();
+        pixel.splat(Vector3::new(4.0, 0.5, 0.0), 1.0);
+        pixel.splat(Vector3::new(2.0, 0.5, 0.0), 1.0);
+
+        assert_eq!(pixel.color().x, 3.0);
+    }
+
+    #[test]
+    fn samples_reach_past_the_edges_of_their_tile() {
+        let filter = Filter::new(FilterKind::Tent);
+        let mut framebuffer = Framebuffer::new(4, 1);
+        let mut tiles = framebuffer.tiles(2, filter.margin());
+
+        // Right between the two tiles, at the border of pixels 1 and 2
+        tiles[0].splat(2.0, 0.5, Vector3::new(1.0, 1.0, 1.0), &filter);
+
+        for tile in &tiles {
+            framebuffer.merge(tile);
+        }
+
+        let weights: Vec<_> = framebuffer
+            .pixels()
+            .iter()
+            .map(|pixel| pixel.weight)
+            .collect();
+        assert_eq!(weights, vec![0.0, 0.5, 0.5, 0.0]);
+    }
+
+    #[test]
+    fn relative_error_shrinks_with_consistent_samples() {
+        let mut flat = Pixel::default();
+        let mut noisy = Pixel::default();
+
+        for i in 0..16 {
+            flat.add_sample(Vector3::new(0.5, 0.5, 0.5));
+            noisy.add_sample(Vector3::new(1.0, 1.0, 1.0) * f64::from(i % 2));
+        }
+
+        assert!(flat.relative_error() < 1e-6);
+        // A pixel that's either black or white: mean 0.5, standard deviation ~0.52
+        assert!((noisy.relative_error() - 0.258).abs() < 1e-3);
+    }
+}
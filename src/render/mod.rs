@@ -0,0 +1,284 @@
+use crate::math::Vector3;
+use crate::random::Random;
+use crate::raytracer::{Camera, Hitable, Ray};
+use crate::sampler::{Halton, Sampler, SamplerKind, Sobol, Stratified};
+
+mod filter;
+mod framebuffer;
+mod scheduler;
+
+pub use filter::{Filter, FilterKind};
+pub use framebuffer::Framebuffer;
+use framebuffer::Tile;
+use std::ops::Range;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Settings {
+    pub width: usize,
+    pub height: usize,
+    // Samples per pixel, or the most any pixel can get with adaptive sampling
+    pub samples: usize,
+    // Render in passes of this many samples per pixel, instead of all at once
+    pub pass_samples: Option<usize>,
+    pub adaptive: Option<Adaptive>,
+    pub sampler: SamplerKind,
+    pub filter: Filter,
+    pub max_depth: usize,
+    pub threads: usize,
+    pub tile_size: usize,
+    pub seed: u64,
+}
+
+// With adaptive sampling, every pixel gets `min_samples` samples and then keeps getting more only
+// while its relative error (see `Pixel::relative_error`) is above `threshold`
+#[derive(Copy, Clone, Debug)]
+pub struct Adaptive {
+    pub min_samples: usize,
+    pub threshold: f64,
+}
+
+pub struct Progress {
+    pub pass: usize,
+    // How many pixels got new samples in this pass
+    pub sampled_pixels: usize,
+    // The most samples any pixel has so far
+    pub max_samples: usize,
+    pub done: bool,
+}
+
+// Renders the image in passes over the whole image: a single one by default, or passes of
+// `settings.pass_samples` samples per pixel. `after_pass` gets the image as it is after each one.
+pub fn render<F: FnMut(&Framebuffer, &Progress)>(
+    scene: &(dyn Hitable + Sync),
+    camera: &Camera,
+    settings: &Settings,
+    mut after_pass: F,
+) -> Framebuffer {
+    let mut framebuffer = Framebuffer::new(settings.width, settings.height);
+    let mut plan = plan_pass(&framebuffer, settings);
+    let mut pass = 0;
+
+    while plan.iter().any(|samples| !samples.is_empty()) {
+        scheduler::render_tiles(
+            &mut framebuffer,
+            settings.tile_size,
+            settings.filter.margin(),
+            settings.threads,
+            |tile| match settings.sampler {
+                SamplerKind::Independent => {
+                    render_tile::<Random>(tile, scene, camera, settings, &plan)
+                }
+                SamplerKind::Stratified => {
+                    render_tile::<Stratified>(tile, scene, camera, settings, &plan)
+                }
+                SamplerKind::Halton => render_tile::<Halton>(tile, scene, camera, settings, &plan),
+                SamplerKind::Sobol => render_tile::<Sobol>(tile, scene, camera, settings, &plan),
+            },
+        );
+
+        let next_plan = plan_pass(&framebuffer, settings);
+
+        pass += 1;
+        after_pass(
+            &framebuffer,
+            &Progress {
+                pass,
+                sampled_pixels: plan.iter().filter(|samples| !samples.is_empty()).count(),
+                max_samples: plan
+                    .iter()
+                    .map(|samples| samples.end as usize)
+                    .max()
+                    .unwrap_or(0),
+                done: next_plan.iter().all(|samples| samples.is_empty()),
+            },
+        );
+
+        plan = next_plan;
+    }
+
+    framebuffer
+}
+
+// Which samples each pixel gets in the next pass, numbered from the samples it already has so
+// every sample keeps its own random numbers however the passes are split
+fn plan_pass(framebuffer: &Framebuffer, settings: &Settings) -> Vec<Range<u32>> {
+    let max = settings.samples as u32;
+    let min = settings
+        .adaptive
+        .map_or(0, |adaptive| adaptive.min_samples as u32)
+        .min(max);
+    let pass_samples = settings
+        .pass_samples
+        .or_else(|| settings.adaptive.map(|adaptive| adaptive.min_samples))
+        .unwrap_or(settings.samples) as u32;
+
+    framebuffer
+        .pixels()
+        .iter()
+        .map(|pixel| {
+            let start = pixel.samples;
+            let converged = match settings.adaptive {
+                Some(adaptive) => start >= min && pixel.relative_error() <= adaptive.threshold,
+                None => false,
+            };
+
+            if start >= max || converged {
+                start..start
+            } else {
+                start..(start + pass_samples).max(min).min(max)
+            }
+        })
+        .collect()
+}
+
+fn render_tile<S: Sampler>(
+    tile: &mut Tile,
+    scene: &(dyn Hitable + Sync),
+    camera: &Camera,
+    settings: &Settings,
+    plan: &[Range<u32>],
+) {
+    let width = settings.width;
+    let height = settings.height;
+
+    for row in tile.rows() {
+        // Rows go top to bottom, but the camera's v goes bottom to top
+        let y = height - row - 1;
+
+        for x in tile.columns() {
+            for sample in plan[row * width + x].clone() {
+                let mut sampler =
+                    S::for_sample(settings.seed, x, y, sample as usize, settings.samples);
+
+                let (du, dv) = sampler.next_2d();
+                let u = (f64::from(x as u32) + du) / f64::from(width as u32);
+                let v = (f64::from(y as u32) + dv) / f64::from(height as u32);
+
+                let ray = camera.get_ray(u, v, &mut sampler);
+
+                let color = color_for(ray, scene, 0, settings.max_depth, &mut sampler);
+
+                tile.pixel_mut(x, row).add_sample(color);
+                tile.splat(
+                    x as f64 + du,
+                    (row + 1) as f64 - dv,
+                    color,
+                    &settings.filter,
+                );
+            }
+        }
+    }
+}
+
+pub fn color_for(
+    ray: Ray,
+    scene: &(dyn Hitable + Sync),
+    depth: usize,
+    max_depth: usize,
+    sampler: &mut dyn Sampler,
+) -> Vector3 {
+    match scene.check_hit(ray, 0.0001, f64::MAX) {
+        Some(hit) => {
+            if depth >= max_depth {
+                return Vector3::new(0.0, 0.0, 0.0);
+            }
+
+            if let Some(scattered_hit) = hit.material.scatter(&hit, &ray, sampler) {
+                color_for(scattered_hit.ray, scene, depth + 1, max_depth, sampler)
+                    * scattered_hit.attenuation
+            } else {
+                Vector3::new(0.0, 0.0, 0.0)
+            }
+        }
+        None => {
+            let unit_direction = ray.direction.unit();
+            let t = 0.5 * (unit_direction.y + 1.0);
+            Vector3::new(1.0, 1.0, 1.0) * (1.0 - t) + Vector3::new(0.5, 0.7, 1.0) * t
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene;
+
+    fn settings(threads: usize, seed: u64) -> Settings {
+        Settings {
+            width: 24,
+            height: 12,
+            samples: 4,
+            pass_samples: None,
+            adaptive: None,
+            sampler: SamplerKind::Sobol,
+            filter: Filter::default(),
+            max_depth: 10,
+            threads,
+            tile_size: 5,
+            seed,
+        }
+    }
+
+    #[test]
+    fn same_seed_renders_the_same_image_with_any_thread_count() {
+        let scene = scene::random_scene(&mut Random::new(7));
+        let camera = scene.camera.build(2.0);
+
+        let image = |threads, seed| {
+            render(&scene.objects, &camera, &settings(threads, seed), |_, _| {})
+                .to_rgb8(&Default::default())
+        };
+
+        let single_thread = image(1, 7);
+        assert_eq!(single_thread, image(5, 7));
+        assert_ne!(single_thread, image(5, 8));
+    }
+
+    #[test]
+    fn progressive_passes_add_up_to_the_full_render() {
+        let scene = scene::random_scene(&mut Random::new(7));
+        let camera = scene.camera.build(2.0);
+
+        let mut passes = Vec::new();
+        let progressive = Settings {
+            pass_samples: Some(3),
+            ..settings(2, 7)
+        };
+        let progressive = render(&scene.objects, &camera, &progressive, |_, progress| {
+            passes.push(progress.max_samples)
+        });
+        let full = render(&scene.objects, &camera, &settings(2, 7), |_, _| {});
+
+        assert_eq!(passes, vec![3, 4]);
+
+        for (a, b) in progressive.colors().zip(full.colors()) {
+            assert!((a - b).magnitude() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn adaptive_sampling_stops_on_converged_pixels() {
+        let scene = scene::random_scene(&mut Random::new(7));
+        let camera = scene.camera.build(2.0);
+
+        let adaptive = Settings {
+            samples: 64,
+            adaptive: Some(Adaptive {
+                min_samples: 4,
+                threshold: 0.05,
+            }),
+            ..settings(2, 7)
+        };
+        let framebuffer = render(&scene.objects, &camera, &adaptive, |_, _| {});
+        let samples: Vec<_> = framebuffer
+            .pixels()
+            .iter()
+            .map(|pixel| pixel.samples)
+            .collect();
+
+        assert!(samples.iter().all(|n| (4..=64).contains(n)));
+        // The sky converges right away, while the noisier parts of the scene get more samples
+        assert!(samples.contains(&4));
+        assert!(samples.iter().any(|&n| n > 4));
+    }
+}
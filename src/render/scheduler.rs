@@ -0,0 +1,54 @@
+use crate::render::framebuffer::{Framebuffer, Tile};
+use std::sync::{mpsc, Mutex};
+
+// Renders the whole image, `tile_size` by `tile_size` pixels at a time, on `threads` workers.
+// Samples can land up to `margin` pixels away from the tile they were taken for.
+//
+// Tiles wait in a shared queue and every worker takes a new one as soon as it finishes the
+// previous. Tiles of sky are done quickly while glass and shadows take longer, and this way no
+// thread sits idle while there's still work left. Finished tiles are sent back to this thread,
+// which merges them into the image.
+pub fn render_tiles<F>(
+    framebuffer: &mut Framebuffer,
+    tile_size: usize,
+    margin: usize,
+    threads: usize,
+    render_tile: F,
+) where
+    F: Fn(&mut Tile) + Sync,
+{
+    let tiles = framebuffer.tiles(tile_size, margin);
+    let workers = threads.min(tiles.len());
+    let queue = Mutex::new(tiles.into_iter());
+    let (sender, receiver) = mpsc::channel();
+
+    crossbeam::scope(|spawner| {
+        for _ in 0..workers {
+            let sender = sender.clone();
+            let (queue, render_tile) = (&queue, &render_tile);
+
+            spawner.spawn(move |_| loop {
+                // The lock is only held while taking the tile, not while rendering it
+                let next = queue.lock().unwrap().next();
+
+                let mut tile = match next {
+                    Some(tile) => tile,
+                    None => break,
+                };
+
+                render_tile(&mut tile);
+                sender
+                    .send(tile)
+                    .expect("the receiver outlives every worker");
+            });
+        }
+
+        // Once every worker is done, and their senders with them, the loop below ends
+        drop(sender);
+
+        for tile in receiver {
+            framebuffer.merge(&tile);
+        }
+    })
+    .expect("crossbeam::scope error");
+}
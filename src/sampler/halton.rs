@@ -0,0 +1,88 @@
+use crate::random::Random;
+use crate::sampler::{dimension_seed, pixel_seed, Sampler};
+
+// The Halton sequence: dimension d of sample i is i written in the d-th prime base, with its
+// digits mirrored around the decimal point. Each pixel shifts every dimension by its own random
+// offset (wrapping around 1), a Cranley-Patterson rotation, so neighbouring pixels don't share
+// the same points.
+//
+// Large bases have long stretches of nearly identical values, so dimensions past the table of
+// primes fall back to independent random numbers. Paths that bounce that many times don't
+// contribute much to the image anyway.
+pub struct Halton {
+    pixel_seed: u64,
+    sample: u64,
+    dimension: usize,
+    random: Random,
+}
+
+const PRIMES: [u64; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131,
+];
+
+impl Sampler for Halton {
+    fn for_sample(seed: u64, x: usize, y: usize, sample: usize, _samples: usize) -> Halton {
+        Halton {
+            pixel_seed: pixel_seed(seed, x, y),
+            sample: sample as u64,
+            dimension: 0,
+            random: Random::for_sample(seed, x, y, sample),
+        }
+    }
+
+    fn next_1d(&mut self) -> f64 {
+        let dimension = self.dimension;
+        self.dimension += 1;
+
+        match PRIMES.get(dimension) {
+            Some(&base) => {
+                let offset = dimension_seed(self.pixel_seed, dimension as u32) >> 11;
+                let offset = offset as f64 / (1u64 << 53) as f64;
+                let value = radical_inverse(self.sample, base) + offset;
+
+                if value >= 1.0 {
+                    value - 1.0
+                } else {
+                    value
+                }
+            }
+            None => self.random.next_f64(),
+        }
+    }
+
+    fn next_2d(&mut self) -> (f64, f64) {
+        (self.next_1d(), self.next_1d())
+    }
+}
+
+fn radical_inverse(mut index: u64, base: u64) -> f64 {
+    let inverse_base = 1.0 / base as f64;
+    let mut digit_scale = inverse_base;
+    let mut value = 0.0;
+
+    while index > 0 {
+        value += (index % base) as f64 * digit_scale;
+        index /= base;
+        digit_scale *= inverse_base;
+    }
+
+    value.min(1.0 - f64::EPSILON / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn radical_inverses() {
+        let base_2: Vec<_> = (0..5).map(|i| radical_inverse(i, 2)).collect();
+        assert_eq!(base_2, vec![0.0, 0.5, 0.25, 0.75, 0.125]);
+
+        assert_approx_eq!(radical_inverse(1, 3), 1.0 / 3.0);
+        assert_approx_eq!(radical_inverse(2, 3), 2.0 / 3.0);
+        assert_approx_eq!(radical_inverse(3, 3), 1.0 / 9.0);
+        assert_approx_eq!(radical_inverse(5, 3), 2.0 / 3.0 + 1.0 / 9.0);
+    }
+}
@@ -0,0 +1,132 @@
+use crate::random::{mix, Random};
+
+mod halton;
+mod sobol;
+mod stratified;
+
+pub use halton::Halton;
+pub use sobol::Sobol;
+pub use stratified::Stratified;
+
+// Where every random decision along a path gets its numbers from. A sampler hands out the
+// dimensions of one sample of one pixel in order: first the position in the pixel, then the
+// point on the lens, then the time while the shutter is open (if it stays open at all), then
+// whatever the materials ask for at every bounce. Samplers other than `Random` spread the values
+// of each dimension over all the samples of a pixel more evenly than independent random numbers
+// would, so the image converges with fewer samples.
+pub trait Sampler {
+    // `samples` is how many samples the pixel can get at most, which some samplers divide
+    // their patterns by
+    fn for_sample(seed: u64, x: usize, y: usize, sample: usize, samples: usize) -> Self
+    where
+        Self: Sized;
+
+    // A number in [0, 1)
+    fn next_1d(&mut self) -> f64;
+
+    // Two numbers in [0, 1), distributed well as a pair
+    fn next_2d(&mut self) -> (f64, f64);
+}
+
+// Plain independent random numbers
+impl Sampler for Random {
+    fn for_sample(seed: u64, x: usize, y: usize, sample: usize, _samples: usize) -> Random {
+        Random::for_sample(seed, x, y, sample)
+    }
+
+    fn next_1d(&mut self) -> f64 {
+        self.next_f64()
+    }
+
+    fn next_2d(&mut self) -> (f64, f64) {
+        (self.next_f64(), self.next_f64())
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SamplerKind {
+    Independent,
+    Stratified,
+    Halton,
+    Sobol,
+}
+
+impl SamplerKind {
+    pub const NAMES: &'static [&'static str] = &["independent", "stratified", "halton", "sobol"];
+
+    pub fn from_name(name: &str) -> Option<SamplerKind> {
+        match name.to_ascii_lowercase().as_str() {
+            "independent" => Some(SamplerKind::Independent),
+            "stratified" => Some(SamplerKind::Stratified),
+            "halton" => Some(SamplerKind::Halton),
+            "sobol" => Some(SamplerKind::Sobol),
+            _ => None,
+        }
+    }
+}
+
+// Every pixel gets its own, unrelated version of the pattern of a sampler, otherwise the same
+// error would repeat across the whole image
+fn pixel_seed(seed: u64, x: usize, y: usize) -> u64 {
+    mix(seed ^ mix((x as u64) << 32 | y as u64))
+}
+
+// A seed for each dimension of a pixel's pattern, so the dimensions don't line up with each other
+fn dimension_seed(pixel_seed: u64, dimension: u32) -> u64 {
+    mix(pixel_seed ^ mix(u64::from(dimension)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Takes `samples` 2D points from the first dimension of a pixel
+    fn points<S: Sampler>(samples: usize) -> Vec<(f64, f64)> {
+        (0..samples)
+            .map(|sample| S::for_sample(7, 3, 5, sample, samples).next_2d())
+            .collect()
+    }
+
+    // Whether every one of the `n` intervals along both axes has exactly one point
+    fn stratified(points: &[(f64, f64)]) -> bool {
+        let n = points.len();
+        let mut xs = vec![0; n];
+        let mut ys = vec![0; n];
+
+        for &(x, y) in points {
+            assert!((0.0..1.0).contains(&x) && (0.0..1.0).contains(&y));
+            xs[(x * n as f64) as usize] += 1;
+            ys[(y * n as f64) as usize] += 1;
+        }
+
+        xs.iter().chain(&ys).all(|&count| count == 1)
+    }
+
+    #[test]
+    fn samples_cover_every_stratum() {
+        assert!(stratified(&points::<Stratified>(16)));
+        assert!(stratified(&points::<Stratified>(12)));
+        assert!(stratified(&points::<Sobol>(64)));
+        assert!(!stratified(&points::<Random>(64)));
+    }
+
+    #[test]
+    fn every_dimension_stays_in_the_unit_interval() {
+        fn check<S: Sampler>() {
+            for sample in 0..32 {
+                let mut sampler = S::for_sample(1, 0, 0, sample, 32);
+
+                for _ in 0..200 {
+                    let (x, y) = sampler.next_2d();
+                    let z = sampler.next_1d();
+                    assert!([x, y, z].iter().all(|n| (0.0..1.0).contains(n)));
+                }
+            }
+        }
+
+        check::<Random>();
+        check::<Stratified>();
+        check::<Halton>();
+        check::<Sobol>();
+    }
+}
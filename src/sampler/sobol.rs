@@ -0,0 +1,115 @@
+use crate::sampler::{dimension_seed, pixel_seed, Sampler};
+
+// Owen-scrambled Sobol points, following Brent Burley's "Practical Hash-based Owen Scrambling":
+// https://jcgt.org/published/0009/04/01/
+//
+// Only the first two Sobol dimensions are used. Every request for one or two dimensions takes
+// them from its own copy of the sequence, scrambled and shuffled with a different seed, which
+// keeps the requests independent of each other while each of them is still as well spread as
+// the Sobol sequence. The first 2^k samples of any request are stratified in every 2^k interval.
+pub struct Sobol {
+    pixel_seed: u64,
+    sample: u32,
+    dimension: u32,
+}
+
+impl Sampler for Sobol {
+    fn for_sample(seed: u64, x: usize, y: usize, sample: usize, _samples: usize) -> Sobol {
+        Sobol {
+            pixel_seed: pixel_seed(seed, x, y),
+            sample: sample as u32,
+            dimension: 0,
+        }
+    }
+
+    fn next_1d(&mut self) -> f64 {
+        let (index, seed) = self.next_pattern();
+        to_unit(nested_uniform_scramble(
+            van_der_corput(index),
+            hash(seed, 1),
+        ))
+    }
+
+    fn next_2d(&mut self) -> (f64, f64) {
+        let (index, seed) = self.next_pattern();
+
+        (
+            to_unit(nested_uniform_scramble(
+                van_der_corput(index),
+                hash(seed, 1),
+            )),
+            to_unit(nested_uniform_scramble(sobol_second(index), hash(seed, 2))),
+        )
+    }
+}
+
+impl Sobol {
+    // The index of this sample in the next shuffled copy of the sequence, and that copy's seed
+    fn next_pattern(&mut self) -> (u32, u64) {
+        self.dimension += 1;
+        let seed = dimension_seed(self.pixel_seed, self.dimension);
+
+        (nested_uniform_scramble(self.sample, hash(seed, 0)), seed)
+    }
+}
+
+fn hash(seed: u64, n: u64) -> u32 {
+    (dimension_seed(seed, n as u32) >> 32) as u32
+}
+
+// The first Sobol dimension: the bits of the index mirrored
+fn van_der_corput(index: u32) -> u32 {
+    index.reverse_bits()
+}
+
+// The second one, generated by the polynomial x + 1. Its direction numbers are the rows of
+// Pascal's triangle mod 2, each one the previous xor-ed with itself shifted by one.
+fn sobol_second(mut index: u32) -> u32 {
+    let mut direction = 1u32 << 31;
+    let mut value = 0;
+
+    while index != 0 {
+        if index & 1 != 0 {
+            value ^= direction;
+        }
+
+        index >>= 1;
+        direction ^= direction >> 1;
+    }
+
+    value
+}
+
+// A hash that only lets each bit depend on the bits below it, which combined with reversing the
+// bits gives a nested uniform (Owen) scramble: a random permutation of every level of the binary
+// subdivision of the unit interval
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50_b47c);
+    x ^= x.wrapping_mul(0xb82f_1e52);
+    x ^= x.wrapping_mul(0xc7af_e638);
+    x ^= x.wrapping_mul(0x8d22_f6e6);
+    x
+}
+
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+fn to_unit(x: u32) -> f64 {
+    f64::from(x) / 4_294_967_296.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unscrambled_sequence() {
+        let first: Vec<_> = (0..4).map(van_der_corput).map(to_unit).collect();
+        let second: Vec<_> = (0..4).map(sobol_second).map(to_unit).collect();
+
+        assert_eq!(first, vec![0.0, 0.5, 0.25, 0.75]);
+        assert_eq!(second, vec![0.0, 0.5, 0.75, 0.25]);
+    }
+}
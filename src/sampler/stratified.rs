@@ -0,0 +1,114 @@
+use crate::random::Random;
+use crate::sampler::{dimension_seed, pixel_seed, Sampler};
+
+// Correlated multi-jittered sampling, from Andrew Kensler's "Correlated Multi-Jittered Sampling":
+// https://graphics.pixar.com/library/MultiJitteredSampling/
+//
+// The samples of a pixel split every dimension into as many strata as there are samples, with
+// one sample somewhere in each. Pairs of dimensions are also stratified on a grid, so no two
+// samples end up in the same row or column of it. Which sample gets which stratum is shuffled
+// differently for every dimension of every pixel.
+pub struct Stratified {
+    pixel_seed: u64,
+    sample: u32,
+    samples: u32,
+    dimension: u32,
+    random: Random,
+}
+
+impl Sampler for Stratified {
+    fn for_sample(seed: u64, x: usize, y: usize, sample: usize, samples: usize) -> Stratified {
+        Stratified {
+            pixel_seed: pixel_seed(seed, x, y),
+            sample: sample as u32,
+            samples: samples.max(sample + 1) as u32,
+            dimension: 0,
+            random: Random::for_sample(seed, x, y, sample),
+        }
+    }
+
+    fn next_1d(&mut self) -> f64 {
+        let seed = self.next_seed();
+        let stratum = permute(self.sample, self.samples, seed);
+
+        (f64::from(stratum) + self.random.next_f64()) / f64::from(self.samples)
+    }
+
+    fn next_2d(&mut self) -> (f64, f64) {
+        let seed = self.next_seed();
+        let n = self.samples;
+        // A grid of m columns and k rows with room for every sample
+        let m = (f64::from(n).sqrt() as u32).max(1);
+        let k = n.div_ceil(m);
+
+        let s = permute(self.sample, n, seed.wrapping_mul(0x5163_3e2d));
+        let column = permute(s % m, m, seed.wrapping_mul(0x68bc_21eb));
+        let row = permute(s / m, k, seed.wrapping_mul(0x02e5_be93));
+
+        let x = (f64::from(column) + (f64::from(row) + self.random.next_f64()) / f64::from(k))
+            / f64::from(m);
+        let y = (f64::from(s) + self.random.next_f64()) / f64::from(n);
+
+        (x, y)
+    }
+}
+
+impl Stratified {
+    fn next_seed(&mut self) -> u32 {
+        self.dimension += 1;
+        dimension_seed(self.pixel_seed, self.dimension) as u32
+    }
+}
+
+// A random permutation of [0, length) that is different for every `seed`, computed one element
+// at a time: a hash that is reversible within the next power of two, repeated until it falls
+// inside the range
+fn permute(index: u32, length: u32, seed: u32) -> u32 {
+    let mut mask = length - 1;
+    mask |= mask >> 1;
+    mask |= mask >> 2;
+    mask |= mask >> 4;
+    mask |= mask >> 8;
+    mask |= mask >> 16;
+
+    let mut i = index;
+
+    loop {
+        i ^= seed;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= seed >> 16;
+        i ^= (i & mask) >> 4;
+        i ^= seed >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= seed >> 23;
+        i ^= (i & mask) >> 1;
+        i = i.wrapping_mul(1 | seed >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & mask) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & mask) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & mask) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= mask;
+        i ^= i >> 5;
+
+        if i < length {
+            return ((u64::from(i) + u64::from(seed)) % u64::from(length)) as u32;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permutations_hit_every_index_once() {
+        for &length in &[1, 7, 16, 100] {
+            let mut seen: Vec<_> = (0..length).map(|i| permute(i, length, 1234)).collect();
+            seen.sort();
+            assert_eq!(seen, (0..length).collect::<Vec<_>>());
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use crate::image::Image;
+use crate::math::Vector3;
+use crate::raytracer::material::{Dielectric, Lambertian, Material, Metal};
+use crate::raytracer::texture::{Checker, Constant, Gradient, ImageTexture, Texture};
+use crate::raytracer::{Aabb, Camera, Hitable};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+mod model;
+mod obj;
+mod parser;
+mod ply;
+mod random;
+mod stl;
+
+pub use model::{is_model, load_model, ModelError, MODEL_FORMATS};
+pub use parser::{parse, ParseError};
+pub use random::random_scene;
+
+pub struct Scene {
+    pub camera: CameraSettings,
+    pub objects: Vec<Box<dyn Hitable + Sync>>,
+}
+
+// Everything needed to build a `Camera`. The aspect ratio is usually left to whoever renders the
+// scene, since it depends on the resolution of the output image.
+#[derive(Copy, Clone, Debug)]
+pub struct CameraSettings {
+    pub look_from: Vector3,
+    pub look_at: Vector3,
+    pub view_up: Vector3,
+    pub vertical_fov: f64,
+    pub aspect_ratio: Option<f64>,
+    pub aperture: f64,
+    pub focus_distance: Option<f64>,
+    // When the shutter opens and closes. Moving objects go from where they are at time 0 to
+    // where they are at time 1.
+    pub shutter: (f64, f64),
+}
+
+impl CameraSettings {
+    // Looks at every bounded object from the front (down the -z axis), close enough that the
+    // sphere around all of them just fits in the vertical field of view. Unbounded ones, like a
+    // floor, are left out, and without any bounded objects there is nothing to frame.
+    pub fn framing(objects: &[Box<dyn Hitable + Sync>]) -> Option<CameraSettings> {
+        let bounds = objects
+            .iter()
+            .filter_map(|object| object.bounding_box())
+            .fold(Aabb::empty(), |bounds, object| bounds.surrounding(&object));
+
+        if bounds.is_empty() {
+            return None;
+        }
+
+        let vertical_fov: f64 = 40.0;
+        let radius = (bounds.diagonal().magnitude() / 2.0).max(1e-3);
+        let distance = radius / (vertical_fov.to_radians() / 2.0).sin();
+
+        Some(CameraSettings {
+            look_from: bounds.center() + Vector3::new(0.0, 0.0, distance),
+            look_at: bounds.center(),
+            view_up: Vector3::new(0.0, 1.0, 0.0),
+            vertical_fov,
+            aspect_ratio: None,
+            aperture: 0.0,
+            focus_distance: None,
+            shutter: (0.0, 1.0),
+        })
+    }
+
+    pub fn build(&self, default_aspect_ratio: f64) -> Camera {
+        Camera::new(
+            self.look_from,
+            self.look_at,
+            self.view_up,
+            self.vertical_fov,
+            self.aspect_ratio.unwrap_or(default_aspect_ratio),
+            self.aperture,
+            self.focus_distance
+                .unwrap_or_else(|| (self.look_from - self.look_at).magnitude()),
+        )
+        .with_shutter(self.shutter.0, self.shutter.1)
+    }
+}
+
+// A material as written in a scene or model file, which can be built as many times as it's used
+#[derive(Clone, Debug, PartialEq)]
+pub enum MaterialDescription {
+    Lambertian {
+        albedo: TextureDescription,
+    },
+    Metal {
+        albedo: TextureDescription,
+        fuzz: TextureDescription,
+    },
+    Water,
+    Glass,
+    Diamond,
+    Dielectric {
+        refraction_index: f64,
+    },
+}
+
+impl MaterialDescription {
+    pub fn build(&self) -> Box<dyn Material + Sync> {
+        match self {
+            MaterialDescription::Lambertian { albedo } => {
+                Box::new(Lambertian::textured(albedo.build()))
+            }
+            MaterialDescription::Metal { albedo, fuzz } => {
+                Box::new(Metal::textured(albedo.build(), fuzz.build()))
+            }
+            MaterialDescription::Water => Box::new(Dielectric::water()),
+            MaterialDescription::Glass => Box::new(Dielectric::glass()),
+            MaterialDescription::Diamond => Box::new(Dielectric::diamond()),
+            MaterialDescription::Dielectric { refraction_index } => {
+                Box::new(Dielectric::new(*refraction_index))
+            }
+        }
+    }
+}
+
+// A texture as written in a scene or model file. Images are loaded once and shared by every
+// texture built from them.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TextureDescription {
+    Constant(Vector3),
+    Checker {
+        even: Box<TextureDescription>,
+        odd: Box<TextureDescription>,
+        scale: f64,
+    },
+    Gradient {
+        start: Vector3,
+        end: Vector3,
+        start_color: Vector3,
+        end_color: Vector3,
+    },
+    Image {
+        image: Arc<Image>,
+        scale: f64,
+    },
+}
+
+impl TextureDescription {
+    // The same shade of grey everywhere, for parameters that are a single number
+    pub fn grey(value: f64) -> TextureDescription {
+        TextureDescription::Constant(Vector3::new(value, value, value))
+    }
+
+    pub fn build(&self) -> Box<dyn Texture> {
+        match self {
+            TextureDescription::Constant(value) => Box::new(Constant::new(*value)),
+            TextureDescription::Checker { even, odd, scale } => {
+                Box::new(Checker::new(even.build(), odd.build(), *scale))
+            }
+            TextureDescription::Gradient {
+                start,
+                end,
+                start_color,
+                end_color,
+            } => Box::new(Gradient::new(*start, *end, *start_color, *end_color)),
+            TextureDescription::Image { image, scale } => {
+                Box::new(ImageTexture::new(image.clone(), *scale))
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, ParseError),
+    Model(ModelError),
+    // Models on their own are framed by the camera, which needs them to have a size
+    Empty(PathBuf),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io(path, error) => write!(f, "{}: {}", path.display(), error),
+            SceneError::Parse(path, error) => write!(f, "{}:{}", path.display(), error),
+            SceneError::Model(error) => write!(f, "{}", error),
+            SceneError::Empty(path) => write!(f, "{}: there is nothing to render", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+// Scene files, or models on their own, looked at from the front
+pub fn load(path: &Path) -> Result<Scene, SceneError> {
+    if is_model(path) {
+        let objects = load_model(path, None).map_err(SceneError::Model)?;
+
+        return match CameraSettings::framing(&objects) {
+            Some(camera) => Ok(Scene { camera, objects }),
+            None => Err(SceneError::Empty(path.to_path_buf())),
+        };
+    }
+
+    let source =
+        std::fs::read_to_string(path).map_err(|error| SceneError::Io(path.to_path_buf(), error))?;
+
+    // Files referenced by the scene are found next to it
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+
+    parse(&source, directory).map_err(|error| SceneError::Parse(path.to_path_buf(), error))
+}
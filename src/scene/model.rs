@@ -0,0 +1,83 @@
+// Meshes loaded from model files, in any of the formats in `MODEL_FORMATS`, told apart by their
+// extension
+use crate::image::ImageError;
+use crate::raytracer::{Hitable, Mesh, TriangleMesh};
+use crate::scene::{obj, ply, stl, MaterialDescription, TextureDescription};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+pub const MODEL_FORMATS: &[&str] = &["obj", "ply", "stl"];
+
+#[derive(Debug)]
+pub enum ModelError {
+    Io(PathBuf, std::io::Error),
+    // The file, the line and what's wrong with it
+    Syntax(PathBuf, usize, String),
+    // For files without lines to point to, like binary ones
+    Invalid(PathBuf, String),
+    // A texture of one of its materials
+    Image(ImageError),
+}
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ModelError::Io(path, error) => write!(f, "{}: {}", path.display(), error),
+            ModelError::Syntax(path, line, message) => {
+                write!(f, "{}:{}: {}", path.display(), line, message)
+            }
+            ModelError::Invalid(path, message) => write!(f, "{}: {}", path.display(), message),
+            ModelError::Image(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for ModelError {}
+
+pub fn is_model(path: &Path) -> bool {
+    format(path).is_some()
+}
+
+fn format(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    MODEL_FORMATS
+        .iter()
+        .find(|&&format| format == extension)
+        .copied()
+}
+
+// The meshes of a model, in their own materials or all in `material`. Models without materials
+// of their own are a light grey, or white if their vertices have colors to tint it with.
+pub fn load_model(
+    path: &Path,
+    material: Option<MaterialDescription>,
+) -> Result<Vec<Box<dyn Hitable + Sync>>, ModelError> {
+    let invalid = |message: String| ModelError::Invalid(path.to_path_buf(), message);
+    let bytes = || std::fs::read(path).map_err(|error| ModelError::Io(path.to_path_buf(), error));
+
+    let mesh = match format(path) {
+        Some("obj") => return obj::load_obj(path, material),
+        Some("ply") => ply::parse_ply(&bytes()?).map_err(invalid)?,
+        Some("stl") => stl::parse_stl(&bytes()?).map_err(invalid)?,
+        _ => {
+            return Err(invalid(format!(
+                "unknown model format, expected one of: {}",
+                MODEL_FORMATS.join(", ")
+            )))
+        }
+    };
+
+    let material = material.unwrap_or_else(|| default_material(&mesh));
+    let mesh =
+        TriangleMesh::new(mesh, material.build()).map_err(|error| invalid(error.to_string()))?;
+
+    Ok(vec![Box::new(mesh)])
+}
+
+pub fn default_material(mesh: &Mesh) -> MaterialDescription {
+    let albedo = if mesh.colors.is_empty() { 0.8 } else { 1.0 };
+
+    MaterialDescription::Lambertian {
+        albedo: TextureDescription::grey(albedo),
+    }
+}
@@ -0,0 +1,570 @@
+// Wavefront OBJ meshes, with their MTL materials.
+//
+// Vertices (`v`), texture coordinates (`vt`) and normals (`vn`) are read into their own lists,
+// and faces (`f`) pick one of each for every corner, counting from 1, or back from the latest one
+// with negative indices. Faces with more than three corners are split into a fan of triangles,
+// which works for the convex polygons exporters write. Every object (`o`), group (`g`) and change
+// of material (`usemtl`) starts a new mesh.
+//
+// MTL materials only map loosely onto the ones we have: transparent materials (`d` below 1, or
+// one of the refracting `illum` models) become dielectrics with their `Ni` refraction index,
+// materials whose specular color (`Ks`) is brighter than their diffuse color (`Kd`) become metals
+// as rough as their specular exponent (`Ns`) says, and everything else is lambertian. A diffuse
+// texture map (`map_Kd`), found next to the MTL file, takes the place of the diffuse color.
+use crate::image::{self, ImageError, Transfer};
+use crate::math::{luminance, Vector3};
+use crate::raytracer::{Hitable, Mesh, TriangleMesh};
+use crate::scene::model::{default_material, ModelError};
+use crate::scene::{MaterialDescription, TextureDescription};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+// The line and the message of a syntax error, before it's known which file it was in
+type LineError = (usize, String);
+
+// The meshes of an OBJ file, each with the materials of its MTL files, or `material` for all of
+// them instead
+pub fn load_obj(
+    path: &Path,
+    material: Option<MaterialDescription>,
+) -> Result<Vec<Box<dyn Hitable + Sync>>, ModelError> {
+    let read = |path: &Path| {
+        std::fs::read_to_string(path).map_err(|error| ModelError::Io(path.to_path_buf(), error))
+    };
+    let syntax = |path: &Path| {
+        let path = path.to_path_buf();
+        move |(line, message)| ModelError::Syntax(path, line, message)
+    };
+
+    let obj = parse_obj(&read(path)?).map_err(syntax(path))?;
+
+    // Libraries are found next to the OBJ file, and their textures next to them
+    let mut materials = HashMap::new();
+
+    if material.is_none() {
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+
+        for library in &obj.libraries {
+            let library = directory.join(library);
+            let textures = library.parent().unwrap_or_else(|| Path::new(""));
+
+            for (name, mtl) in parse_mtl(&read(&library)?).map_err(syntax(&library))? {
+                let description = mtl.describe(textures).map_err(ModelError::Image)?;
+                materials.insert(name, description);
+            }
+        }
+    }
+
+    let mut objects: Vec<Box<dyn Hitable + Sync>> = Vec::with_capacity(obj.meshes.len());
+
+    for (name, mesh) in obj.meshes {
+        let description = match (&material, name) {
+            (Some(material), _) => material.clone(),
+            (None, None) => default_material(&mesh),
+            (None, Some((name, line))) => match materials.get(&name) {
+                Some(material) => material.clone(),
+                None => {
+                    return Err(ModelError::Syntax(
+                        path.to_path_buf(),
+                        line,
+                        format!(
+                            "material `{}` is not in any of the material libraries",
+                            name
+                        ),
+                    ))
+                }
+            },
+        };
+
+        let mesh = TriangleMesh::new(mesh, description.build())
+            .expect("faces are checked against the vertices while parsing");
+        objects.push(Box::new(mesh));
+    }
+
+    Ok(objects)
+}
+
+struct Obj {
+    libraries: Vec<String>,
+    // Every mesh, with the name of its material and the line that set it
+    meshes: Vec<(Option<(String, usize)>, Mesh)>,
+}
+
+fn parse_obj(source: &str) -> Result<Obj, LineError> {
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut normals = Vec::new();
+
+    let mut obj = Obj {
+        libraries: Vec::new(),
+        meshes: Vec::new(),
+    };
+    let mut material = None;
+    let mut mesh = MeshBuilder::default();
+
+    for (index, line) in source.lines().enumerate() {
+        let number = index + 1;
+        let line = match line.find('#') {
+            Some(comment) => &line[..comment],
+            None => line,
+        };
+        let mut words = line.split_whitespace();
+        let statement = match words.next() {
+            Some(statement) => statement,
+            None => continue,
+        };
+        let arguments: Vec<_> = words.collect();
+
+        match statement {
+            "v" => {
+                // Some exporters add a color after the position, which is ignored
+                let v = numbers(statement, &arguments, 3, 7).map_err(|e| (number, e))?;
+                positions.push(Vector3::new(v[0], v[1], v[2]));
+            }
+            "vt" => {
+                let vt = numbers(statement, &arguments, 1, 3).map_err(|e| (number, e))?;
+                uvs.push((vt[0], vt.get(1).copied().unwrap_or(0.0)));
+            }
+            "vn" => {
+                let n = numbers(statement, &arguments, 3, 3).map_err(|e| (number, e))?;
+                normals.push(Vector3::new(n[0], n[1], n[2]));
+            }
+            "f" => {
+                if arguments.len() < 3 {
+                    return Err((number, String::from("faces need at least 3 corners")));
+                }
+
+                let corners = arguments
+                    .iter()
+                    .map(|corner| {
+                        let corner = parse_corner(corner, &positions, &uvs, &normals)
+                            .map_err(|e| (number, e))?;
+                        Ok(mesh.vertex(corner, &positions, &uvs, &normals))
+                    })
+                    .collect::<Result<Vec<_>, LineError>>()?;
+
+                for i in 1..corners.len() - 1 {
+                    mesh.mesh
+                        .triangles
+                        .push([corners[0], corners[i], corners[i + 1]]);
+                }
+            }
+            "o" | "g" => obj.meshes.extend(mesh.finish(&material)),
+            "usemtl" => {
+                obj.meshes.extend(mesh.finish(&material));
+
+                if arguments.len() != 1 {
+                    return Err((number, String::from("`usemtl` expects a material name")));
+                }
+
+                material = Some((arguments[0].to_string(), number));
+            }
+            "mtllib" => {
+                if arguments.is_empty() {
+                    return Err((number, String::from("`mtllib` expects file names")));
+                }
+
+                obj.libraries
+                    .extend(arguments.iter().map(|library| library.to_string()));
+            }
+            // Smoothing groups, lines, points, free-form surfaces and so on
+            _ => {}
+        }
+    }
+
+    obj.meshes.extend(mesh.finish(&material));
+
+    Ok(obj)
+}
+
+// Between `min` and `max` numbers
+fn numbers(
+    statement: &str,
+    arguments: &[&str],
+    min: usize,
+    max: usize,
+) -> Result<Vec<f64>, String> {
+    if arguments.len() < min || arguments.len() > max {
+        return Err(if min == max {
+            format!("`{}` expects {} numbers", statement, min)
+        } else {
+            format!("`{}` expects {} to {} numbers", statement, min, max)
+        });
+    }
+
+    arguments
+        .iter()
+        .map(|argument| {
+            argument
+                .parse()
+                .map_err(|_| format!("invalid number `{}`", argument))
+        })
+        .collect()
+}
+
+// The position, uv and normal of a face corner, as in `1`, `1/2`, `1//3` or `1/2/3`
+type Corner = (usize, Option<usize>, Option<usize>);
+
+fn parse_corner(
+    corner: &str,
+    positions: &[Vector3],
+    uvs: &[(f64, f64)],
+    normals: &[Vector3],
+) -> Result<Corner, String> {
+    let mut parts = corner.split('/');
+    let position = resolve(parts.next().unwrap_or(""), positions.len(), "vertex")?;
+
+    let uv = match parts.next() {
+        None | Some("") => None,
+        Some(index) => Some(resolve(index, uvs.len(), "texture coordinate")?),
+    };
+
+    let normal = match parts.next() {
+        None | Some("") => None,
+        Some(index) => Some(resolve(index, normals.len(), "normal")?),
+    };
+
+    if parts.next().is_some() {
+        return Err(format!("invalid face corner `{}`", corner));
+    }
+
+    Ok((position, uv, normal))
+}
+
+// OBJ indices start at 1, and negative ones count back from the last one so far
+fn resolve(index: &str, count: usize, what: &str) -> Result<usize, String> {
+    let parsed: i64 = index
+        .parse()
+        .map_err(|_| format!("invalid {} index `{}`", what, index))?;
+
+    let resolved = match parsed {
+        0 => None,
+        i if i > 0 => Some(i - 1),
+        i => Some(count as i64 + i),
+    };
+
+    match resolved {
+        Some(resolved) if resolved >= 0 && (resolved as usize) < count => Ok(resolved as usize),
+        _ => Err(format!(
+            "{} {} does not exist, there are {} so far",
+            what, parsed, count
+        )),
+    }
+}
+
+// Meshes need every vertex to have its own position, uv and normal, where OBJ files pick them
+// separately for every corner, so every distinct combination becomes a vertex
+#[derive(Default)]
+struct MeshBuilder {
+    mesh: Mesh,
+    vertices: HashMap<Corner, u32>,
+    uvs: Vec<Option<(f64, f64)>>,
+    normals: Vec<Option<Vector3>>,
+}
+
+impl MeshBuilder {
+    fn vertex(
+        &mut self,
+        corner: Corner,
+        positions: &[Vector3],
+        uvs: &[(f64, f64)],
+        normals: &[Vector3],
+    ) -> u32 {
+        let mesh = &mut self.mesh;
+        let (new_uvs, new_normals) = (&mut self.uvs, &mut self.normals);
+
+        *self.vertices.entry(corner).or_insert_with(|| {
+            let (position, uv, normal) = corner;
+            mesh.positions.push(positions[position]);
+            new_uvs.push(uv.map(|uv| uvs[uv]));
+            new_normals.push(normal.map(|normal| normals[normal]));
+            (mesh.positions.len() - 1) as u32
+        })
+    }
+
+    // The mesh so far, if it has any triangles, and a fresh start for the next one. Corners
+    // without a uv get (0, 0), but without a normal for every corner the mesh is left flat.
+    fn finish(
+        &mut self,
+        material: &Option<(String, usize)>,
+    ) -> Option<(Option<(String, usize)>, Mesh)> {
+        let builder = std::mem::take(self);
+        let mut mesh = builder.mesh;
+
+        if mesh.triangles.is_empty() {
+            return None;
+        }
+
+        if builder.uvs.iter().any(Option::is_some) {
+            mesh.uvs = builder
+                .uvs
+                .iter()
+                .map(|uv| uv.unwrap_or((0.0, 0.0)))
+                .collect();
+        }
+
+        if builder.normals.iter().all(Option::is_some) {
+            mesh.normals = builder.normals.into_iter().flatten().collect();
+        }
+
+        Some((material.clone(), mesh))
+    }
+}
+
+struct MtlMaterial {
+    diffuse: Vector3,
+    diffuse_map: Option<String>,
+    specular: Vector3,
+    exponent: f64,
+    refraction_index: f64,
+    dissolve: f64,
+    illumination: u32,
+}
+
+impl Default for MtlMaterial {
+    fn default() -> MtlMaterial {
+        MtlMaterial {
+            diffuse: Vector3::new(0.8, 0.8, 0.8),
+            diffuse_map: None,
+            specular: Vector3::new(0.0, 0.0, 0.0),
+            exponent: 0.0,
+            refraction_index: 1.0,
+            dissolve: 1.0,
+            illumination: 2,
+        }
+    }
+}
+
+impl MtlMaterial {
+    // Texture maps are looked for in `directory`
+    fn describe(&self, directory: &Path) -> Result<MaterialDescription, ImageError> {
+        // The illumination models with refraction
+        if self.dissolve < 1.0 || [4, 6, 7, 9].contains(&self.illumination) {
+            // Plenty of files leave `Ni` at 1, which would make the material invisible
+            let refraction_index = if self.refraction_index > 1.0 {
+                self.refraction_index
+            } else {
+                1.5
+            };
+
+            return Ok(MaterialDescription::Dielectric { refraction_index });
+        }
+
+        if luminance(self.specular) > luminance(self.diffuse) {
+            // Phong exponents go from very rough at 0 to a perfect mirror as they grow
+            return Ok(MaterialDescription::Metal {
+                albedo: TextureDescription::Constant(self.specular),
+                fuzz: TextureDescription::grey((2.0 / (self.exponent + 2.0)).sqrt()),
+            });
+        }
+
+        let albedo = match &self.diffuse_map {
+            Some(file) => TextureDescription::Image {
+                image: Arc::new(image::read(&directory.join(file))?.decode(Transfer::Srgb)),
+                scale: 1.0,
+            },
+            None => TextureDescription::Constant(self.diffuse),
+        };
+
+        Ok(MaterialDescription::Lambertian { albedo })
+    }
+}
+
+fn parse_mtl(source: &str) -> Result<HashMap<String, MtlMaterial>, LineError> {
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlMaterial)> = None;
+
+    for (index, line) in source.lines().enumerate() {
+        let number = index + 1;
+        let line = match line.find('#') {
+            Some(comment) => &line[..comment],
+            None => line,
+        };
+        let mut words = line.split_whitespace();
+        let statement = match words.next() {
+            Some(statement) => statement,
+            None => continue,
+        };
+        let arguments: Vec<_> = words.collect();
+
+        if statement == "newmtl" {
+            if arguments.len() != 1 {
+                return Err((number, String::from("`newmtl` expects a material name")));
+            }
+
+            materials.extend(current.take());
+            current = Some((arguments[0].to_string(), MtlMaterial::default()));
+            continue;
+        }
+
+        if !["Kd", "Ks", "Ns", "Ni", "d", "Tr", "illum", "map_Kd"].contains(&statement) {
+            // Ambient and emissive colors, other texture maps and so on
+            continue;
+        }
+
+        let material = match &mut current {
+            Some((_, material)) => material,
+            None => return Err((number, format!("`{}` comes before any `newmtl`", statement))),
+        };
+
+        let color = || -> Result<Vector3, LineError> {
+            let c = numbers(statement, &arguments, 1, 3).map_err(|e| (number, e))?;
+
+            // A single number is a shade of grey
+            Ok(match *c.as_slice() {
+                [grey] => Vector3::new(grey, grey, grey),
+                [r, g, b] => Vector3::new(r, g, b),
+                _ => return Err((number, format!("`{}` expects 1 or 3 numbers", statement))),
+            })
+        };
+        let number_value = || -> Result<f64, LineError> {
+            Ok(numbers(statement, &arguments, 1, 1).map_err(|e| (number, e))?[0])
+        };
+
+        match statement {
+            "Kd" => material.diffuse = color()?,
+            "Ks" => material.specular = color()?,
+            "Ns" => material.exponent = number_value()?.max(0.0),
+            "Ni" => material.refraction_index = number_value()?,
+            "d" => material.dissolve = number_value()?,
+            // Options like `-s 2 2 1` come before the file name, and are ignored
+            "map_Kd" => match arguments.last() {
+                Some(file) => material.diffuse_map = Some(file.to_string()),
+                None => return Err((number, String::from("`map_Kd` expects a file name"))),
+            },
+            "Tr" => material.dissolve = 1.0 - number_value()?,
+            _ => {
+                let illumination = number_value()?;
+
+                if illumination < 0.0 || illumination.fract() != 0.0 {
+                    return Err((number, String::from("`illum` expects a whole number")));
+                }
+
+                material.illumination = illumination as u32;
+            }
+        }
+    }
+
+    materials.extend(current);
+
+    Ok(materials)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn triangulates_faces_and_shares_vertices() {
+        let obj = parse_obj(
+            "# A square, then a triangle with its own normals and a material\n\
+             mtllib shapes.mtl\n\
+             v 0 0 0\n\
+             v 1 0 0\n\
+             v 1 1 0\n\
+             v 0 1 0\n\
+             vt 0 0\n\
+             vt 1 0\n\
+             vn 0 0 1\n\
+             f 1 2 3 4\n\
+             o triangle\n\
+             usemtl red\n\
+             f -4/1/1 -3/2/1 -2//1\n",
+        )
+        .unwrap();
+
+        assert_eq!(obj.libraries, vec!["shapes.mtl"]);
+        assert_eq!(obj.meshes.len(), 2);
+
+        let (material, square) = &obj.meshes[0];
+        assert!(material.is_none());
+        assert_eq!(square.positions.len(), 4);
+        assert_eq!(square.triangles, vec![[0, 1, 2], [0, 2, 3]]);
+        assert!(square.uvs.is_empty() && square.normals.is_empty());
+
+        let (material, triangle) = &obj.meshes[1];
+        assert_eq!(material, &Some((String::from("red"), 12)));
+        assert_eq!(triangle.positions[0], Vector3::new(0.0, 0.0, 0.0));
+        assert_eq!(triangle.uvs, vec![(0.0, 0.0), (1.0, 0.0), (0.0, 0.0)]);
+        assert_eq!(triangle.normals.len(), 3);
+    }
+
+    #[test]
+    fn reports_malformed_lines() {
+        let error = |source| parse_obj(source).err().unwrap();
+
+        assert_eq!(
+            error("v 0 0 0\nv 1 0\n"),
+            (2, String::from("`v` expects 3 to 7 numbers"))
+        );
+        assert_eq!(
+            error("v 0 0 0\nv 1 0 0\nf 1 2 3\n"),
+            (
+                3,
+                String::from("vertex 3 does not exist, there are 2 so far")
+            )
+        );
+        assert_eq!(
+            error("v 0 0 0\nf 1 1\n"),
+            (2, String::from("faces need at least 3 corners"))
+        );
+        assert_eq!(
+            error("v 0 0 0\nf 1/x 1 1\n"),
+            (2, String::from("invalid texture coordinate index `x`"))
+        );
+    }
+
+    #[test]
+    fn maps_mtl_materials_onto_ours() {
+        let materials = parse_mtl(
+            "newmtl matte\n\
+             Kd 0.5 0.1 0.1\n\
+             newmtl chrome\n\
+             Kd 0.1 0.1 0.1\n\
+             Ks 0.9\n\
+             Ns 1000\n\
+             newmtl glass\n\
+             d 0.2\n\
+             Ni 1.45\n\
+             newmtl wood\n\
+             map_Kd -s 2 2 1 textures/wood.png\n",
+        )
+        .unwrap();
+        let describe = |name: &str| materials[name].describe(Path::new("")).unwrap();
+
+        assert_eq!(
+            describe("matte"),
+            MaterialDescription::Lambertian {
+                albedo: TextureDescription::Constant(Vector3::new(0.5, 0.1, 0.1))
+            }
+        );
+        match describe("chrome") {
+            MaterialDescription::Metal {
+                albedo: TextureDescription::Constant(albedo),
+                fuzz: TextureDescription::Constant(fuzz),
+            } => {
+                assert_eq!(albedo, Vector3::new(0.9, 0.9, 0.9));
+                assert!(fuzz.x < 0.05);
+            }
+            other => panic!("expected a metal, got {:?}", other),
+        }
+        assert_eq!(
+            describe("glass"),
+            MaterialDescription::Dielectric {
+                refraction_index: 1.45
+            }
+        );
+
+        assert_eq!(
+            materials["wood"].diffuse_map.as_deref(),
+            Some("textures/wood.png")
+        );
+        assert!(materials["wood"].describe(Path::new("")).is_err());
+
+        assert_eq!(
+            parse_mtl("Kd 1 1 1\n").err().unwrap(),
+            (1, String::from("`Kd` comes before any `newmtl`"))
+        );
+    }
+}
@@ -0,0 +1,2067 @@
+// A small, whitespace-insensitive format to describe scenes. A scene file looks like this:
+//
+//     # Comments run until the end of the line
+//     camera {
+//         look_from 11 1.8 3.5
+//         look_at -1 0.5 0
+//         vertical_fov 20
+//     }
+//
+//     material ground lambertian { albedo 0.5 0.5 0.5 }
+//     material mirror metal { albedo 0.7 0.6 0.5 fuzz 0 }
+//
+//     sphere { center 0 -1000 0 radius 1000 material ground }
+//     sphere { center 0 1 0 radius 1 material dielectric glass }
+//
+// Materials can either be declared once with a name and referenced from objects, or written
+// inline in place of the name. `dielectric` takes either one of its presets (`water`, `glass`,
+// `diamond`) or a `{ refraction_index <n> }` block. Scenes without a camera are looked at from the
+// front, from far enough to see all of them.
+//
+// Besides spheres, shapes can be infinite planes, flat quads (a corner and two edges from it, at
+// any angle), axis-aligned boxes and disks:
+//
+//     plane { point 0 0 0 normal 0 1 0 material ground }
+//     quad { corner -1 0 -1 u 2 0 0 v 0 2 0 material mirror }
+//     box { min -1 0 -1 max 1 2 1 material ground }
+//     disk { center 0 3 0 normal 0 -1 0 radius 0.5 material mirror }
+//
+// Round shapes are built around an axis, which points up unless `axis` says otherwise.
+// Cylinders, cones (narrowing to a tip at `height`) and paraboloids (bowls that are `radius`
+// wide at `height`) go up from their `base`. They can be cut down to a `z_range` along the axis
+// and to `phi_max` degrees around it, and their ends closed with `capped`. Tori go around their
+// `center`, and capsules are rods with round ends, reaching `height` from their `base`:
+//
+//     cylinder { base 0 0 0 radius 0.5 height 2 phi_max 270 capped material ground }
+//     cone { base 2 0 0 axis 1 1 0 radius 1 height 2 z_range 0 1.5 material mirror }
+//     paraboloid { base -2 0 0 radius 1 height 1 material ground }
+//     torus { center 0 3 0 major_radius 1 minor_radius 0.25 material mirror }
+//     capsule { base 0 0 3 axis 1 0 0 radius 0.3 height 2 material ground }
+//
+// Solid shapes can be combined with `union`, `intersection` and `difference`, which take two or
+// more shapes and combine them in order. Spheres, boxes, tori, capsules, capped cylinders, cones
+// and paraboloids that go the whole way around are solid, and so are their combinations and
+// instances of objects made of them. Every surface keeps its own material:
+//
+//     # A lens, where two glass spheres overlap
+//     intersection {
+//         sphere { center 0 1 -2 radius 2.5 material dielectric glass }
+//         sphere { center 0 1 2 radius 2.5 material dielectric glass }
+//     }
+//
+// Meshes list the positions of their vertices, three numbers each, and then their triangles,
+// as three indices into those vertices (counting from 0) each. They can also have `normals`,
+// `uvs` (two numbers each) and linear `colors` for every vertex:
+//
+//     mesh {
+//         vertices 0 0 0  1 0 0  1 1 0  0 1 0
+//         triangles 0 1 2  0 2 3
+//         material ground
+//     }
+//
+// Models are loaded from Wavefront OBJ (see `obj.rs`), PLY or STL files, found next to the scene
+// file. Their own materials can be replaced with a single one:
+//
+//     model { file "models/teapot.obj" material mirror }
+//
+// Groups of objects can be declared once with a name and then placed as many times as needed,
+// each instance sharing the same geometry. Instances are scaled (by one factor or one per axis),
+// then rotated, then translated. Rotations are either an axis and some degrees, or three angles
+// in degrees around x, then y, then z. Instead of all that, `matrix` takes the top three rows of
+// any affine transform, 12 numbers in total:
+//
+//     object pair {
+//         sphere { center -1 0 0 radius 1 material ground }
+//         sphere { center 1 0 0 radius 1 material mirror }
+//     }
+//
+//     instance { object pair scale 0.5 rotate 0 1 0 45 translate 0 1 -2 }
+//     instance { object pair rotate 90 0 30 }
+//     instance { object pair matrix 1 0 0 0  0 1 0 2  0 0 1 0 }
+//
+// Things can move, for motion blur: spheres from their `center` to an `end_center`, and
+// instances to wherever `end_scale`, `end_rotate` and `end_translate` put them. They start
+// moving at time 0 and get there at time 1, while the camera's `shutter` is open for the whole
+// of that unless it's given a shorter time to stay open:
+//
+//     camera { look_from 0 1 5 look_at 0 1 0 vertical_fov 40 shutter 0 0.5 }
+//     sphere { center -1 1 0 end_center 1 1 0 radius 0.5 material mirror }
+//     instance { object pair translate 0 1 -2 end_rotate 0 1 0 90 }
+//
+// The `albedo` of lambertian and metal materials and the `fuzz` of metals can be textures
+// instead of a color or a number. Textures are declared with a name, or written inline, like
+// materials. Checkers take turns between two other textures, `scale` squares for every unit of
+// uv. Gradients blend between two colors from a `start` point in the scene to an `end` point.
+// Images are PNG or PPM files found next to the scene file, stretched over the uv square and
+// repeated `scale` times for every unit; their colors are taken to be sRGB, unless they're
+// marked `linear` for things other than colors, like fuzz:
+//
+//     texture tiles checker { even 0.9 0.9 0.9 odd 0.2 0.3 0.1 scale 2 }
+//     texture sky gradient { start 0 0 0 end 0 2 0 start_color 1 1 1 end_color 0.5 0.7 1 }
+//     material ground lambertian { albedo tiles }
+//     material brushed metal { albedo 0.8 0.8 0.8 fuzz image { file "scratches.png" linear } }
+use crate::image::{self, Transfer};
+use crate::math::{Matrix4, Pose, Quaternion, Transform, Vector3};
+use crate::raytracer::material::Material;
+use crate::raytracer::{
+    Bvh, Capsule, Cone, Csg, Cuboid, Cylinder, Disk, Hitable, Instance, Mesh, Operation,
+    Paraboloid, Plane, Quad, Sphere, Torus, TriangleMesh,
+};
+use crate::scene::{
+    is_model, load_model, CameraSettings, MaterialDescription, Scene, TextureDescription,
+    MODEL_FORMATS,
+};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    fn new(position: Position, message: String) -> ParseError {
+        ParseError {
+            line: position.line,
+            column: position.column,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// Files the scene refers to are looked for in `directory`
+pub fn parse(source: &str, directory: &Path) -> Result<Scene, ParseError> {
+    Parser::new(tokenize(source)?, directory).parse_scene()
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Position {
+    line: usize,
+    column: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Number(f64),
+    Text(String),
+    OpenBrace,
+    CloseBrace,
+    End,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "`{}`", word),
+            Token::Number(number) => write!(f, "number `{}`", number),
+            Token::Text(text) => write!(f, "text \"{}\"", text),
+            Token::OpenBrace => write!(f, "`{{`"),
+            Token::CloseBrace => write!(f, "`}}`"),
+            Token::End => write!(f, "end of file"),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<(Token, Position)>, ParseError> {
+    let mut tokens = Vec::new();
+
+    for (line_index, line) in source.lines().enumerate() {
+        let mut chars = line.char_indices().peekable();
+
+        while let Some((start, c)) = chars.next() {
+            let position = Position {
+                line: line_index + 1,
+                column: start + 1,
+            };
+
+            match c {
+                c if c.is_whitespace() => continue,
+                // Comments run to the end of the line, unless they're inside quotes
+                '#' => break,
+                '{' => tokens.push((Token::OpenBrace, position)),
+                '}' => tokens.push((Token::CloseBrace, position)),
+                // Text goes between double quotes, and can't span lines
+                '"' => {
+                    let text = chars
+                        .by_ref()
+                        .map(|(_, c)| c)
+                        .take_while(|&c| c != '"')
+                        .collect::<String>();
+
+                    if !line[start + 1..].contains('"') {
+                        return Err(ParseError::new(
+                            position,
+                            String::from("text is missing its closing `\"`"),
+                        ));
+                    }
+
+                    tokens.push((Token::Text(text), position));
+                }
+                _ => {
+                    let mut end = start + c.len_utf8();
+
+                    while let Some(&(index, next)) = chars.peek() {
+                        if next.is_whitespace() || next == '{' || next == '}' || next == '#' {
+                            break;
+                        }
+
+                        end = index + next.len_utf8();
+                        chars.next();
+                    }
+
+                    let text = &line[start..end];
+                    let token = if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' {
+                        Token::Number(text.parse().map_err(|_| {
+                            ParseError::new(position, format!("invalid number `{}`", text))
+                        })?)
+                    } else if c.is_alphabetic() || c == '_' {
+                        Token::Word(text.to_string())
+                    } else {
+                        return Err(ParseError::new(
+                            position,
+                            format!("unexpected character `{}`", c),
+                        ));
+                    };
+
+                    tokens.push((token, position));
+                }
+            }
+        }
+    }
+
+    let end = Position {
+        line: source.lines().count() + 1,
+        column: 1,
+    };
+    tokens.push((Token::End, end));
+
+    Ok(tokens)
+}
+
+const MATERIAL_KINDS: &[&str] = &["lambertian", "metal", "dielectric"];
+const TEXTURE_KINDS: &[&str] = &["checker", "gradient", "image"];
+// The properties that take a texture, which can be written inline
+const TEXTURED_PROPERTIES: &[&str] = &["albedo", "fuzz", "even", "odd"];
+const SHAPES: &[&str] = &[
+    "sphere",
+    "plane",
+    "quad",
+    "box",
+    "disk",
+    "cylinder",
+    "cone",
+    "paraboloid",
+    "torus",
+    "capsule",
+    "union",
+    "intersection",
+    "difference",
+    "mesh",
+    "model",
+    "instance",
+];
+
+struct Parser {
+    tokens: Vec<(Token, Position)>,
+    index: usize,
+    materials: HashMap<String, MaterialDescription>,
+    textures: HashMap<String, TextureDescription>,
+    objects: HashMap<String, Arc<dyn Hitable>>,
+    directory: PathBuf,
+}
+
+// A property name, where it was written and the values that follow it
+type Property = (String, Position, Vec<(Token, Position)>);
+
+// A shape and where it was written
+type ShapeAt = (Position, Box<dyn Hitable + Sync>);
+
+// The properties of a `{ ... }` block, in the order they were written
+struct Block {
+    position: Position,
+    properties: Vec<Property>,
+    inline_material: Option<MaterialDescription>,
+    // The properties given inline textures, and their textures
+    inline_textures: Vec<(String, TextureDescription)>,
+}
+
+impl Parser {
+    fn new(tokens: Vec<(Token, Position)>, directory: &Path) -> Parser {
+        Parser {
+            tokens,
+            index: 0,
+            materials: HashMap::new(),
+            textures: HashMap::new(),
+            objects: HashMap::new(),
+            directory: directory.to_path_buf(),
+        }
+    }
+
+    fn peek(&self) -> &(Token, Position) {
+        &self.tokens[self.index]
+    }
+
+    fn next(&mut self) -> (Token, Position) {
+        let token = self.tokens[self.index].clone();
+
+        if token.0 != Token::End {
+            self.index += 1;
+        }
+
+        token
+    }
+
+    fn expect_word(&mut self, what: &str) -> Result<(String, Position), ParseError> {
+        match self.next() {
+            (Token::Word(word), position) => Ok((word, position)),
+            (token, position) => Err(ParseError::new(
+                position,
+                format!("expected {}, found {}", what, token),
+            )),
+        }
+    }
+
+    fn parse_scene(mut self) -> Result<Scene, ParseError> {
+        let mut camera = None;
+        let mut objects: Vec<Box<dyn Hitable + Sync>> = Vec::new();
+
+        loop {
+            let (token, position) = self.next();
+
+            match token {
+                Token::End => break,
+                Token::Word(ref word) if word == "camera" => {
+                    if camera.is_some() {
+                        return Err(ParseError::new(
+                            position,
+                            String::from("the camera can only be declared once"),
+                        ));
+                    }
+
+                    camera = Some(self.parse_camera()?);
+                }
+                Token::Word(ref word) if word == "material" => self.parse_material_declaration()?,
+                Token::Word(ref word) if word == "texture" => self.parse_texture_declaration()?,
+                Token::Word(ref word) if word == "object" => self.parse_object_declaration()?,
+                Token::Word(ref word) if SHAPES.contains(&word.as_str()) => {
+                    objects.push(self.parse_shape(word)?)
+                }
+                token => {
+                    return Err(ParseError::new(
+                        position,
+                        format!(
+                            "expected `camera`, `material`, `texture`, `object` or a shape ({}), \
+                             found {}",
+                            SHAPES.join(", "),
+                            token
+                        ),
+                    ))
+                }
+            }
+        }
+
+        // Without a camera, the scene is framed from the front, if it has an extent to frame
+        let camera = camera
+            .or_else(|| CameraSettings::framing(&objects))
+            .ok_or_else(|| {
+                ParseError::new(
+                    Position { line: 1, column: 1 },
+                    String::from(
+                        "the scene does not declare a camera, and has no bounded objects to frame",
+                    ),
+                )
+            })?;
+
+        Ok(Scene { camera, objects })
+    }
+
+    // Reads a `{ name values... name values... }` block. Values are only checked for their
+    // count and type once someone asks for a given property.
+    fn parse_block(&mut self, allowed: &[&str]) -> Result<Block, ParseError> {
+        let position = match self.next() {
+            (Token::OpenBrace, position) => position,
+            (token, position) => {
+                return Err(ParseError::new(
+                    position,
+                    format!("expected `{{`, found {}", token),
+                ))
+            }
+        };
+
+        let mut properties: Vec<Property> = Vec::new();
+        let mut inline_material = None;
+        let mut inline_textures = Vec::new();
+
+        loop {
+            match self.next() {
+                (Token::CloseBrace, _) => break,
+                (Token::Word(name), name_position) => {
+                    if !allowed.contains(&name.as_str()) {
+                        return Err(ParseError::new(
+                            name_position,
+                            format!(
+                                "unknown property `{}`, expected one of: {}",
+                                name,
+                                allowed.join(", ")
+                            ),
+                        ));
+                    }
+
+                    if properties.iter().any(|(existing, _, _)| *existing == name) {
+                        return Err(ParseError::new(
+                            name_position,
+                            format!("property `{}` is set more than once", name),
+                        ));
+                    }
+
+                    // An inline material, as in `material metal { albedo 1 1 1 }`, has a block
+                    // of its own so it can't be read as a plain list of values
+                    if name == "material" {
+                        if let (Token::Word(kind), _) = self.peek() {
+                            if MATERIAL_KINDS.contains(&kind.as_str()) {
+                                inline_material = Some(self.parse_material_body()?);
+                                properties.push((name, name_position, Vec::new()));
+                                continue;
+                            }
+                        }
+                    }
+
+                    // And so does an inline texture, as in `albedo checker { ... }`
+                    if TEXTURED_PROPERTIES.contains(&name.as_str()) {
+                        if let (Token::Word(kind), _) = self.peek() {
+                            if TEXTURE_KINDS.contains(&kind.as_str()) {
+                                inline_textures.push((name.clone(), self.parse_texture_body()?));
+                                properties.push((name, name_position, Vec::new()));
+                                continue;
+                            }
+                        }
+                    }
+
+                    // Everything up to the next property name belongs to this property
+                    let mut values = Vec::new();
+
+                    loop {
+                        match self.peek() {
+                            (Token::Number(_), _) | (Token::Text(_), _) => values.push(self.next()),
+                            (Token::Word(word), _) if !allowed.contains(&word.as_str()) => {
+                                values.push(self.next())
+                            }
+                            _ => break,
+                        }
+                    }
+
+                    properties.push((name, name_position, values));
+                }
+                (token, position) => {
+                    return Err(ParseError::new(
+                        position,
+                        format!("expected a property name or `}}`, found {}", token),
+                    ))
+                }
+            }
+        }
+
+        Ok(Block {
+            position,
+            properties,
+            inline_material,
+            inline_textures,
+        })
+    }
+
+    fn parse_camera(&mut self) -> Result<CameraSettings, ParseError> {
+        let block = self.parse_block(&[
+            "look_from",
+            "look_at",
+            "view_up",
+            "vertical_fov",
+            "aspect_ratio",
+            "aperture",
+            "focus_distance",
+            "shutter",
+        ])?;
+
+        // Open for the whole time objects take to move, unless it's given
+        let shutter = match block.numbers("shutter", 2)?.as_deref() {
+            Some(&[open, close]) if 0.0 <= open && open <= close && close <= 1.0 => (open, close),
+            Some(_) => {
+                return Err(ParseError::new(
+                    block.get("shutter").unwrap().0,
+                    String::from("`shutter` must open and then close, between times 0 and 1"),
+                ))
+            }
+            None => (0.0, 1.0),
+        };
+
+        let look_from = block.required_vector("look_from")?;
+        let look_at = block.required_vector("look_at")?;
+
+        if look_from == look_at {
+            return Err(ParseError::new(
+                block.get("look_at").unwrap().0,
+                String::from("`look_at` must be somewhere else than `look_from`"),
+            ));
+        }
+
+        // Up has to be off the line of sight, or there's no telling which way is up in the image
+        let view_up = block
+            .vector("view_up")?
+            .unwrap_or_else(|| Vector3::new(0.0, 1.0, 0.0));
+        let view = look_at - look_from;
+
+        if view_up.cross(view).squared_length()
+            <= 1e-12 * view_up.squared_length() * view.squared_length()
+        {
+            let position = block.get("view_up").or_else(|| block.get("look_at"));
+            return Err(ParseError::new(
+                position.unwrap().0,
+                String::from("`view_up` cannot point along the line from `look_from` to `look_at`"),
+            ));
+        }
+
+        let vertical_fov = block.required_number("vertical_fov")?;
+
+        if vertical_fov <= 0.0 || vertical_fov >= 180.0 {
+            return Err(ParseError::new(
+                block.get("vertical_fov").unwrap().0,
+                String::from("`vertical_fov` must be above 0 and below 180 degrees"),
+            ));
+        }
+
+        Ok(CameraSettings {
+            look_from,
+            look_at,
+            view_up,
+            vertical_fov,
+            aspect_ratio: block.positive_number("aspect_ratio")?,
+            aperture: block.number("aperture")?.unwrap_or(0.0),
+            focus_distance: block.positive_number("focus_distance")?,
+            shutter,
+        })
+    }
+
+    fn parse_material_declaration(&mut self) -> Result<(), ParseError> {
+        let (name, position) = self.expect_word("a material name")?;
+
+        if MATERIAL_KINDS.contains(&name.as_str()) {
+            return Err(ParseError::new(
+                position,
+                format!("`{}` is a material kind and cannot be used as a name", name),
+            ));
+        }
+
+        if self.materials.contains_key(&name) {
+            return Err(ParseError::new(
+                position,
+                format!("material `{}` is declared more than once", name),
+            ));
+        }
+
+        let material = self.parse_material_body()?;
+        self.materials.insert(name, material);
+
+        Ok(())
+    }
+
+    // Parses `<kind> <definition>`, as in `metal { albedo 1 1 1 fuzz 0.2 }`
+    fn parse_material_body(&mut self) -> Result<MaterialDescription, ParseError> {
+        let (kind, position) = self.expect_word("a material kind")?;
+
+        match kind.as_str() {
+            "lambertian" => {
+                let block = self.parse_block(&["albedo"])?;
+                Ok(MaterialDescription::Lambertian {
+                    albedo: self.required_texture(&block, "albedo")?,
+                })
+            }
+            "metal" => {
+                let block = self.parse_block(&["albedo", "fuzz"])?;
+                Ok(MaterialDescription::Metal {
+                    albedo: self.required_texture(&block, "albedo")?,
+                    fuzz: self
+                        .texture(&block, "fuzz")?
+                        .unwrap_or_else(|| TextureDescription::grey(0.0)),
+                })
+            }
+            "dielectric" => {
+                if let (Token::Word(_), _) = self.peek() {
+                    let (preset, position) = self.expect_word("a dielectric preset")?;
+
+                    return match preset.as_str() {
+                        "water" => Ok(MaterialDescription::Water),
+                        "glass" => Ok(MaterialDescription::Glass),
+                        "diamond" => Ok(MaterialDescription::Diamond),
+                        _ => Err(ParseError::new(
+                            position,
+                            format!(
+                                "unknown dielectric preset `{}`, expected one of: water, glass, diamond",
+                                preset
+                            ),
+                        )),
+                    };
+                }
+
+                let block = self.parse_block(&["refraction_index"])?;
+                Ok(MaterialDescription::Dielectric {
+                    refraction_index: block
+                        .positive_number("refraction_index")?
+                        .ok_or_else(|| block.missing("refraction_index"))?,
+                })
+            }
+            _ => Err(ParseError::new(
+                position,
+                format!(
+                    "unknown material kind `{}`, expected one of: {}",
+                    kind,
+                    MATERIAL_KINDS.join(", ")
+                ),
+            )),
+        }
+    }
+
+    fn parse_texture_declaration(&mut self) -> Result<(), ParseError> {
+        let (name, position) = self.expect_word("a texture name")?;
+
+        if TEXTURE_KINDS.contains(&name.as_str()) {
+            return Err(ParseError::new(
+                position,
+                format!("`{}` is a texture kind and cannot be used as a name", name),
+            ));
+        }
+
+        if self.textures.contains_key(&name) {
+            return Err(ParseError::new(
+                position,
+                format!("texture `{}` is declared more than once", name),
+            ));
+        }
+
+        let texture = self.parse_texture_body()?;
+        self.textures.insert(name, texture);
+
+        Ok(())
+    }
+
+    // Parses `<kind> <definition>`, as in `checker { even 1 1 1 odd 0 0 0 }`
+    fn parse_texture_body(&mut self) -> Result<TextureDescription, ParseError> {
+        let (kind, position) = self.expect_word("a texture kind")?;
+
+        match kind.as_str() {
+            "checker" => {
+                let block = self.parse_block(&["even", "odd", "scale"])?;
+                Ok(TextureDescription::Checker {
+                    even: Box::new(self.required_texture(&block, "even")?),
+                    odd: Box::new(self.required_texture(&block, "odd")?),
+                    scale: block.positive_number("scale")?.unwrap_or(1.0),
+                })
+            }
+            "gradient" => {
+                let block = self.parse_block(&["start", "end", "start_color", "end_color"])?;
+                let start = block.required_vector("start")?;
+                let end = block.required_vector("end")?;
+
+                if start == end {
+                    return Err(ParseError::new(
+                        block.get("end").unwrap().0,
+                        String::from("a gradient cannot `start` and `end` at the same point"),
+                    ));
+                }
+
+                Ok(TextureDescription::Gradient {
+                    start,
+                    end,
+                    start_color: block.required_vector("start_color")?,
+                    end_color: block.required_vector("end_color")?,
+                })
+            }
+            "image" => {
+                let block = self.parse_block(&["file", "scale", "linear"])?;
+                let (position, path) = block.required_text("file")?;
+                let image = image::read(&self.directory.join(path))
+                    .map_err(|error| ParseError::new(position, error.to_string()))?;
+
+                // Most images store colors for a display, which have to be made linear again
+                let image = if block.flag("linear")? {
+                    image
+                } else {
+                    image.decode(Transfer::Srgb)
+                };
+
+                Ok(TextureDescription::Image {
+                    image: Arc::new(image),
+                    scale: block.positive_number("scale")?.unwrap_or(1.0),
+                })
+            }
+            _ => Err(ParseError::new(
+                position,
+                format!(
+                    "unknown texture kind `{}`, expected one of: {}",
+                    kind,
+                    TEXTURE_KINDS.join(", ")
+                ),
+            )),
+        }
+    }
+
+    fn parse_shape(&mut self, shape: &str) -> Result<Box<dyn Hitable + Sync>, ParseError> {
+        match shape {
+            "sphere" => self.parse_sphere(),
+            "plane" => self.parse_plane(),
+            "quad" => self.parse_quad(),
+            "box" => self.parse_box(),
+            "disk" => self.parse_disk(),
+            "cylinder" | "cone" | "paraboloid" => self.parse_quadric(shape),
+            "torus" => self.parse_torus(),
+            "capsule" => self.parse_capsule(),
+            "union" | "intersection" | "difference" => self.parse_csg(shape),
+            "mesh" => self.parse_mesh(),
+            "model" => self.parse_model(),
+            "instance" => self.parse_instance(),
+            _ => unreachable!("`{}` is not in SHAPES", shape),
+        }
+    }
+
+    // Parses `<name> { <shapes> }`
+    fn parse_object_declaration(&mut self) -> Result<(), ParseError> {
+        let (name, position) = self.expect_word("an object name")?;
+
+        if self.objects.contains_key(&name) {
+            return Err(ParseError::new(
+                position,
+                format!("object `{}` is declared more than once", name),
+            ));
+        }
+
+        let (_, shapes) = self.parse_shapes()?;
+        let shapes = shapes.into_iter().map(|(_, shape)| shape).collect();
+        self.objects.insert(name, Arc::new(Bvh::new(shapes)));
+
+        Ok(())
+    }
+
+    // Parses `{ <shapes> }`, keeping where the block and every shape start
+    fn parse_shapes(&mut self) -> Result<(Position, Vec<ShapeAt>), ParseError> {
+        let block_position = match self.next() {
+            (Token::OpenBrace, position) => position,
+            (token, position) => {
+                return Err(ParseError::new(
+                    position,
+                    format!("expected `{{`, found {}", token),
+                ))
+            }
+        };
+
+        let mut shapes = Vec::new();
+
+        loop {
+            match self.next() {
+                (Token::CloseBrace, _) => break,
+                (Token::Word(ref word), position) if SHAPES.contains(&word.as_str()) => {
+                    shapes.push((position, self.parse_shape(word)?))
+                }
+                (token, position) => {
+                    return Err(ParseError::new(
+                        position,
+                        format!(
+                            "expected a shape ({}) or `}}`, found {}",
+                            SHAPES.join(", "),
+                            token
+                        ),
+                    ))
+                }
+            }
+        }
+
+        Ok((block_position, shapes))
+    }
+
+    // Parses `{ <shapes> }` and combines them in order, so a difference is what's left of the
+    // first shape after taking away all the others
+    fn parse_csg(&mut self, shape: &str) -> Result<Box<dyn Hitable + Sync>, ParseError> {
+        let operation = match shape {
+            "union" => Operation::Union,
+            "intersection" => Operation::Intersection,
+            _ => Operation::Difference,
+        };
+        let (position, shapes) = self.parse_shapes()?;
+
+        if shapes.len() < 2 {
+            return Err(ParseError::new(
+                position,
+                format!("`{}` needs at least two shapes to combine", shape),
+            ));
+        }
+
+        if let Some((position, _)) = shapes.iter().find(|(_, shape)| !shape.is_solid()) {
+            return Err(ParseError::new(
+                *position,
+                String::from(
+                    "only solid shapes can be combined: spheres, boxes, tori, capsules, capped \
+                     cylinders, cones and paraboloids that go the whole way around, combinations \
+                     and instances of those",
+                ),
+            ));
+        }
+
+        let mut shapes = shapes.into_iter().map(|(_, shape)| shape);
+        let first = shapes.next().unwrap();
+
+        Ok(shapes.fold(first, |a, b| {
+            Box::new(Csg::new(operation, a, b).expect("both shapes are solid"))
+        }))
+    }
+
+    fn parse_instance(&mut self) -> Result<Box<dyn Hitable + Sync>, ParseError> {
+        let block = self.parse_block(&[
+            "object",
+            "scale",
+            "rotate",
+            "translate",
+            "end_scale",
+            "end_rotate",
+            "end_translate",
+            "matrix",
+        ])?;
+
+        let object = match block.get("object") {
+            Some((_, [(Token::Word(name), position)])) => match self.objects.get(name) {
+                Some(object) => object.clone(),
+                None => {
+                    return Err(ParseError::new(
+                        *position,
+                        format!("object `{}` has not been declared", name),
+                    ))
+                }
+            },
+            Some((position, _)) => {
+                return Err(ParseError::new(
+                    position,
+                    String::from("`object` expects the name of a declared object"),
+                ))
+            }
+            None => return Err(block.missing("object")),
+        };
+
+        if let Some((position, numbers)) = block.all_numbers("matrix")? {
+            if block.properties.iter().any(|(name, _, _)| {
+                ["scale", "rotate", "translate"]
+                    .iter()
+                    .any(|transform| name.ends_with(transform))
+            }) {
+                return Err(ParseError::new(
+                    position,
+                    String::from(
+                        "`matrix` cannot be combined with `scale`, `rotate`, `translate` \
+                         or their `end_` versions",
+                    ),
+                ));
+            }
+
+            if numbers.len() != 12 {
+                return Err(ParseError::new(
+                    position,
+                    String::from("`matrix` expects 12 numbers, the top three rows of the matrix"),
+                ));
+            }
+
+            let mut rows = Matrix4::identity().rows;
+            for (row, numbers) in rows.iter_mut().zip(numbers.chunks(4)) {
+                row.copy_from_slice(numbers);
+            }
+
+            return match Transform::from_matrix(Matrix4::new(rows)) {
+                Some(transform) => Ok(Box::new(Instance::new(object, transform))),
+                None => Err(ParseError::new(
+                    position,
+                    String::from("`matrix` cannot be inverted"),
+                )),
+            };
+        }
+
+        // Instances with an end pose move to it while the shutter is open
+        let moves = ["end_scale", "end_rotate", "end_translate"]
+            .iter()
+            .any(|name| block.get(name).is_some());
+
+        if !moves {
+            return Ok(Box::new(Instance::new(object, block.transform()?)));
+        }
+
+        let start = block.pose("", &Pose::identity())?;
+        let end = block.pose("end_", &start)?;
+
+        if end == start {
+            Ok(Box::new(Instance::new(object, start.transform())))
+        } else {
+            Ok(Box::new(Instance::moving(object, start, end)))
+        }
+    }
+
+    fn parse_sphere(&mut self) -> Result<Box<dyn Hitable + Sync>, ParseError> {
+        let block = self.parse_block(&["center", "end_center", "radius", "material"])?;
+
+        let center = block.required_vector("center")?;
+        let end = block.vector("end_center")?.unwrap_or(center);
+        let radius = block
+            .positive_number("radius")?
+            .ok_or_else(|| block.missing("radius"))?;
+        let material = self.material(&block)?;
+
+        Ok(Box::new(Sphere::moving(center, end, radius, material)))
+    }
+
+    fn parse_plane(&mut self) -> Result<Box<dyn Hitable + Sync>, ParseError> {
+        let block = self.parse_block(&["point", "normal", "material"])?;
+
+        let point = block.required_vector("point")?;
+        let normal = block.required_direction("normal")?;
+        let material = self.material(&block)?;
+
+        Ok(Box::new(Plane::new(point, normal, material)))
+    }
+
+    fn parse_quad(&mut self) -> Result<Box<dyn Hitable + Sync>, ParseError> {
+        let block = self.parse_block(&["corner", "u", "v", "material"])?;
+
+        let corner = block.required_vector("corner")?;
+        let u = block.required_vector("u")?;
+        let v = block.required_vector("v")?;
+        let material = self.material(&block)?;
+
+        if u.cross(v).squared_length() == 0.0 {
+            return Err(ParseError::new(
+                block.position,
+                String::from("the edges `u` and `v` of a quad cannot be parallel, or zero"),
+            ));
+        }
+
+        Ok(Box::new(Quad::new(corner, u, v, material)))
+    }
+
+    fn parse_box(&mut self) -> Result<Box<dyn Hitable + Sync>, ParseError> {
+        let block = self.parse_block(&["min", "max", "material"])?;
+
+        let min = block.required_vector("min")?;
+        let max = block.required_vector("max")?;
+        let material = self.material(&block)?;
+
+        if min.x >= max.x || min.y >= max.y || min.z >= max.z {
+            return Err(ParseError::new(
+                block.get("max").unwrap().0,
+                String::from("`max` must be above `min` on every axis"),
+            ));
+        }
+
+        Ok(Box::new(Cuboid::new(min, max, material)))
+    }
+
+    fn parse_disk(&mut self) -> Result<Box<dyn Hitable + Sync>, ParseError> {
+        let block = self.parse_block(&["center", "normal", "radius", "material"])?;
+
+        let center = block.required_vector("center")?;
+        let normal = block.required_direction("normal")?;
+        let radius = block
+            .positive_number("radius")?
+            .ok_or_else(|| block.missing("radius"))?;
+        let material = self.material(&block)?;
+
+        Ok(Box::new(Disk::new(center, normal, radius, material)))
+    }
+
+    // Cylinders, cones and paraboloids, which all go up from their `base` along their `axis`
+    fn parse_quadric(&mut self, shape: &str) -> Result<Box<dyn Hitable + Sync>, ParseError> {
+        let block = self.parse_block(&[
+            "base", "axis", "radius", "height", "z_range", "phi_max", "capped", "material",
+        ])?;
+
+        let placement = block.placement("base")?;
+        let radius = block
+            .positive_number("radius")?
+            .ok_or_else(|| block.missing("radius"))?;
+        let height = block
+            .positive_number("height")?
+            .ok_or_else(|| block.missing("height"))?;
+        let (z_min, z_max) = block.z_range(height)?;
+        let phi_max = block.phi_max()?;
+        let capped = block.flag("capped")?;
+        let material = self.material(&block)?;
+
+        let shape: Arc<dyn Hitable> = match shape {
+            "cylinder" => Arc::new(Cylinder::new(
+                radius, z_min, z_max, phi_max, capped, material,
+            )),
+            "cone" => Arc::new(Cone::new(
+                radius, height, z_min, z_max, phi_max, capped, material,
+            )),
+            _ => Arc::new(Paraboloid::new(
+                radius, height, z_min, z_max, phi_max, capped, material,
+            )),
+        };
+
+        Ok(Box::new(Instance::new(shape, placement)))
+    }
+
+    fn parse_torus(&mut self) -> Result<Box<dyn Hitable + Sync>, ParseError> {
+        let block = self.parse_block(&[
+            "center",
+            "axis",
+            "major_radius",
+            "minor_radius",
+            "phi_max",
+            "material",
+        ])?;
+
+        let placement = block.placement("center")?;
+        let major_radius = block
+            .positive_number("major_radius")?
+            .ok_or_else(|| block.missing("major_radius"))?;
+        let minor_radius = block
+            .positive_number("minor_radius")?
+            .ok_or_else(|| block.missing("minor_radius"))?;
+        let phi_max = block.phi_max()?;
+        let material = self.material(&block)?;
+
+        let torus = Torus::new(major_radius, minor_radius, phi_max, material);
+        Ok(Box::new(Instance::new(Arc::new(torus), placement)))
+    }
+
+    fn parse_capsule(&mut self) -> Result<Box<dyn Hitable + Sync>, ParseError> {
+        let block = self.parse_block(&["base", "axis", "radius", "height", "material"])?;
+
+        let placement = block.placement("base")?;
+        let radius = block
+            .positive_number("radius")?
+            .ok_or_else(|| block.missing("radius"))?;
+        let height = block
+            .positive_number("height")?
+            .ok_or_else(|| block.missing("height"))?;
+        let material = self.material(&block)?;
+
+        let capsule = Capsule::new(radius, height, material);
+        Ok(Box::new(Instance::new(Arc::new(capsule), placement)))
+    }
+
+    fn parse_mesh(&mut self) -> Result<Box<dyn Hitable + Sync>, ParseError> {
+        let block = self.parse_block(&[
+            "vertices",
+            "triangles",
+            "normals",
+            "uvs",
+            "colors",
+            "material",
+        ])?;
+
+        let vectors = |name: &str| -> Result<Vec<Vector3>, ParseError> {
+            let (position, numbers) = match block.all_numbers(name)? {
+                Some(property) => property,
+                None => return Ok(Vec::new()),
+            };
+
+            if numbers.len() % 3 != 0 {
+                return Err(ParseError::new(
+                    position,
+                    format!("`{}` expects 3 numbers for every vertex", name),
+                ));
+            }
+
+            Ok(numbers
+                .chunks(3)
+                .map(|v| Vector3::new(v[0], v[1], v[2]))
+                .collect())
+        };
+
+        let positions = vectors("vertices")?;
+        let normals = vectors("normals")?;
+        let colors = vectors("colors")?;
+
+        if positions.is_empty() {
+            return Err(block.missing("vertices"));
+        }
+
+        let uvs = match block.all_numbers("uvs")? {
+            Some((position, numbers)) if numbers.len() % 2 != 0 => {
+                return Err(ParseError::new(
+                    position,
+                    String::from("`uvs` expects 2 numbers for every vertex"),
+                ))
+            }
+            Some((_, numbers)) => numbers.chunks(2).map(|uv| (uv[0], uv[1])).collect(),
+            None => Vec::new(),
+        };
+
+        let (position, indices) = block
+            .all_numbers("triangles")?
+            .ok_or_else(|| block.missing("triangles"))?;
+
+        if indices.len() % 3 != 0
+            || indices
+                .iter()
+                .any(|&index| index < 0.0 || index.fract() != 0.0 || index > f64::from(u32::MAX))
+        {
+            return Err(ParseError::new(
+                position,
+                String::from("`triangles` expects 3 vertex indices for every triangle"),
+            ));
+        }
+
+        let mesh = Mesh {
+            positions,
+            normals,
+            uvs,
+            colors,
+            triangles: indices
+                .chunks(3)
+                .map(|t| [t[0] as u32, t[1] as u32, t[2] as u32])
+                .collect(),
+        };
+        let material = self.material(&block)?;
+
+        match TriangleMesh::new(mesh, material) {
+            Ok(mesh) => Ok(Box::new(mesh)),
+            Err(error) => Err(ParseError::new(block.position, error.to_string())),
+        }
+    }
+
+    // A model from an OBJ, PLY or STL file, as one or more meshes. `material` replaces all of the materials
+    // of the file, which then doesn't need its MTL files.
+    fn parse_model(&mut self) -> Result<Box<dyn Hitable + Sync>, ParseError> {
+        let block = self.parse_block(&["file", "material"])?;
+
+        let (position, path) = block.required_text("file")?;
+        let material = self.material_description(&block)?;
+        let path = self.directory.join(path);
+
+        if !is_model(&path) {
+            return Err(ParseError::new(
+                position,
+                format!(
+                    "models can only be loaded from files with one of these extensions: {}",
+                    MODEL_FORMATS.join(", ")
+                ),
+            ));
+        }
+
+        match load_model(&path, material) {
+            // A single BVH around all of the model's meshes, which nothing else is inside of
+            Ok(meshes) => Ok(Box::new(Bvh::new(meshes))),
+            Err(error) => Err(ParseError::new(position, error.to_string())),
+        }
+    }
+
+    fn material(&self, block: &Block) -> Result<Box<dyn Material + Sync>, ParseError> {
+        match self.material_description(block)? {
+            Some(material) => Ok(material.build()),
+            None => Err(block.missing("material")),
+        }
+    }
+
+    fn material_description(
+        &self,
+        block: &Block,
+    ) -> Result<Option<MaterialDescription>, ParseError> {
+        let (position, values) = match block.get("material") {
+            Some(property) => property,
+            None => return Ok(None),
+        };
+
+        if let Some(material) = &block.inline_material {
+            return Ok(Some(material.clone()));
+        }
+
+        match values {
+            [(Token::Word(name), name_position)] => match self.materials.get(name) {
+                Some(material) => Ok(Some(material.clone())),
+                None => Err(ParseError::new(
+                    *name_position,
+                    format!("material `{}` has not been declared", name),
+                )),
+            },
+            _ => Err(ParseError::new(
+                position,
+                String::from("`material` expects a material name or an inline material"),
+            )),
+        }
+    }
+
+    // A color (or one number for a shade of grey), a declared texture's name or an inline texture
+    fn texture(&self, block: &Block, name: &str) -> Result<Option<TextureDescription>, ParseError> {
+        let (position, values) = match block.get(name) {
+            Some(property) => property,
+            None => return Ok(None),
+        };
+
+        if let Some((_, texture)) = block
+            .inline_textures
+            .iter()
+            .find(|(property, _)| property == name)
+        {
+            return Ok(Some(texture.clone()));
+        }
+
+        match values {
+            [(Token::Word(texture), texture_position)] => match self.textures.get(texture) {
+                Some(texture) => Ok(Some(texture.clone())),
+                None => Err(ParseError::new(
+                    *texture_position,
+                    format!("texture `{}` has not been declared", texture),
+                )),
+            },
+            [(Token::Number(grey), _)] => Ok(Some(TextureDescription::grey(*grey))),
+            [(Token::Number(r), _), (Token::Number(g), _), (Token::Number(b), _)] => {
+                Ok(Some(TextureDescription::Constant(Vector3::new(*r, *g, *b))))
+            }
+            _ => Err(ParseError::new(
+                position,
+                format!(
+                    "`{}` expects a color, a texture name or an inline texture",
+                    name
+                ),
+            )),
+        }
+    }
+
+    fn required_texture(
+        &self,
+        block: &Block,
+        name: &str,
+    ) -> Result<TextureDescription, ParseError> {
+        self.texture(block, name)?
+            .ok_or_else(|| block.missing(name))
+    }
+}
+
+// A `rotate` property, kept as written until it's known whether the instance moves
+enum Rotation {
+    // An axis and degrees around it
+    Axis(Vector3, f64),
+    // Degrees around x, y and then z
+    Euler(Vector3),
+}
+
+impl Rotation {
+    fn transform(&self) -> Transform {
+        match *self {
+            Rotation::Axis(axis, degrees) => Transform::rotation(axis, degrees),
+            Rotation::Euler(degrees) => Transform::rotation_euler(degrees),
+        }
+    }
+
+    fn quaternion(&self) -> Quaternion {
+        match *self {
+            Rotation::Axis(axis, degrees) => Quaternion::from_axis_angle(axis, degrees),
+            Rotation::Euler(degrees) => Quaternion::from_euler(degrees),
+        }
+    }
+}
+
+impl Block {
+    fn get(&self, name: &str) -> Option<(Position, &[(Token, Position)])> {
+        self.properties
+            .iter()
+            .find(|(property, _, _)| property == name)
+            .map(|(_, position, values)| (*position, values.as_slice()))
+    }
+
+    fn missing(&self, name: &str) -> ParseError {
+        ParseError::new(
+            self.position,
+            format!("missing required property `{}`", name),
+        )
+    }
+
+    fn numbers(&self, name: &str, count: usize) -> Result<Option<Vec<f64>>, ParseError> {
+        let (position, numbers) = match self.all_numbers(name)? {
+            Some(property) => property,
+            None => return Ok(None),
+        };
+
+        if numbers.len() != count {
+            return Err(ParseError::new(
+                position,
+                format!(
+                    "`{}` expects {} number(s), found {}",
+                    name,
+                    count,
+                    numbers.len()
+                ),
+            ));
+        }
+
+        Ok(Some(numbers))
+    }
+
+    // However many numbers a property has, for the few that take a variable amount
+    fn all_numbers(&self, name: &str) -> Result<Option<(Position, Vec<f64>)>, ParseError> {
+        let (position, values) = match self.get(name) {
+            Some(property) => property,
+            None => return Ok(None),
+        };
+
+        let numbers: Vec<f64> = values
+            .iter()
+            .map(|(token, position)| match token {
+                Token::Number(number) => Ok(*number),
+                token => Err(ParseError::new(
+                    *position,
+                    format!("expected a number for `{}`, found {}", name, token),
+                )),
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Some((position, numbers)))
+    }
+
+    fn number(&self, name: &str) -> Result<Option<f64>, ParseError> {
+        Ok(self.numbers(name, 1)?.map(|numbers| numbers[0]))
+    }
+
+    fn positive_number(&self, name: &str) -> Result<Option<f64>, ParseError> {
+        match self.number(name)? {
+            Some(number) if number <= 0.0 => Err(ParseError::new(
+                self.get(name).unwrap().0,
+                format!("`{}` must be positive", name),
+            )),
+            number => Ok(number),
+        }
+    }
+
+    fn required_number(&self, name: &str) -> Result<f64, ParseError> {
+        self.number(name)?.ok_or_else(|| self.missing(name))
+    }
+
+    fn vector(&self, name: &str) -> Result<Option<Vector3>, ParseError> {
+        Ok(self
+            .numbers(name, 3)?
+            .map(|numbers| Vector3::new(numbers[0], numbers[1], numbers[2])))
+    }
+
+    fn required_text(&self, name: &str) -> Result<(Position, &str), ParseError> {
+        match self.get(name) {
+            Some((_, [(Token::Text(text), position)])) => Ok((*position, text)),
+            Some((position, _)) => Err(ParseError::new(
+                position,
+                format!("`{}` expects text between double quotes", name),
+            )),
+            None => Err(self.missing(name)),
+        }
+    }
+
+    fn required_vector(&self, name: &str) -> Result<Vector3, ParseError> {
+        self.vector(name)?.ok_or_else(|| self.missing(name))
+    }
+
+    // A vector that only matters for its direction, so it can't be zero
+    fn required_direction(&self, name: &str) -> Result<Vector3, ParseError> {
+        let direction = self.required_vector(name)?;
+
+        if direction.squared_length() == 0.0 {
+            return Err(ParseError::new(
+                self.get(name).unwrap().0,
+                format!("`{}` cannot be zero", name),
+            ));
+        }
+
+        Ok(direction)
+    }
+
+    // A property that is either there, without any values, or not
+    fn flag(&self, name: &str) -> Result<bool, ParseError> {
+        match self.get(name) {
+            Some((_, [])) => Ok(true),
+            Some((_, [(_, position), ..])) => Err(ParseError::new(
+                *position,
+                format!("`{}` does not take any values", name),
+            )),
+            None => Ok(false),
+        }
+    }
+
+    // Where a shape built around the z axis goes: its `origin`, and the direction its `axis`
+    // points in, up unless it's given
+    fn placement(&self, origin: &str) -> Result<Transform, ParseError> {
+        let origin = self.required_vector(origin)?;
+        let axis = match self.get("axis") {
+            Some(_) => self.required_direction("axis")?,
+            None => Vector3::new(0.0, 1.0, 0.0),
+        };
+
+        Ok(Transform::along(origin, axis))
+    }
+
+    // How an instance that stays put is scaled, rotated and translated
+    fn transform(&self) -> Result<Transform, ParseError> {
+        let mut transform = Transform::identity();
+
+        if let Some(scale) = self.scale("scale")? {
+            transform = transform.then(&Transform::scaling(scale));
+        }
+
+        if let Some(rotation) = self.rotation("rotate")? {
+            transform = transform.then(&rotation.transform());
+        }
+
+        if let Some(offset) = self.vector("translate")? {
+            transform = transform.then(&Transform::translation(offset));
+        }
+
+        Ok(transform)
+    }
+
+    // The same, for an instance that moves, with `prefix` in front of each property's name.
+    // Whatever isn't given is left as it is in `from`.
+    fn pose(&self, prefix: &str, from: &Pose) -> Result<Pose, ParseError> {
+        let mut pose = *from;
+
+        if let Some(scale) = self.scale(&format!("{}scale", prefix))? {
+            pose.scale = scale;
+        }
+
+        if let Some(rotation) = self.rotation(&format!("{}rotate", prefix))? {
+            pose.rotation = rotation.quaternion();
+        }
+
+        if let Some(offset) = self.vector(&format!("{}translate", prefix))? {
+            pose.translation = offset;
+        }
+
+        Ok(pose)
+    }
+
+    // One factor, or one per axis
+    fn scale(&self, name: &str) -> Result<Option<Vector3>, ParseError> {
+        let (position, numbers) = match self.all_numbers(name)? {
+            Some(found) => found,
+            None => return Ok(None),
+        };
+
+        let scale = match *numbers.as_slice() {
+            [s] => Vector3::new(s, s, s),
+            [x, y, z] => Vector3::new(x, y, z),
+            _ => {
+                return Err(ParseError::new(
+                    position,
+                    format!("`{}` expects either 1 or 3 numbers", name),
+                ))
+            }
+        };
+
+        if scale.x == 0.0 || scale.y == 0.0 || scale.z == 0.0 {
+            return Err(ParseError::new(
+                position,
+                format!("`{}` cannot be zero", name),
+            ));
+        }
+
+        Ok(Some(scale))
+    }
+
+    // Around an axis, or around x, y and then z
+    fn rotation(&self, name: &str) -> Result<Option<Rotation>, ParseError> {
+        let (position, numbers) = match self.all_numbers(name)? {
+            Some(found) => found,
+            None => return Ok(None),
+        };
+
+        match *numbers.as_slice() {
+            [x, y, z] => Ok(Some(Rotation::Euler(Vector3::new(x, y, z)))),
+            [x, y, z, degrees] => {
+                let axis = Vector3::new(x, y, z);
+
+                if axis.squared_length() == 0.0 {
+                    return Err(ParseError::new(
+                        position,
+                        format!(
+                            "`{}` needs a non-zero axis, as in `{} 0 1 0 45`",
+                            name, name
+                        ),
+                    ));
+                }
+
+                Ok(Some(Rotation::Axis(axis, degrees)))
+            }
+            _ => Err(ParseError::new(
+                position,
+                format!(
+                    "`{}` expects either an axis and an angle, or 3 angles in degrees",
+                    name
+                ),
+            )),
+        }
+    }
+
+    // Degrees around the axis, the whole way around unless it's given
+    fn phi_max(&self) -> Result<f64, ParseError> {
+        match self.number("phi_max")? {
+            Some(degrees) if degrees <= 0.0 || degrees > 360.0 => Err(ParseError::new(
+                self.get("phi_max").unwrap().0,
+                String::from("`phi_max` must be above 0 and at most 360 degrees"),
+            )),
+            degrees => Ok(degrees.unwrap_or(360.0)),
+        }
+    }
+
+    // The part along the axis that's kept, all of it unless it's given
+    fn z_range(&self, height: f64) -> Result<(f64, f64), ParseError> {
+        match self.numbers("z_range", 2)?.as_deref() {
+            Some(&[z_min, z_max]) if 0.0 <= z_min && z_min < z_max && z_max <= height => {
+                Ok((z_min, z_max))
+            }
+            Some(_) => Err(ParseError::new(
+                self.get("z_range").unwrap().0,
+                String::from(
+                    "`z_range` must go up, from no lower than 0 to no higher than `height`",
+                ),
+            )),
+            None => Ok((0.0, height)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raytracer::Ray;
+
+    fn parse(source: &str) -> Result<Scene, ParseError> {
+        super::parse(source, Path::new(""))
+    }
+
+    const CAMERA: &str = "camera { look_from 0 0 1 look_at 0 0 0 vertical_fov 90 }\n";
+
+    fn error_for(source: &str) -> ParseError {
+        match parse(source) {
+            Ok(_) => panic!("expected `{}` to fail to parse", source),
+            Err(error) => error,
+        }
+    }
+
+    #[test]
+    fn parses_a_full_scene() {
+        let scene = parse(
+            "# A comment\n\
+             camera {\n\
+                 look_from 11 1.8 3.5 # trailing comment\n\
+                 look_at -1 0.5 0\n\
+                 vertical_fov 20\n\
+                 aperture 0.1\n\
+             }\n\
+             material ground lambertian { albedo 0.5 0.5 0.5 }\n\
+             sphere { center 0 -1000 0 radius 1000 material ground }\n\
+             sphere { center 0 1 0 radius 1 material dielectric glass }\n\
+             sphere { material metal { albedo 1 1 1 fuzz 0.3 } radius 1 center 4 1 0 }\n",
+        )
+        .unwrap();
+
+        assert_eq!(scene.objects.len(), 3);
+        assert_eq!(scene.camera.vertical_fov, 20.0);
+        assert_eq!(scene.camera.aperture, 0.1);
+        assert_eq!(scene.camera.look_at.x, -1.0);
+        assert_eq!(scene.camera.view_up.y, 1.0);
+        assert!(scene.camera.focus_distance.is_none());
+    }
+
+    #[test]
+    fn frames_scenes_without_a_camera() {
+        let scene = parse(
+            "sphere { center 0 0 0 radius 1 material lambertian { albedo 1 1 1 } }\n\
+             sphere { center 4 0 0 radius 1 material lambertian { albedo 1 1 1 } }\n",
+        )
+        .unwrap();
+
+        assert_eq!(scene.camera.look_at, Vector3::new(2.0, 0.0, 0.0));
+        assert_eq!(scene.camera.look_from.x, 2.0);
+        assert!(scene.camera.look_from.z > 3.0);
+    }
+
+    #[test]
+    fn frames_the_bounded_objects_of_scenes_with_a_floor() {
+        let scene = parse(
+            "plane { point 0 -1 0 normal 0 1 0 material lambertian { albedo 1 1 1 } }\n\
+             sphere { center 0 0 0 radius 1 material lambertian { albedo 1 1 1 } }\n",
+        )
+        .unwrap();
+
+        assert_eq!(scene.camera.look_at, Vector3::new(0.0, 0.0, 0.0));
+        assert!(scene.camera.look_from.z > 1.0);
+
+        // A floor on its own has nothing to frame
+        let floor = "plane { point 0 0 0 normal 0 1 0 material lambertian { albedo 1 1 1 } }";
+        assert!(error_for(floor)
+            .message
+            .starts_with("the scene does not declare a camera"));
+    }
+
+    #[test]
+    fn places_instances_of_declared_objects() {
+        let scene = parse(&format!(
+            "{}object ball {{ sphere {{ center 0 0 0 radius 1 material metal {{ albedo 1 1 1 }} }} }}\n\
+             instance {{ object ball }}\n\
+             instance {{ object ball scale 2 1 1 rotate 0 0 1 90 translate 0 0 -5 }}\n",
+            CAMERA
+        ))
+        .unwrap();
+
+        assert_eq!(scene.objects.len(), 2);
+
+        // Stretched along x, then turned to stand along y
+        let bounds = scene.objects[1].bounding_box().unwrap();
+        assert!((bounds.max - Vector3::new(1.0, 2.0, -4.0)).magnitude() < 1e-9);
+
+        assert!(error_for(&format!("{}instance {{ object ball }}", CAMERA))
+            .message
+            .contains("has not been declared"));
+        assert!(error_for(&format!(
+            "{}object ball {{ }} instance {{ object ball scale 1 2 }}",
+            CAMERA
+        ))
+        .message
+        .contains("either 1 or 3 numbers"));
+    }
+
+    #[test]
+    fn places_instances_with_euler_angles_or_a_matrix() {
+        let ball =
+            "object ball { sphere { center 1 0 0 radius 1 material metal { albedo 1 1 1 } } }\n";
+        let scene = parse(&format!(
+            "{}{}instance {{ object ball rotate 0 0 90 }}\n\
+             instance {{ object ball matrix 0 -1 0 0  1 0 0 0  0 0 1 0 }}\n",
+            CAMERA, ball
+        ))
+        .unwrap();
+
+        // Both turn the ball a quarter around z, from x to y
+        for object in &scene.objects {
+            let bounds = object.bounding_box().unwrap();
+            assert!((bounds.max - Vector3::new(1.0, 2.0, 1.0)).magnitude() < 1e-9);
+        }
+
+        assert!(error_for(&format!(
+            "{}{}instance {{ object ball matrix 1 0 0 0  0 1 0 0  0 0 0 0 }}",
+            CAMERA, ball
+        ))
+        .message
+        .contains("cannot be inverted"));
+        assert!(error_for(&format!(
+            "{}{}instance {{ object ball translate 1 0 0 matrix 1 0 0 0  0 1 0 0  0 0 1 0 }}",
+            CAMERA, ball
+        ))
+        .message
+        .contains("cannot be combined"));
+    }
+
+    #[test]
+    fn parses_moving_objects() {
+        let ball =
+            "object ball { sphere { center 1 0 0 radius 1 material metal { albedo 1 1 1 } } }\n";
+        let scene = parse(&format!(
+            "camera {{ look_from 0 0 1 look_at 0 0 0 vertical_fov 90 shutter 0.25 0.5 }}\n\
+             {}sphere {{ center 0 0 0 end_center 4 0 0 radius 1 material lambertian {{ albedo 1 1 1 }} }}\n\
+             instance {{ object ball end_rotate 0 0 1 180 }}\n",
+            ball
+        ))
+        .unwrap();
+
+        assert_eq!(scene.camera.shutter, (0.25, 0.5));
+
+        // Where the sphere goes, from x = 0 to x = 4
+        let sphere = &scene.objects[0];
+        let bounds = sphere.bounding_box().unwrap();
+        assert_eq!(bounds.min.x, -1.0);
+        assert_eq!(bounds.max.x, 5.0);
+
+        let ray = |time| {
+            Ray::new(
+                Vector3::new(3.0, 0.0, 5.0),
+                Vector3::new(0.0, 0.0, -1.0),
+                time,
+            )
+        };
+        assert!(sphere.check_hit(ray(0.0), 0.0, f64::MAX).is_none());
+        assert!(sphere.check_hit(ray(1.0), 0.0, f64::MAX).is_some());
+
+        // Halfway through its half turn around z, the ball is on top
+        let ray = Ray::new(
+            Vector3::new(0.0, 1.0, 5.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            0.5,
+        );
+        assert!(scene.objects[1].check_hit(ray, 0.0, f64::MAX).is_some());
+
+        assert!(error_for(&format!(
+            "{}{}instance {{ object ball end_scale 0 }}",
+            CAMERA, ball
+        ))
+        .message
+        .contains("`end_scale` cannot be zero"));
+        assert!(error_for(&format!(
+            "{}{}instance {{ object ball end_translate 1 0 0 matrix 1 0 0 0  0 1 0 0  0 0 1 0 }}",
+            CAMERA, ball
+        ))
+        .message
+        .contains("cannot be combined"));
+        assert!(
+            error_for("camera { look_from 0 0 1 look_at 0 0 0 vertical_fov 90 shutter 1 0 }")
+                .message
+                .contains("`shutter` must open and then close")
+        );
+    }
+
+    #[test]
+    fn parses_flat_shapes_and_boxes() {
+        let scene = parse(&format!(
+            "{}material grey lambertian {{ albedo 0.5 0.5 0.5 }}\n\
+             plane {{ point 0 0 0 normal 0 1 0 material grey }}\n\
+             quad {{ corner 0 0 0 u 1 0 0 v 0 1 0 material grey }}\n\
+             box {{ min 0 0 0 max 1 2 3 material grey }}\n\
+             disk {{ center 0 0 0 normal 0 0 1 radius 2 material grey }}\n",
+            CAMERA
+        ))
+        .unwrap();
+
+        assert_eq!(scene.objects.len(), 4);
+        assert!(scene.objects[0].bounding_box().is_none());
+        assert_eq!(
+            scene.objects[2].bounding_box().unwrap().max,
+            Vector3::new(1.0, 2.0, 3.0)
+        );
+
+        assert!(error_for(&format!(
+            "{}plane {{ point 0 0 0 normal 0 0 0 material metal {{ albedo 1 1 1 }} }}",
+            CAMERA
+        ))
+        .message
+        .contains("`normal` cannot be zero"));
+        assert!(error_for(&format!(
+            "{}quad {{ corner 0 0 0 u 1 0 0 v 2 0 0 material metal {{ albedo 1 1 1 }} }}",
+            CAMERA
+        ))
+        .message
+        .contains("cannot be parallel"));
+        assert!(error_for(&format!(
+            "{}box {{ min 0 0 0 max 1 0 1 material metal {{ albedo 1 1 1 }} }}",
+            CAMERA
+        ))
+        .message
+        .contains("above `min`"));
+    }
+
+    #[test]
+    fn places_round_shapes_along_their_axis() {
+        let scene = parse(&format!(
+            "{}material grey lambertian {{ albedo 0.5 0.5 0.5 }}\n\
+             cylinder {{ base 1 0 0 radius 0.5 height 2 capped material grey }}\n\
+             cone {{ base 0 0 0 axis 1 0 0 radius 1 height 2 z_range 0 1 material grey }}\n\
+             paraboloid {{ base 0 0 0 radius 1 height 1 phi_max 90 material grey }}\n\
+             torus {{ center 0 0 0 axis 0 0 1 major_radius 2 minor_radius 0.5 material grey }}\n\
+             capsule {{ base 0 0 0 radius 1 height 2 material grey }}\n",
+            CAMERA
+        ))
+        .unwrap();
+
+        assert_eq!(scene.objects.len(), 5);
+
+        let assert_bounds = |index: usize, min: Vector3, max: Vector3| {
+            let bounds = scene.objects[index].bounding_box().unwrap();
+            assert!((bounds.min - min).magnitude() < 1e-9, "{:?}", bounds);
+            assert!((bounds.max - max).magnitude() < 1e-9, "{:?}", bounds);
+        };
+        assert_bounds(0, Vector3::new(0.5, 0.0, -0.5), Vector3::new(1.5, 2.0, 0.5));
+        assert_bounds(
+            1,
+            Vector3::new(0.0, -1.0, -1.0),
+            Vector3::new(1.0, 1.0, 1.0),
+        );
+        assert_bounds(
+            3,
+            Vector3::new(-2.5, -2.5, -0.5),
+            Vector3::new(2.5, 2.5, 0.5),
+        );
+
+        assert!(error_for(&format!(
+            "{}cylinder {{ base 0 0 0 radius 1 height 1 z_range 0 2 material grey }}",
+            CAMERA
+        ))
+        .message
+        .contains("no higher than `height`"));
+        assert!(error_for(&format!(
+            "{}cone {{ base 0 0 0 radius 1 height 1 phi_max 400 material grey }}",
+            CAMERA
+        ))
+        .message
+        .contains("at most 360"));
+        assert!(error_for(&format!(
+            "{}cylinder {{ base 0 0 0 radius 1 height 1 capped 1 material grey }}",
+            CAMERA
+        ))
+        .message
+        .contains("`capped` does not take any values"));
+    }
+
+    #[test]
+    fn combines_solid_shapes() {
+        let scene = parse(&format!(
+            "{}material grey lambertian {{ albedo 0.5 0.5 0.5 }}\n\
+             object ball {{ sphere {{ center 0 0 0 radius 1 material grey }} }}\n\
+             difference {{\n\
+                 box {{ min -1 -1 -1 max 1 1 1 material grey }}\n\
+                 instance {{ object ball scale 1.2 }}\n\
+                 cylinder {{ base 0 -2 0 radius 0.2 height 4 capped material grey }}\n\
+             }}\n",
+            CAMERA
+        ))
+        .unwrap();
+
+        assert_eq!(scene.objects.len(), 1);
+
+        // Only the corners of the box are left, and the hole through them along y is empty
+        let ray = Ray::new(
+            Vector3::new(0.0, 0.0, 5.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            0.0,
+        );
+        assert!(scene.objects[0].check_hit(ray, 0.0, f64::MAX).is_none());
+        let ray = Ray::new(
+            Vector3::new(0.95, 0.95, 5.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            0.0,
+        );
+        assert_eq!(
+            scene.objects[0].check_hit(ray, 0.0, f64::MAX).unwrap().t,
+            4.0
+        );
+
+        let grey = "material grey lambertian { albedo 0.5 0.5 0.5 }\n";
+        assert!(error_for(&format!(
+            "{}{}union {{ sphere {{ center 0 0 0 radius 1 material grey }} }}",
+            CAMERA, grey
+        ))
+        .message
+        .contains("at least two shapes"));
+        let error = error_for(&format!(
+            "{}{}union {{\n\
+                 sphere {{ center 0 0 0 radius 1 material grey }}\n\
+                 cylinder {{ base 0 0 0 radius 1 height 1 material grey }}\n\
+             }}",
+            CAMERA, grey
+        ));
+        assert_eq!(error.line, 5);
+        assert!(error
+            .message
+            .starts_with("only solid shapes can be combined"));
+    }
+
+    #[test]
+    fn parses_meshes() {
+        let scene = parse(&format!(
+            "{}mesh {{\n\
+                 vertices 0 0 0  1 0 0  1 1 0  0 1 0\n\
+                 uvs 0 0  1 0  1 1  0 1\n\
+                 triangles 0 1 2  0 2 3\n\
+                 material lambertian {{ albedo 1 1 1 }}\n\
+             }}\n",
+            CAMERA
+        ))
+        .unwrap();
+
+        assert_eq!(
+            scene.objects[0].bounding_box().unwrap().max,
+            Vector3::new(1.0, 1.0, 0.0)
+        );
+
+        let mesh = |properties: &str| {
+            error_for(&format!(
+                "{}mesh {{ vertices 0 0 0  1 0 0  1 1 0 {} material metal {{ albedo 1 1 1 }} }}",
+                CAMERA, properties
+            ))
+            .message
+        };
+        assert!(mesh("triangles 0 1 3").contains("refers to vertex 3"));
+        assert!(mesh("triangles 0 1").contains("3 vertex indices"));
+        assert!(mesh("triangles 0 1 2 normals 0 0 1").contains("3 vertices but 1 normals"));
+    }
+
+    #[test]
+    fn parses_textures() {
+        let source = "texture tiles checker { even 1 odd checker { even 0 odd 0.5 } scale 2 }\n\
+                      texture grid image { file \"textures/grid.png\" scale 4 }\n\
+                      material ground lambertian { albedo tiles }\n\
+                      material brushed metal {\n\
+                          albedo grid\n\
+                          fuzz image { file \"textures/stripes.png\" linear }\n\
+                      }\n";
+        let mut parser = Parser::new(tokenize(source).unwrap(), Path::new("scenes"));
+
+        while let (Token::Word(word), _) = parser.next() {
+            match word.as_str() {
+                "texture" => parser.parse_texture_declaration().unwrap(),
+                _ => parser.parse_material_declaration().unwrap(),
+            }
+        }
+
+        let grey = |value| Box::new(TextureDescription::grey(value));
+        assert_eq!(
+            parser.materials["ground"],
+            MaterialDescription::Lambertian {
+                albedo: TextureDescription::Checker {
+                    even: grey(1.0),
+                    odd: Box::new(TextureDescription::Checker {
+                        even: grey(0.0),
+                        odd: grey(0.5),
+                        scale: 1.0,
+                    }),
+                    scale: 2.0,
+                }
+            }
+        );
+
+        match &parser.materials["brushed"] {
+            MaterialDescription::Metal {
+                albedo: TextureDescription::Image { image, scale },
+                fuzz: TextureDescription::Image { image: fuzz, .. },
+            } => {
+                assert_eq!((image.width, image.height, *scale), (256, 128, 4.0));
+                // The grid lines are a dark grey in sRGB, but only linear fuzz is left alone
+                assert!(image.pixel(0, 0).x < 0.005);
+                assert_eq!(fuzz.pixel(4, 0).x, 160.0 / 255.0);
+            }
+            other => panic!("expected an image-textured metal, got {:?}", other),
+        }
+
+        let error = error_for("material mud lambertian { albedo dirt }");
+        assert_eq!(error.message, "texture `dirt` has not been declared");
+        assert!(error_for("texture image checker { even 1 odd 0 }")
+            .message
+            .contains("cannot be used as a name"));
+        assert!(error_for(
+            "texture fade gradient { start 0 1 0 end 0 1 0 start_color 1 end_color 0 }"
+        )
+        .message
+        .contains("same point"));
+        assert!(error_for("material mud lambertian { albedo 0.5 0.5 }")
+            .message
+            .contains("expects a color, a texture name or an inline texture"));
+    }
+
+    #[test]
+    fn loads_models_relative_to_the_scene() {
+        let error = super::parse(
+            &format!("{}model {{ file \"models/missing.obj\" }}", CAMERA),
+            Path::new("scenes"),
+        )
+        .err()
+        .unwrap();
+        assert_eq!((error.line, error.column), (2, 14));
+        assert!(error.message.starts_with(&format!(
+            "{}",
+            Path::new("scenes/models/missing.obj").display()
+        )));
+
+        assert!(
+            error_for(&format!("{}model {{ file \"teapot.3ds\" }}", CAMERA))
+                .message
+                .contains("obj, ply, stl")
+        );
+        assert!(error_for(&format!("{}model {{ file teapot }}", CAMERA))
+            .message
+            .contains("double quotes"));
+        assert!(
+            error_for(&format!("{}model {{ file \"teapot.obj }}", CAMERA))
+                .message
+                .contains("closing")
+        );
+    }
+
+    #[test]
+    fn reports_line_and_column() {
+        let error = error_for(&format!("{}sphere {{ center 0 0 radius 1 }}", CAMERA));
+        assert_eq!((error.line, error.column), (2, 10));
+
+        let error = error_for(&format!(
+            "{}\n  sphere {{ center 0 0 0 radius 1 material rock }}",
+            CAMERA
+        ));
+        assert_eq!((error.line, error.column), (3, 43));
+        assert_eq!(error.message, "material `rock` has not been declared");
+    }
+
+    #[test]
+    fn comments_stop_at_quotes() {
+        let tokens = tokenize("file \"textures/#1.png\" # the first one\nradius 1# one\n").unwrap();
+        let tokens: Vec<Token> = tokens.into_iter().map(|(token, _)| token).collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Word(String::from("file")),
+                Token::Text(String::from("textures/#1.png")),
+                Token::Word(String::from("radius")),
+                Token::Number(1.0),
+                Token::End,
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_cameras_that_cannot_see() {
+        let error = error_for("camera { look_from 0 0 0 look_at 0 0 0 vertical_fov 90 }");
+        assert_eq!((error.line, error.column), (1, 26));
+        assert!(error.message.contains("somewhere else than `look_from`"));
+
+        let error =
+            error_for("camera { look_from 0 5 0 look_at 0 0 0 view_up 0 2 0 vertical_fov 90 }");
+        assert_eq!(error.column, 40);
+        assert!(error.message.contains("`view_up` cannot point along"));
+
+        // Looking straight down with the default up
+        let error = error_for("camera { look_from 0 5 0 look_at 0 0 0 vertical_fov 90 }");
+        assert_eq!(error.column, 26);
+
+        for fov in &["0", "180", "-10"] {
+            let source = format!(
+                "camera {{ look_from 0 0 1 look_at 0 0 0 vertical_fov {} }}",
+                fov
+            );
+            let error = error_for(&source);
+            assert_eq!(error.column, 40);
+            assert!(error.message.contains("above 0 and below 180"));
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_scenes() {
+        assert!(error_for("")
+            .message
+            .starts_with("the scene does not declare a camera"));
+        assert_eq!(error_for(&format!("{}{}", CAMERA, CAMERA)).line, 2);
+        assert!(error_for(&format!("{}cube {{}}", CAMERA))
+            .message
+            .contains("found `cube`"));
+        assert!(
+            error_for(&format!("{}sphere {{ center 0 0 0 radius -1 }}", CAMERA))
+                .message
+                .contains("must be positive")
+        );
+        assert!(error_for(&format!(
+            "{}material metal lambertian {{ albedo 1 1 1 }}",
+            CAMERA
+        ))
+        .message
+        .contains("cannot be used as a name"));
+        assert!(error_for("camera { look_from 0 0 1 1e }")
+            .message
+            .contains("invalid number"));
+    }
+}
@@ -0,0 +1,489 @@
+// Stanford PLY meshes, as written by most scanning software.
+//
+// A PLY file starts with a text header that declares elements (usually `vertex` and `face`),
+// how many of each there are, and the properties every one of them has. The elements follow,
+// either as text or as binary numbers in either byte order. Vertices can have normals, colors
+// and uv coordinates besides their position, and faces list the indices of their vertices.
+// Anything else, like other elements or properties, is skipped.
+use crate::image::Transfer;
+use crate::math::Vector3;
+use crate::raytracer::Mesh;
+use std::convert::TryInto;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn from_name(name: &str) -> Option<Scalar> {
+        Some(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    // Colors stored as integers go from 0 to their largest value
+    fn color_scale(self) -> f64 {
+        match self {
+            Scalar::U8 => 255.0,
+            Scalar::U16 => 65535.0,
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Property {
+    Scalar(String, Scalar),
+    // A count, followed by that many items
+    List(String, Scalar, Scalar),
+}
+
+impl Property {
+    fn name(&self) -> &str {
+        match self {
+            Property::Scalar(name, _) | Property::List(name, _, _) => name,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Format {
+    Ascii,
+    LittleEndian,
+    BigEndian,
+}
+
+pub fn parse_ply(bytes: &[u8]) -> Result<Mesh, String> {
+    let (format, elements, body) = parse_header(bytes)?;
+
+    let mut reader = match format {
+        Format::Ascii => Reader::Ascii(
+            std::str::from_utf8(body)
+                .map_err(|_| String::from("the elements of an ASCII PLY file are not text"))?
+                .split_ascii_whitespace(),
+        ),
+        _ => Reader::Binary {
+            bytes: body,
+            big_endian: format == Format::BigEndian,
+        },
+    };
+
+    let mut mesh = Mesh::default();
+
+    for element in &elements {
+        match element.name.as_str() {
+            "vertex" => read_vertices(element, &mut reader, &mut mesh)?,
+            "face" => read_faces(element, &mut reader, &mut mesh)?,
+            _ => {
+                for _ in 0..element.count {
+                    for property in &element.properties {
+                        read_property(property, &mut reader)?;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(mesh)
+}
+
+fn parse_header(bytes: &[u8]) -> Result<(Format, Vec<Element>, &[u8]), String> {
+    if !bytes.starts_with(b"ply") {
+        return Err(String::from("not a PLY file, it does not start with `ply`"));
+    }
+
+    let end = b"end_header";
+    let header_end = bytes
+        .windows(end.len())
+        .position(|window| window == end)
+        .ok_or_else(|| String::from("the header has no `end_header`"))?;
+
+    // The elements start on the line after `end_header`
+    let body_start = match bytes[header_end..].iter().position(|&byte| byte == b'\n') {
+        Some(newline) => header_end + newline + 1,
+        None => bytes.len(),
+    };
+
+    let header = std::str::from_utf8(&bytes[..header_end])
+        .map_err(|_| String::from("the header is not text"))?;
+
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+
+    for (index, line) in header.lines().enumerate().skip(1) {
+        let error = |message: String| format!("header line {}: {}", index + 1, message);
+        let words: Vec<_> = line.split_whitespace().collect();
+
+        match words.as_slice() {
+            [] | ["comment", ..] | ["obj_info", ..] => {}
+            ["format", name, _version] => {
+                format = Some(match *name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::LittleEndian,
+                    "binary_big_endian" => Format::BigEndian,
+                    _ => return Err(error(format!("unknown format `{}`", name))),
+                })
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| error(format!("invalid element count `{}`", count)))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => {
+                let property = Property::List(
+                    name.to_string(),
+                    scalar(count).map_err(error)?,
+                    scalar(item).map_err(error)?,
+                );
+
+                match elements.last_mut() {
+                    Some(element) => element.properties.push(property),
+                    None => return Err(error(String::from("property before any element"))),
+                }
+            }
+            ["property", kind, name] => {
+                let property = Property::Scalar(name.to_string(), scalar(kind).map_err(error)?);
+
+                match elements.last_mut() {
+                    Some(element) => element.properties.push(property),
+                    None => return Err(error(String::from("property before any element"))),
+                }
+            }
+            _ => return Err(error(format!("unexpected `{}`", line.trim()))),
+        }
+    }
+
+    let format = format.ok_or_else(|| String::from("the header has no `format`"))?;
+
+    Ok((format, elements, &bytes[body_start..]))
+}
+
+fn scalar(name: &str) -> Result<Scalar, String> {
+    Scalar::from_name(name).ok_or_else(|| format!("unknown property type `{}`", name))
+}
+
+enum Reader<'a> {
+    Ascii(std::str::SplitAsciiWhitespace<'a>),
+    Binary { bytes: &'a [u8], big_endian: bool },
+}
+
+impl<'a> Reader<'a> {
+    fn read(&mut self, scalar: Scalar) -> Result<f64, String> {
+        match self {
+            Reader::Ascii(words) => {
+                let word = words
+                    .next()
+                    .ok_or_else(|| String::from("the file ends too soon"))?;
+
+                word.parse()
+                    .map_err(|_| format!("invalid number `{}`", word))
+            }
+            Reader::Binary { bytes, big_endian } => {
+                if bytes.len() < scalar.size() {
+                    return Err(String::from("the file ends too soon"));
+                }
+
+                let (value, rest) = bytes.split_at(scalar.size());
+                *bytes = rest;
+
+                macro_rules! convert {
+                    ($type:ty) => {{
+                        let array = value.try_into().expect("sized by `Scalar::size`");
+
+                        if *big_endian {
+                            <$type>::from_be_bytes(array)
+                        } else {
+                            <$type>::from_le_bytes(array)
+                        }
+                    }};
+                }
+
+                Ok(match scalar {
+                    Scalar::I8 => f64::from(convert!(i8)),
+                    Scalar::U8 => f64::from(convert!(u8)),
+                    Scalar::I16 => f64::from(convert!(i16)),
+                    Scalar::U16 => f64::from(convert!(u16)),
+                    Scalar::I32 => f64::from(convert!(i32)),
+                    Scalar::U32 => f64::from(convert!(u32)),
+                    Scalar::F32 => f64::from(convert!(f32)),
+                    Scalar::F64 => convert!(f64),
+                })
+            }
+        }
+    }
+}
+
+// The values of a property: one for scalars, any number for lists
+fn read_property(property: &Property, reader: &mut Reader) -> Result<Vec<f64>, String> {
+    match property {
+        Property::Scalar(_, scalar) => Ok(vec![reader.read(*scalar)?]),
+        Property::List(_, count, item) => {
+            let count = reader.read(*count)?;
+
+            if count < 0.0 || count.fract() != 0.0 {
+                return Err(format!("invalid list length `{}`", count));
+            }
+
+            (0..count as usize).map(|_| reader.read(*item)).collect()
+        }
+    }
+}
+
+fn read_vertices(element: &Element, reader: &mut Reader, mesh: &mut Mesh) -> Result<(), String> {
+    let find = |names: &[&str]| {
+        element
+            .properties
+            .iter()
+            .position(|property| names.contains(&property.name()))
+    };
+    let all = |names: &[&[&str]]| {
+        names
+            .iter()
+            .map(|names| find(names))
+            .collect::<Option<Vec<_>>>()
+    };
+
+    let position = all(&[&["x"], &["y"], &["z"]])
+        .ok_or_else(|| String::from("vertices need `x`, `y` and `z` properties"))?;
+    let normal = all(&[&["nx"], &["ny"], &["nz"]]);
+    let color = all(&[
+        &["red", "r", "diffuse_red"],
+        &["green", "g", "diffuse_green"],
+        &["blue", "b", "diffuse_blue"],
+    ]);
+    let uv = all(&[&["u", "s", "texture_u"], &["v", "t", "texture_v"]]);
+
+    let color_scale = color
+        .as_ref()
+        .map_or(1.0, |color| match &element.properties[color[0]] {
+            Property::Scalar(_, scalar) => scalar.color_scale(),
+            Property::List(_, _, _) => 1.0,
+        });
+
+    for index in 0..element.count {
+        let values = element
+            .properties
+            .iter()
+            .map(|property| {
+                let values = read_property(property, reader)?;
+                Ok(values.first().copied().unwrap_or(0.0))
+            })
+            .collect::<Result<Vec<_>, String>>()
+            .map_err(|error| format!("vertex {}: {}", index, error))?;
+
+        let vector = |indices: &[usize]| {
+            Vector3::new(values[indices[0]], values[indices[1]], values[indices[2]])
+        };
+
+        mesh.positions.push(vector(&position));
+
+        if let Some(normal) = &normal {
+            mesh.normals.push(vector(normal));
+        }
+
+        // Colors are stored for display, like the pixels of an image
+        if let Some(color) = &color {
+            let decode = |value: f64| Transfer::Srgb.decode(value / color_scale);
+            let color = vector(color);
+            mesh.colors.push(Vector3::new(
+                decode(color.x),
+                decode(color.y),
+                decode(color.z),
+            ));
+        }
+
+        if let Some(uv) = &uv {
+            mesh.uvs.push((values[uv[0]], values[uv[1]]));
+        }
+    }
+
+    Ok(())
+}
+
+fn read_faces(element: &Element, reader: &mut Reader, mesh: &mut Mesh) -> Result<(), String> {
+    let indices = element
+        .properties
+        .iter()
+        .position(|property| {
+            matches!(property, Property::List(name, _, _) if name == "vertex_indices" || name == "vertex_index")
+        })
+        .ok_or_else(|| String::from("faces need a `vertex_indices` list"))?;
+
+    for index in 0..element.count {
+        let error = |message: String| format!("face {}: {}", index, message);
+        let mut corners = Vec::new();
+
+        for (property_index, property) in element.properties.iter().enumerate() {
+            let values = read_property(property, reader).map_err(error)?;
+
+            if property_index == indices {
+                corners = values;
+            }
+        }
+
+        if corners.len() < 3 {
+            return Err(error(String::from("faces need at least 3 vertices")));
+        }
+
+        if let Some(corner) = corners
+            .iter()
+            .find(|&&corner| corner < 0.0 || corner > f64::from(u32::MAX))
+        {
+            return Err(error(format!("invalid vertex index `{}`", corner)));
+        }
+
+        // Polygons are split into a fan of triangles
+        for i in 1..corners.len() - 1 {
+            mesh.triangles
+                .push([corners[0] as u32, corners[i] as u32, corners[i + 1] as u32]);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_ascii_files() {
+        let mesh = parse_ply(
+            b"ply\n\
+              format ascii 1.0\n\
+              comment a colored square\n\
+              element vertex 4\n\
+              property float x\n\
+              property float y\n\
+              property float z\n\
+              property uchar red\n\
+              property uchar green\n\
+              property uchar blue\n\
+              element face 1\n\
+              property list uchar int vertex_indices\n\
+              end_header\n\
+              0 0 0 255 0 0\n\
+              1 0 0 255 0 0\n\
+              1 1 0 0 0 255\n\
+              0 1 0 0 0 255\n\
+              4 0 1 2 3\n",
+        )
+        .unwrap();
+
+        assert_eq!(mesh.positions.len(), 4);
+        assert_eq!(mesh.triangles, vec![[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(mesh.colors[0], Vector3::new(1.0, 0.0, 0.0));
+        assert!(mesh.normals.is_empty() && mesh.uvs.is_empty());
+    }
+
+    #[test]
+    fn reads_binary_files_in_either_byte_order() {
+        let header = |format: &str| {
+            format!(
+                "ply\nformat {} 1.0\n\
+                 element vertex 3\n\
+                 property double x\nproperty double y\nproperty double z\n\
+                 property float nx\nproperty float ny\nproperty float nz\n\
+                 element edge 1\n\
+                 property int vertex1\nproperty int vertex2\n\
+                 element face 1\n\
+                 property uchar flags\n\
+                 property list uchar uint vertex_indices\n\
+                 end_header\n",
+                format
+            )
+        };
+        let positions = [[0.0, 0.0, 0.0], [2.0, 0.0, 0.0], [0.0, 2.0, 0.0]];
+
+        for &(format, big_endian) in &[("binary_little_endian", false), ("binary_big_endian", true)]
+        {
+            let mut bytes = header(format).into_bytes();
+            let mut push = |value: &[u8]| {
+                let mut value = value.to_vec();
+                if big_endian {
+                    value.reverse();
+                }
+                bytes.extend(value);
+            };
+
+            for position in &positions {
+                for &coordinate in position {
+                    push(&f64::to_le_bytes(coordinate));
+                }
+                for &coordinate in &[0.0f32, 0.0, 1.0] {
+                    push(&f32::to_le_bytes(coordinate));
+                }
+            }
+
+            // The edge, which is skipped
+            push(&i32::to_le_bytes(0));
+            push(&i32::to_le_bytes(1));
+
+            push(&[7]);
+            push(&[3]);
+            for index in 0..3u32 {
+                push(&u32::to_le_bytes(index));
+            }
+
+            let mesh = parse_ply(&bytes).unwrap();
+            assert_eq!(mesh.positions[1], Vector3::new(2.0, 0.0, 0.0));
+            assert_eq!(mesh.normals[2], Vector3::new(0.0, 0.0, 1.0));
+            assert_eq!(mesh.triangles, vec![[0, 1, 2]]);
+        }
+    }
+
+    #[test]
+    fn reports_malformed_files() {
+        let error = |source: &[u8]| parse_ply(source).err().unwrap();
+
+        assert_eq!(
+            error(b"ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nend_header\n0\n"),
+            "vertices need `x`, `y` and `z` properties"
+        );
+        assert_eq!(
+            error(b"ply\nformat ascii 1.0\nelement vertex 1\nproperty half x\nend_header\n"),
+            "header line 4: unknown property type `half`"
+        );
+        assert_eq!(
+            error(
+                b"ply\nformat ascii 1.0\nelement vertex 2\n\
+                  property float x\nproperty float y\nproperty float z\nend_header\n0 0 0\n1 1\n"
+            ),
+            "vertex 1: the file ends too soon"
+        );
+    }
+}
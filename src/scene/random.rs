@@ -0,0 +1,86 @@
+use crate::math::Vector3;
+use crate::raytracer::material::{Dielectric, Lambertian, Material, Metal};
+use crate::raytracer::{Hitable, Sphere};
+use crate::scene::{CameraSettings, Scene};
+use rand::Rng;
+
+pub fn random_scene<R: Rng>(rng: &mut R) -> Scene {
+    let camera = CameraSettings {
+        look_from: Vector3::new(11.0, 1.8, 3.5),
+        look_at: Vector3::new(-1.0, 0.5, 0.0),
+        view_up: Vector3::new(0.0, 1.0, 0.0),
+        vertical_fov: 20.0,
+        aspect_ratio: None,
+        aperture: 0.1,
+        focus_distance: None,
+        // Nothing moves
+        shutter: (0.0, 0.0),
+    };
+
+    let random_spheres = 20 * 20;
+    let mut spheres: Vec<Box<dyn Hitable + Sync>> = Vec::with_capacity(random_spheres + 1 + 3);
+
+    // First a huge "floor" sphere
+    spheres.push(Box::new(Sphere::new(
+        Vector3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Box::new(Lambertian::new(Vector3::new(0.5, 0.5, 0.5))),
+    )));
+
+    // The three "main" ones
+    spheres.push(Box::new(Sphere::new(
+        Vector3::new(0.0, 1.0, 0.0),
+        1.0,
+        Box::new(Dielectric::glass()),
+    )));
+
+    spheres.push(Box::new(Sphere::new(
+        Vector3::new(-4.0, 1.0, 0.0),
+        1.0,
+        Box::new(Lambertian::new(Vector3::new(0.4, 0.2, 0.1))),
+    )));
+
+    spheres.push(Box::new(Sphere::new(
+        Vector3::new(4.0, 1.0, 0.0),
+        1.0,
+        Box::new(Metal::new(Vector3::new(0.7, 0.6, 0.5), 0.0)),
+    )));
+
+    // And plenty of random, smaller ones
+    for a in -10..10 {
+        for b in -10..10 {
+            let material_odds: f64 = rng.gen();
+            let center = Vector3::new(
+                f64::from(a) + 0.9 * rng.gen::<f64>(),
+                0.2,
+                f64::from(b) + 0.9 * rng.gen::<f64>(),
+            );
+
+            if (center - Vector3::new(4.0, 0.2, 0.0)).magnitude() > 0.9 {
+                let material: Box<dyn Material + Sync> = match material_odds {
+                    p if p < 0.8 => Box::new(Lambertian::new(Vector3::new(
+                        rng.gen::<f64>() * rng.gen::<f64>(),
+                        rng.gen::<f64>() * rng.gen::<f64>(),
+                        rng.gen::<f64>() * rng.gen::<f64>(),
+                    ))),
+                    p if p < 0.95 => Box::new(Metal::new(
+                        Vector3::new(
+                            0.5 * (1.0 + rng.gen::<f64>()),
+                            0.5 * (1.0 + rng.gen::<f64>()),
+                            0.5 * (1.0 + rng.gen::<f64>()),
+                        ),
+                        0.5 * rng.gen::<f64>(),
+                    )),
+                    _ => Box::new(Dielectric::glass()),
+                };
+
+                spheres.push(Box::new(Sphere::new(center, 0.2, material)));
+            }
+        }
+    }
+
+    Scene {
+        camera,
+        objects: spheres,
+    }
+}
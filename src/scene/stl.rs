@@ -0,0 +1,196 @@
+// STL meshes, as exported by CAD software, in either of their two flavors.
+//
+// Binary files have an 80 byte header, the number of triangles, and then 50 bytes per triangle:
+// its normal, its three corners (all as 32 bit floats) and two unused bytes. Text files spell
+// the same out as `facet normal ... outer loop vertex ... endloop endfacet`, between `solid` and
+// `endsolid`. Some binary files also start with `solid`, so a file is only taken as text if its
+// size doesn't match the triangle count it would have as a binary file.
+//
+// Triangles don't share their corners in STL files, so corners at the same position are merged
+// into one vertex. Their normals are flat, from the order of their corners, which are turned
+// around where they disagree with the normal written in the file.
+use crate::math::Vector3;
+use crate::raytracer::Mesh;
+use std::collections::HashMap;
+use std::convert::TryInto;
+
+const HEADER_SIZE: usize = 84;
+const TRIANGLE_SIZE: usize = 50;
+
+pub fn parse_stl(bytes: &[u8]) -> Result<Mesh, String> {
+    let binary_count = bytes
+        .get(80..HEADER_SIZE)
+        .map(|count| u32::from_le_bytes(count.try_into().unwrap()) as usize);
+
+    let triangles = match binary_count {
+        Some(count) if bytes.len() == HEADER_SIZE + count * TRIANGLE_SIZE => {
+            binary_triangles(&bytes[HEADER_SIZE..])
+        }
+        _ if bytes.starts_with(b"solid") => ascii_triangles(
+            std::str::from_utf8(bytes).map_err(|_| String::from("not an STL file"))?,
+        )?,
+        Some(count) => {
+            return Err(format!(
+                "a binary STL file with {} triangles should have {} bytes, not {}",
+                count,
+                HEADER_SIZE + count * TRIANGLE_SIZE,
+                bytes.len()
+            ))
+        }
+        None => return Err(String::from("not an STL file, it is too short")),
+    };
+
+    let mut mesh = Mesh::default();
+    let mut vertices = HashMap::new();
+
+    for (normal, corners) in triangles {
+        let mut indices = corners.map(|corner| {
+            let key = [corner.x.to_bits(), corner.y.to_bits(), corner.z.to_bits()];
+
+            *vertices.entry(key).or_insert_with(|| {
+                mesh.positions.push(corner);
+                (mesh.positions.len() - 1) as u32
+            })
+        });
+
+        let winding = (corners[1] - corners[0]).cross(corners[2] - corners[0]);
+
+        if winding.dot(normal) < 0.0 {
+            indices.swap(1, 2);
+        }
+
+        mesh.triangles.push(indices);
+    }
+
+    Ok(mesh)
+}
+
+// Every triangle's normal, which can be zero, and corners
+type Triangle = (Vector3, [Vector3; 3]);
+
+fn binary_triangles(bytes: &[u8]) -> Vec<Triangle> {
+    bytes
+        .chunks_exact(TRIANGLE_SIZE)
+        .map(|triangle| {
+            let vector = |index: usize| {
+                let float = |offset: usize| {
+                    let start = index * 12 + offset * 4;
+                    f64::from(f32::from_le_bytes(
+                        triangle[start..start + 4].try_into().unwrap(),
+                    ))
+                };
+
+                Vector3::new(float(0), float(1), float(2))
+            };
+
+            (vector(0), [vector(1), vector(2), vector(3)])
+        })
+        .collect()
+}
+
+fn ascii_triangles(source: &str) -> Result<Vec<Triangle>, String> {
+    let mut triangles = Vec::new();
+    let mut normal = Vector3::new(0.0, 0.0, 0.0);
+    let mut corners = Vec::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let error = |message: String| format!("line {}: {}", index + 1, message);
+        let words: Vec<_> = line.split_whitespace().collect();
+
+        let vector = |numbers: &[&str]| -> Result<Vector3, String> {
+            let numbers = numbers
+                .iter()
+                .map(|number| {
+                    number
+                        .parse()
+                        .map_err(|_| error(format!("invalid number `{}`", number)))
+                })
+                .collect::<Result<Vec<f64>, String>>()?;
+
+            match *numbers.as_slice() {
+                [x, y, z] => Ok(Vector3::new(x, y, z)),
+                _ => Err(error(String::from("expected 3 numbers"))),
+            }
+        };
+
+        match words.as_slice() {
+            ["facet", "normal", numbers @ ..] => {
+                normal = vector(numbers)?;
+                corners.clear();
+            }
+            ["vertex", numbers @ ..] => corners.push(vector(numbers)?),
+            ["endfacet"] => match *corners.as_slice() {
+                [a, b, c] => triangles.push((normal, [a, b, c])),
+                _ => {
+                    return Err(error(format!(
+                        "facets need 3 vertices, this one has {}",
+                        corners.len()
+                    )))
+                }
+            },
+            // `solid`, `outer loop` and the ends of every block
+            _ => {}
+        }
+    }
+
+    Ok(triangles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_binary_files_and_merges_corners() {
+        let mut bytes = b"solid but actually binary".to_vec();
+        bytes.resize(80, 0);
+        bytes.extend(&2u32.to_le_bytes());
+
+        // The second triangle's normal points down, so its corners get turned around
+        let triangles: [[f32; 12]; 2] = [
+            [0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0],
+        ];
+
+        for triangle in &triangles {
+            for value in triangle {
+                bytes.extend(&value.to_le_bytes());
+            }
+            bytes.extend(&[0, 0]);
+        }
+
+        let mesh = parse_stl(&bytes).unwrap();
+        assert_eq!(mesh.positions.len(), 4);
+        assert_eq!(mesh.triangles, vec![[0, 1, 2], [0, 2, 3]]);
+    }
+
+    #[test]
+    fn reads_ascii_files() {
+        let mesh = parse_stl(
+            b"solid triangle\n\
+              facet normal 0 0 1\n\
+                outer loop\n\
+                  vertex 0 0 0\n\
+                  vertex 1 0 0\n\
+                  vertex 0 1 0\n\
+                endloop\n\
+              endfacet\n\
+              endsolid triangle\n",
+        )
+        .unwrap();
+
+        assert_eq!(mesh.positions[1], Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(mesh.triangles, vec![[0, 1, 2]]);
+
+        assert_eq!(
+            parse_stl(b"solid\nfacet normal 0 0 1\nvertex 0 0 0\nvertex 1 0\n")
+                .err()
+                .unwrap(),
+            "line 4: expected 3 numbers"
+        );
+        assert!(parse_stl(&[0; 90])
+            .err()
+            .unwrap()
+            .contains("should have 84 bytes"));
+    }
+}